@@ -272,7 +272,7 @@ export class PluginBusService
         if (response.success) {
           pending.resolve(response.data ?? null);
         } else {
-          pending.reject(new Error(response.error ?? 'Unknown error'));
+          pending.reject(toPluginError(response.error));
         }
       }
     } catch (error) {
@@ -292,3 +292,14 @@ export class PluginBusService
     }
   }
 }
+
+/** Builds the rejection for a failed response; structured errors keep their `code`. */
+function toPluginError(error: PluginResponseMessage['error']): Error & { code?: string } {
+  if (typeof error === 'string') {
+    return new Error(error);
+  }
+  if (error) {
+    return Object.assign(new Error(error.message), { code: error.code });
+  }
+  return new Error('Unknown error');
+}
@@ -93,12 +93,19 @@ export interface PluginRequestMessage {
   token?: string;
 }
 
+/** Structured error of a plugin response, e.g. `{ code: 'not_found', message: '...' }`. */
+export interface PluginErrorDetail {
+  code: string;
+  message: string;
+}
+
 export interface PluginResponseMessage {
   type: 'response';
   requestId: string;
   success: boolean;
   data?: unknown;
-  error?: string;
+  /** Older plugins send a plain string. */
+  error?: string | PluginErrorDetail;
 }
 
 export type PluginBusMessage =
@@ -0,0 +1,93 @@
+use brainml::core::distance::Kernel;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/// Common embedding sizes.
+const DIMS: [usize; 3] = [384, 768, 1536];
+
+type F32Op = fn(Kernel, &[f32], &[f32]) -> f32;
+type I8Op = fn(Kernel, &[i8], &[i8]) -> f32;
+
+fn floats(dims: usize, seed: u32) -> Vec<f32> {
+    (0..dims as u32)
+        .map(|idx| ((idx.wrapping_mul(2654435761) ^ seed) % 2001) as f32 / 1000.0 - 1.0)
+        .collect()
+}
+
+fn bytes(dims: usize, seed: u32) -> Vec<i8> {
+    (0..dims as u32)
+        .map(|idx| (idx.wrapping_mul(2654435761) ^ seed) as i8)
+        .collect()
+}
+
+fn f32_kernels(c: &mut Criterion) {
+    let ops: [(&str, F32Op); 3] = [
+        ("dot", Kernel::dot),
+        ("cosine", Kernel::cosine),
+        ("l2_squared", Kernel::l2_squared),
+    ];
+    for (name, op) in ops {
+        let mut group = c.benchmark_group(format!("f32/{name}"));
+        for dims in DIMS {
+            let (a, b) = (floats(dims, 1), floats(dims, 2));
+            group.throughput(Throughput::Elements(dims as u64));
+            for kernel in Kernel::available() {
+                group.bench_with_input(
+                    BenchmarkId::new(kernel.to_string(), dims),
+                    &dims,
+                    |bench, _| bench.iter(|| op(kernel, black_box(&a), black_box(&b))),
+                );
+            }
+        }
+        group.finish();
+    }
+}
+
+fn int8_kernels(c: &mut Criterion) {
+    let ops: [(&str, I8Op); 3] = [
+        ("dot", |kernel, a, b| kernel.dot_i8(a, b) as f32),
+        ("cosine", Kernel::cosine_i8),
+        ("l2_squared", |kernel, a, b| {
+            kernel.l2_squared_i8(a, b) as f32
+        }),
+    ];
+    for (name, op) in ops {
+        let mut group = c.benchmark_group(format!("i8/{name}"));
+        for dims in DIMS {
+            let (a, b) = (bytes(dims, 1), bytes(dims, 2));
+            group.throughput(Throughput::Elements(dims as u64));
+            for kernel in Kernel::available() {
+                group.bench_with_input(
+                    BenchmarkId::new(kernel.to_string(), dims),
+                    &dims,
+                    |bench, _| bench.iter(|| op(kernel, black_box(&a), black_box(&b))),
+                );
+            }
+        }
+        group.finish();
+    }
+}
+
+/// Scoring a full precision query against int8 quantized codes.
+fn asymmetric_kernels(c: &mut Criterion) {
+    let mut group = c.benchmark_group("u8_affine/dot");
+    for dims in DIMS {
+        let query = floats(dims, 1);
+        let codes: Vec<u8> = bytes(dims, 2).into_iter().map(|b| b as u8).collect();
+        group.throughput(Throughput::Elements(dims as u64));
+        for kernel in Kernel::available() {
+            group.bench_with_input(
+                BenchmarkId::new(kernel.to_string(), dims),
+                &dims,
+                |bench, _| {
+                    bench.iter(|| {
+                        kernel.dot_u8_affine(black_box(&query), black_box(&codes), -1.0, 0.0078)
+                    })
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, f32_kernels, int8_kernels, asymmetric_kernels);
+criterion_main!(benches);
@@ -6,8 +6,13 @@ use tokio::sync::RwLock;
 use tracing::{instrument, warn};
 use uuid::Uuid;
 
-use crate::core::bus::OutboundCommand;
-use crate::core::schema::{DocumentRecord, QueryFilter, QueryResult, QueryStrategy};
+use crate::core::bus::{OutboundCommand, PendingInvoke, INVOKE_TIMEOUT};
+use crate::core::config::QuantizationSettings;
+use crate::core::distance::dot;
+use crate::core::errors::{ErrorCode, ErrorDetail};
+use crate::core::quantization::{full_precision_bytes, Quantization, QuantizedVector};
+use crate::core::schema::{DateRange, DocumentRecord, QueryFilter, QueryResult, QueryStrategy};
+use crate::util::trace_context;
 
 #[derive(Debug, Error)]
 pub enum BraindbError {
@@ -17,6 +22,35 @@ pub enum BraindbError {
     Request(String),
     #[error("unexpected response: {0}")]
     Response(String),
+    #[error("not found: {0}")]
+    NotFound(String),
+    #[error("timed out: {0}")]
+    Timeout(String),
+    #[error("storage error: {0}")]
+    Storage(String),
+}
+
+impl BraindbError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            BraindbError::Connection(_) => ErrorCode::Unavailable,
+            BraindbError::Request(_) | BraindbError::Response(_) => ErrorCode::UpstreamError,
+            BraindbError::NotFound(_) => ErrorCode::NotFound,
+            BraindbError::Timeout(_) => ErrorCode::Timeout,
+            BraindbError::Storage(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+impl From<ErrorDetail> for BraindbError {
+    fn from(error: ErrorDetail) -> Self {
+        match error.code {
+            ErrorCode::NotFound => BraindbError::NotFound(error.message),
+            ErrorCode::Unavailable => BraindbError::Connection(error.message),
+            ErrorCode::Timeout => BraindbError::Timeout(error.message),
+            _ => BraindbError::Request(error.message),
+        }
+    }
 }
 
 pub type BraindbResult<T> = Result<T, BraindbError>;
@@ -41,6 +75,27 @@ pub struct HybridQueryRequest {
     pub top_k: usize,
     pub strategy: QueryStrategy,
     pub filters: Vec<QueryFilter>,
+    #[serde(default)]
+    pub vector_field: Option<String>,
+    /// Only records embedded by this model contribute a vector score.
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+    /// Records outside the range are dropped before `top_k` is applied.
+    #[serde(default)]
+    pub date_range: Option<DateRange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteDocumentsRequest {
+    pub collection: String,
+    pub ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanDocumentsRequest {
+    pub collection: String,
+    pub offset: usize,
+    pub limit: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,40 +108,191 @@ pub struct CollectionStats {
     pub name: String,
     pub document_count: usize,
     pub embedding_dimensions: Option<usize>,
+    #[serde(default)]
+    pub quantization: Quantization,
+    /// Bytes held by default embeddings: codes plus any kept originals.
+    #[serde(default)]
+    pub vector_bytes: u64,
+    /// Estimated bytes held by the collection's documents and vectors.
+    #[serde(default)]
+    pub memory_bytes: u64,
 }
 
 #[async_trait]
 pub trait BraindbClient: Send + Sync {
     async fn create_collection(&self, request: CreateCollectionRequest) -> BraindbResult<()>;
     async fn upsert_documents(&self, request: UpsertDocumentsRequest) -> BraindbResult<()>;
+    async fn delete_documents(&self, request: DeleteDocumentsRequest) -> BraindbResult<()>;
     async fn hybrid_query(&self, request: HybridQueryRequest) -> BraindbResult<Vec<QueryResult>>;
+    async fn scan_documents(
+        &self,
+        request: ScanDocumentsRequest,
+    ) -> BraindbResult<Vec<DocumentRecord>>;
     async fn stats(&self) -> BraindbResult<StatsResponse>;
 }
 
+/// In-process store. The default embedding is kept as configured by the
+/// collection's quantization settings; named vectors stay full precision.
 #[derive(Clone, Default)]
 pub struct NullBraindbClient {
-    state: Arc<RwLock<indexmap::IndexMap<String, Vec<DocumentRecord>>>>,
+    state: Arc<RwLock<indexmap::IndexMap<String, StoredCollection>>>,
+}
+
+#[derive(Default)]
+struct StoredCollection {
+    settings: QuantizationSettings,
+    documents: Vec<StoredDocument>,
+}
+
+impl StoredCollection {
+    /// Applies new settings, re-encoding stored embeddings from their
+    /// originals when kept and from their codes otherwise.
+    fn reconfigure(&mut self, settings: QuantizationSettings) {
+        if settings == self.settings {
+            return;
+        }
+        let documents = std::mem::take(&mut self.documents);
+        self.documents = documents
+            .into_iter()
+            .map(|doc| StoredDocument::new(doc.record(), &settings))
+            .collect();
+        self.settings = settings;
+    }
+
+    /// Replacing a record keeps the `created_at` of the one it replaces.
+    fn upsert(&mut self, mut record: DocumentRecord) {
+        if let Some(existing) = self
+            .documents
+            .iter_mut()
+            .find(|doc| doc.record.id == record.id)
+        {
+            record.created_at = existing.record.created_at;
+            *existing = StoredDocument::new(record, &self.settings);
+        } else {
+            self.documents.push(StoredDocument::new(record, &self.settings));
+        }
+    }
+}
+
+/// A record whose embedding has been moved into `quantized`, and into
+/// `original` as well when rescoring is enabled.
+struct StoredDocument {
+    record: DocumentRecord,
+    quantized: Option<QuantizedVector>,
+    original: Option<Vec<f32>>,
+}
+
+impl StoredDocument {
+    fn new(mut record: DocumentRecord, settings: &QuantizationSettings) -> Self {
+        let quantized = record
+            .embedding
+            .as_deref()
+            .and_then(|embedding| QuantizedVector::encode(settings.kind, embedding));
+        let original = if quantized.is_some() {
+            record.embedding.take().filter(|_| settings.rescore)
+        } else {
+            None
+        };
+        Self {
+            record,
+            quantized,
+            original,
+        }
+    }
+
+    /// The record as indexed, or with a decoded embedding when the original
+    /// was not kept.
+    fn record(&self) -> DocumentRecord {
+        let mut record = self.record.clone();
+        if let Some(quantized) = &self.quantized {
+            record.embedding = Some(self.original.clone().unwrap_or_else(|| quantized.decode()));
+        }
+        record
+    }
+
+    fn dimensions(&self) -> Option<usize> {
+        match &self.quantized {
+            Some(quantized) => Some(quantized.dimensions()),
+            None => self.record.embedding.as_ref().map(Vec::len),
+        }
+    }
+
+    /// Dot product with the stored embedding, approximate when quantized.
+    fn approximate_dot(&self, vector: &[f32]) -> Option<f32> {
+        match &self.quantized {
+            Some(quantized) => Some(quantized.dot(vector)),
+            None => self.record.embedding.as_deref().map(|e| dot(e, vector)),
+        }
+    }
+
+    fn exact_dot(&self, vector: &[f32]) -> Option<f32> {
+        match &self.original {
+            Some(original) => Some(dot(original, vector)),
+            None => self.approximate_dot(vector),
+        }
+    }
+
+    fn vector_bytes(&self) -> usize {
+        let full = |values: &Vec<f32>| full_precision_bytes(values.len());
+        self.quantized
+            .as_ref()
+            .map_or(0, QuantizedVector::size_bytes)
+            + self.original.as_ref().map_or(0, full)
+            + self.record.embedding.as_ref().map_or(0, full)
+    }
+
+    fn memory_bytes(&self) -> usize {
+        let record = &self.record;
+        let named: usize = record
+            .vectors
+            .iter()
+            .map(|(name, named)| {
+                name.len() + named.model.len() + full_precision_bytes(named.values.len())
+            })
+            .sum();
+        record.id.len()
+            + record.text.len()
+            + record.metadata.to_string().len()
+            + record.embedding_model.as_ref().map_or(0, String::len)
+            + named
+            + self.vector_bytes()
+    }
 }
 
 #[async_trait]
 impl BraindbClient for NullBraindbClient {
     #[instrument(skip_all, fields(collection = %request.collection))]
     async fn create_collection(&self, request: CreateCollectionRequest) -> BraindbResult<()> {
+        let settings = match request.schema.get("quantization") {
+            Some(value) => serde_json::from_value(value.clone())
+                .map_err(|err| BraindbError::Request(format!("invalid quantization: {err}")))?,
+            None => QuantizationSettings::default(),
+        };
         let mut state = self.state.write().await;
-        state.entry(request.collection).or_default();
+        state
+            .entry(request.collection)
+            .or_default()
+            .reconfigure(settings);
         Ok(())
     }
 
     #[instrument(skip_all, fields(collection = %request.collection, count = request.documents.len()))]
     async fn upsert_documents(&self, request: UpsertDocumentsRequest) -> BraindbResult<()> {
         let mut state = self.state.write().await;
-        let docs = state.entry(request.collection).or_default();
+        let collection = state.entry(request.collection).or_default();
         for record in request.documents {
-            if let Some(existing) = docs.iter_mut().find(|doc| doc.id == record.id) {
-                *existing = record;
-            } else {
-                docs.push(record);
-            }
+            collection.upsert(record);
+        }
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(collection = %request.collection, count = request.ids.len()))]
+    async fn delete_documents(&self, request: DeleteDocumentsRequest) -> BraindbResult<()> {
+        let mut state = self.state.write().await;
+        if let Some(collection) = state.get_mut(&request.collection) {
+            collection
+                .documents
+                .retain(|doc| !request.ids.contains(&doc.record.id));
         }
         Ok(())
     }
@@ -94,62 +300,119 @@ impl BraindbClient for NullBraindbClient {
     #[instrument(skip_all, fields(collection = %request.collection, top_k = request.top_k))]
     async fn hybrid_query(&self, request: HybridQueryRequest) -> BraindbResult<Vec<QueryResult>> {
         let state = self.state.read().await;
-        let Some(docs) = state.get(&request.collection) else {
+        let Some(collection) = state.get(&request.collection) else {
             return Ok(vec![]);
         };
-        let mut results = Vec::new();
-        for doc in docs {
-            let mut score = 0.0;
+        let settings = &collection.settings;
+        // Quantized scores are approximate; with rescoring the best
+        // `top_k * oversampling` candidates are scored again exactly.
+        let rescore = settings.kind != Quantization::None
+            && settings.rescore
+            && request.vector.is_some()
+            && request.vector_field.is_none();
+        let mut candidates = Vec::new();
+        for doc in &collection.documents {
+            if let Some(range) = &request.date_range {
+                if !range.contains(&doc.record) {
+                    continue;
+                }
+            }
+            let mut text_score = 0.0;
             if let Some(ref query) = request.query {
-                if doc.text.to_lowercase().contains(&query.to_lowercase()) {
-                    score += 0.5;
+                if doc
+                    .record
+                    .text
+                    .to_lowercase()
+                    .contains(&query.to_lowercase())
+                {
+                    text_score += 0.5;
                 }
             }
+            let mut vector_score = 0.0;
             if let Some(ref vector) = request.vector {
-                if let Some(embedding) = &doc.embedding {
-                    let dot: f32 = embedding
-                        .iter()
-                        .zip(vector.iter())
-                        .map(|(a, b)| a * b)
-                        .sum();
-                    score += dot;
+                vector_score = match &request.vector_field {
+                    Some(field) => doc
+                        .record
+                        .vectors
+                        .get(field)
+                        .filter(|named| Some(&named.model) == request.embedding_model.as_ref())
+                        .map(|named| dot(&named.values, vector)),
+                    None if doc.record.embedding_model == request.embedding_model => {
+                        doc.approximate_dot(vector)
+                    }
+                    None => None,
                 }
+                .unwrap_or(0.0);
             }
-            if score > 0.0 {
-                results.push(QueryResult {
-                    id: doc.id.clone(),
-                    score,
-                    document: doc.clone(),
-                    rank: 0,
-                });
+            let score = text_score + vector_score;
+            if rescore || score > 0.0 {
+                candidates.push((doc, text_score, score));
             }
         }
-        results.sort_by(|a, b| {
-            b.score
-                .partial_cmp(&a.score)
+        let by_score = |a: &(&StoredDocument, f32, f32), b: &(&StoredDocument, f32, f32)| {
+            b.2.partial_cmp(&a.2)
                 .unwrap_or(std::cmp::Ordering::Equal)
-        });
-        results.truncate(request.top_k);
-        for (idx, item) in results.iter_mut().enumerate() {
-            item.rank = idx + 1;
+                .then_with(|| a.0.record.id.cmp(&b.0.record.id))
+        };
+        if rescore {
+            let vector = request.vector.as_deref().unwrap_or_default();
+            candidates.sort_by(by_score);
+            candidates.truncate(request.top_k.saturating_mul(settings.oversampling));
+            for (doc, text_score, score) in candidates.iter_mut() {
+                if doc.record.embedding_model == request.embedding_model {
+                    *score = *text_score + doc.exact_dot(vector).unwrap_or(0.0);
+                }
+            }
+            candidates.retain(|(_, _, score)| *score > 0.0);
         }
-        Ok(results)
+        candidates.sort_by(by_score);
+        candidates.truncate(request.top_k);
+        Ok(candidates
+            .into_iter()
+            .enumerate()
+            .map(|(idx, (doc, _, score))| QueryResult {
+                id: doc.record.id.clone(),
+                score,
+                document: doc.record(),
+                rank: idx + 1,
+                snippets: Vec::new(),
+            })
+            .collect())
+    }
+
+    #[instrument(skip_all, fields(collection = %request.collection, offset = request.offset))]
+    async fn scan_documents(
+        &self,
+        request: ScanDocumentsRequest,
+    ) -> BraindbResult<Vec<DocumentRecord>> {
+        let state = self.state.read().await;
+        Ok(state
+            .get(&request.collection)
+            .map(|collection| {
+                collection
+                    .documents
+                    .iter()
+                    .skip(request.offset)
+                    .take(request.limit)
+                    .map(StoredDocument::record)
+                    .collect()
+            })
+            .unwrap_or_default())
     }
 
     #[instrument(skip_all)]
     async fn stats(&self) -> BraindbResult<StatsResponse> {
         let state = self.state.read().await;
         let mut collections = Vec::new();
-        for (name, docs) in state.iter() {
-            let embedding_dimensions = docs
-                .iter()
-                .filter_map(|doc| doc.embedding.as_ref())
-                .map(|embedding| embedding.len())
-                .next();
+        for (name, collection) in state.iter() {
+            let docs = &collection.documents;
             collections.push(CollectionStats {
                 name: name.clone(),
                 document_count: docs.len(),
-                embedding_dimensions,
+                embedding_dimensions: docs.iter().find_map(StoredDocument::dimensions),
+                quantization: collection.settings.kind,
+                vector_bytes: docs.iter().map(StoredDocument::vector_bytes).sum::<usize>() as u64,
+                memory_bytes: docs.iter().map(StoredDocument::memory_bytes).sum::<usize>() as u64,
             });
         }
         Ok(StatsResponse { collections })
@@ -179,6 +442,13 @@ impl BraindbClient for PluginBusBraindbClient {
         self.invoke("db.upsert", payload).await.map(|_| ())
     }
 
+    #[instrument(skip_all, fields(collection = %request.collection, count = request.ids.len()))]
+    async fn delete_documents(&self, request: DeleteDocumentsRequest) -> BraindbResult<()> {
+        let payload = serde_json::to_value(&request)
+            .map_err(|err| BraindbError::Request(format!("serialization error: {err}")))?;
+        self.invoke("db.delete", payload).await.map(|_| ())
+    }
+
     #[instrument(skip_all)]
     async fn hybrid_query(&self, request: HybridQueryRequest) -> BraindbResult<Vec<QueryResult>> {
         let payload = serde_json::to_value(&request)
@@ -187,6 +457,17 @@ impl BraindbClient for PluginBusBraindbClient {
         serde_json::from_value(value).map_err(|err| BraindbError::Response(format!("{err}")))
     }
 
+    #[instrument(skip_all, fields(collection = %request.collection, offset = request.offset))]
+    async fn scan_documents(
+        &self,
+        request: ScanDocumentsRequest,
+    ) -> BraindbResult<Vec<DocumentRecord>> {
+        let payload = serde_json::to_value(&request)
+            .map_err(|err| BraindbError::Request(format!("serialization error: {err}")))?;
+        let value = self.invoke("db.scan", payload).await?;
+        serde_json::from_value(value).map_err(|err| BraindbError::Response(format!("{err}")))
+    }
+
     #[instrument(skip_all)]
     async fn stats(&self) -> BraindbResult<StatsResponse> {
         let value = self.invoke("db.stats", serde_json::Value::Null).await?;
@@ -199,6 +480,7 @@ impl PluginBusBraindbClient {
         Self { sender }
     }
 
+    #[instrument(skip_all, fields(otel.name = %capability, otel.kind = "client", %capability))]
     async fn invoke(
         &self,
         capability: &str,
@@ -211,19 +493,23 @@ impl PluginBusBraindbClient {
             capability: capability.to_string(),
             payload,
             responder: resp_tx,
+            traceparent: trace_context::current_traceparent(),
         };
+        let pending = PendingInvoke::new(request_id, self.sender.clone());
         self.sender
             .send(cmd)
             .await
             .map_err(|err| BraindbError::Connection(format!("{err}")))?;
-        let result = resp_rx
+        let result = tokio::time::timeout(INVOKE_TIMEOUT, resp_rx)
             .await
-            .map_err(|err| BraindbError::Connection(format!("{err}")))?;
+            .map_err(|_| BraindbError::Timeout(capability.to_string()))?;
+        pending.settle();
+        let result = result.map_err(|err| BraindbError::Connection(format!("{err}")))?;
         match result {
             Ok(value) => Ok(value),
             Err(err) => {
                 warn!(%capability, error = %err, "braindb invocation failed");
-                Err(BraindbError::Request(err))
+                Err(err.into())
             }
         }
     }
@@ -4,7 +4,9 @@ use thiserror::Error;
 use tracing::{instrument, warn};
 use uuid::Uuid;
 
-use crate::core::bus::OutboundCommand;
+use crate::core::bus::{OutboundCommand, PendingInvoke, INVOKE_TIMEOUT};
+use crate::core::errors::{ErrorCode, ErrorDetail};
+use crate::util::trace_context;
 
 #[derive(Debug, Error)]
 pub enum LlmError {
@@ -14,6 +16,32 @@ pub enum LlmError {
     Request(String),
     #[error("response error: {0}")]
     Response(String),
+    #[error("unsupported: {0}")]
+    Unsupported(String),
+    #[error("timed out: {0}")]
+    Timeout(String),
+}
+
+impl LlmError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            LlmError::Connection(_) => ErrorCode::Unavailable,
+            LlmError::Request(_) | LlmError::Response(_) => ErrorCode::UpstreamError,
+            LlmError::Unsupported(_) => ErrorCode::Unsupported,
+            LlmError::Timeout(_) => ErrorCode::Timeout,
+        }
+    }
+}
+
+impl From<ErrorDetail> for LlmError {
+    fn from(error: ErrorDetail) -> Self {
+        match error.code {
+            ErrorCode::Unavailable => LlmError::Connection(error.message),
+            ErrorCode::Unsupported => LlmError::Unsupported(error.message),
+            ErrorCode::Timeout => LlmError::Timeout(error.message),
+            _ => LlmError::Request(error.message),
+        }
+    }
 }
 
 pub type LlmResult<T> = Result<T, LlmError>;
@@ -29,9 +57,45 @@ pub struct EmbeddingVector {
     pub embedding: Vec<f32>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatRequest {
+    pub messages: Vec<ChatMessage>,
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatChoice {
+    pub message: ChatMessage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatResponse {
+    pub choices: Vec<ChatChoice>,
+}
+
+impl ChatResponse {
+    /// Content of the first choice.
+    pub fn content(&self) -> Option<&str> {
+        self.choices
+            .first()
+            .map(|choice| choice.message.content.as_str())
+    }
+}
+
 #[async_trait]
 pub trait LlmClient: Send + Sync {
     async fn embed(&self, request: EmbeddingRequest) -> LlmResult<Vec<EmbeddingVector>>;
+
+    /// Chat completion. Clients without a chat model keep this default.
+    async fn chat(&self, _request: ChatRequest) -> LlmResult<ChatResponse> {
+        Err(LlmError::Unsupported("chat".into()))
+    }
 }
 
 #[derive(Clone)]
@@ -55,6 +119,14 @@ impl LlmClient for PluginBusLlmClient {
             serde_json::from_value(response).map_err(|err| LlmError::Response(format!("{err}")))?;
         Ok(vectors)
     }
+
+    #[instrument(skip_all, fields(messages = request.messages.len()))]
+    async fn chat(&self, request: ChatRequest) -> LlmResult<ChatResponse> {
+        let payload = serde_json::to_value(&request)
+            .map_err(|err| LlmError::Request(format!("serialization error: {err}")))?;
+        let response = self.invoke("llm.chat", payload).await?;
+        serde_json::from_value(response).map_err(|err| LlmError::Response(format!("{err}")))
+    }
 }
 
 impl PluginBusLlmClient {
@@ -62,6 +134,7 @@ impl PluginBusLlmClient {
         Self { sender }
     }
 
+    #[instrument(skip_all, fields(otel.name = %capability, otel.kind = "client", %capability))]
     async fn invoke(
         &self,
         capability: &str,
@@ -74,19 +147,22 @@ impl PluginBusLlmClient {
             capability: capability.to_string(),
             payload,
             responder: resp_tx,
+            traceparent: trace_context::current_traceparent(),
         };
+        let pending = PendingInvoke::new(request_id, self.sender.clone());
         self.sender
             .send(cmd)
             .await
             .map_err(|err| LlmError::Connection(format!("{err}")))?;
-        match resp_rx
+        let result = tokio::time::timeout(INVOKE_TIMEOUT, resp_rx)
             .await
-            .map_err(|err| LlmError::Connection(format!("{err}")))?
-        {
+            .map_err(|_| LlmError::Timeout(capability.to_string()))?;
+        pending.settle();
+        match result.map_err(|err| LlmError::Connection(format!("{err}")))? {
             Ok(value) => Ok(value),
             Err(err) => {
                 warn!(%capability, error = %err, "llm invocation failed");
-                Err(LlmError::Request(err))
+                Err(err.into())
             }
         }
     }
@@ -99,11 +175,16 @@ pub struct NullLlmClient;
 impl LlmClient for NullLlmClient {
     #[instrument(skip_all)]
     async fn embed(&self, request: EmbeddingRequest) -> LlmResult<Vec<EmbeddingVector>> {
+        let model = request.model;
         Ok(request
             .input
             .into_iter()
             .map(|text| {
-                let hash = crate::util::id::hash_to_floats(&text, 1536);
+                let seed = match &model {
+                    Some(model) => format!("{model}:{text}"),
+                    None => text,
+                };
+                let hash = crate::util::id::hash_to_floats(&seed, 1536);
                 EmbeddingVector { embedding: hash }
             })
             .collect())
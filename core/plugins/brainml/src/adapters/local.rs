@@ -0,0 +1,355 @@
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tracing::{info, instrument, warn};
+
+use crate::adapters::braindb::{
+    BraindbClient, BraindbError, BraindbResult, CreateCollectionRequest, DeleteDocumentsRequest,
+    HybridQueryRequest, NullBraindbClient, ScanDocumentsRequest, StatsResponse,
+    UpsertDocumentsRequest,
+};
+use crate::core::config::StorageConfig;
+use crate::core::schema::{DocumentRecord, QueryResult};
+use crate::core::wal::{FsyncPolicy, Wal, WalOp};
+
+const WAL_FILE: &str = "wal.log";
+const COLLECTIONS_DIR: &str = "collections";
+
+/// One collection as of the last checkpoint.
+#[derive(Debug, Serialize, Deserialize)]
+struct DataFile {
+    collection: String,
+    schema: serde_json::Value,
+    documents: Vec<DocumentRecord>,
+}
+
+/// What was recovered when the store was opened.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RecoveryReport {
+    pub collections: usize,
+    /// Log entries replayed on top of the data files.
+    pub replayed: usize,
+    /// Bytes of a torn or corrupt log tail that were dropped.
+    pub truncated_bytes: u64,
+}
+
+/// Collections kept in this process and persisted under a directory. Every
+/// mutation is appended to a write-ahead log before it is applied in
+/// memory; once the log passes `checkpoint_bytes` the collections are
+/// written to one data file each and the log is emptied. Opening the store
+/// loads the data files and replays the log, so a crash at any point loses
+/// at most the write that was in flight.
+#[derive(Clone)]
+pub struct LocalBraindbClient {
+    inner: Arc<LocalStore>,
+}
+
+struct LocalStore {
+    memory: NullBraindbClient,
+    dir: PathBuf,
+    wal: Arc<Mutex<Wal>>,
+    /// Last schema per collection. Held across logging and applying so the
+    /// log order is the apply order.
+    schemas: tokio::sync::Mutex<BTreeMap<String, serde_json::Value>>,
+    checkpoint_bytes: u64,
+    max_entry_bytes: usize,
+    recovery: RecoveryReport,
+}
+
+fn storage(err: impl std::fmt::Display) -> BraindbError {
+    BraindbError::Storage(err.to_string())
+}
+
+impl LocalBraindbClient {
+    #[instrument(skip_all, fields(path = %config.path.display()))]
+    pub async fn open(config: &StorageConfig) -> BraindbResult<Self> {
+        let dir = config.path.clone();
+        let policy = config.fsync;
+        let (data_files, wal, recovery) = tokio::task::spawn_blocking(move || {
+            std::fs::create_dir_all(dir.join(COLLECTIONS_DIR))?;
+            let data_files = read_data_files(&dir.join(COLLECTIONS_DIR))?;
+            let (wal, recovery) = Wal::open(&dir.join(WAL_FILE), policy)?;
+            Ok::<_, anyhow::Error>((data_files, wal, recovery))
+        })
+        .await
+        .map_err(storage)?
+        .map_err(storage)?;
+
+        let memory = NullBraindbClient::default();
+        let mut schemas = BTreeMap::new();
+        let report = RecoveryReport {
+            collections: data_files.len(),
+            replayed: recovery.entries.len(),
+            truncated_bytes: recovery.truncated_bytes,
+        };
+        for file in data_files {
+            apply(
+                &memory,
+                &mut schemas,
+                WalOp::CreateCollection(CreateCollectionRequest {
+                    collection: file.collection.clone(),
+                    schema: file.schema,
+                }),
+            )
+            .await?;
+            apply(
+                &memory,
+                &mut schemas,
+                WalOp::Upsert(UpsertDocumentsRequest {
+                    collection: file.collection,
+                    documents: file.documents,
+                }),
+            )
+            .await?;
+        }
+        for op in recovery.entries {
+            // Entries are logged before they are applied, so one that was
+            // rejected when first applied is rejected again here.
+            if let Err(err) = apply(&memory, &mut schemas, op).await {
+                warn!(error = %err, "skipping write-ahead log entry");
+            }
+        }
+        info!(
+            collections = report.collections,
+            replayed = report.replayed,
+            truncated_bytes = report.truncated_bytes,
+            "local storage opened"
+        );
+
+        let client = Self {
+            inner: Arc::new(LocalStore {
+                memory,
+                dir: config.path.clone(),
+                wal: Arc::new(Mutex::new(wal)),
+                schemas: tokio::sync::Mutex::new(schemas),
+                checkpoint_bytes: config.checkpoint_bytes,
+                max_entry_bytes: config.max_entry_bytes,
+                recovery: report,
+            }),
+        };
+        if report.replayed > 0 {
+            client.checkpoint().await?;
+        }
+        if policy == FsyncPolicy::Interval {
+            spawn_flusher(
+                Arc::downgrade(&client.inner.wal),
+                Duration::from_millis(config.fsync_interval_ms),
+            );
+        }
+        Ok(client)
+    }
+
+    pub fn recovery(&self) -> RecoveryReport {
+        self.inner.recovery
+    }
+
+    /// Current size of the write-ahead log.
+    pub fn wal_bytes(&self) -> u64 {
+        self.inner.wal.lock().len()
+    }
+
+    /// Writes every collection to its data file and empties the log.
+    pub async fn checkpoint(&self) -> BraindbResult<()> {
+        let schemas = self.inner.schemas.lock().await;
+        self.inner.checkpoint(&schemas).await
+    }
+
+    /// Fault injection for crash tests, see [`Wal::fail_after_bytes`].
+    #[cfg(any(test, feature = "fault-injection"))]
+    pub fn fail_after_wal_bytes(&self, bytes: u64) {
+        self.inner.wal.lock().fail_after_bytes(bytes);
+    }
+
+    async fn write(&self, op: WalOp) -> BraindbResult<()> {
+        let mut schemas = self.inner.schemas.lock().await;
+        let frame = op.encode(self.inner.max_entry_bytes).map_err(storage)?;
+        let wal = self.inner.wal.clone();
+        let wal_bytes = tokio::task::spawn_blocking(move || wal.lock().append(&frame))
+            .await
+            .map_err(storage)?
+            .map_err(storage)?;
+        apply(&self.inner.memory, &mut schemas, op).await?;
+        if wal_bytes >= self.inner.checkpoint_bytes {
+            // The write is already durable in the log; a failed checkpoint
+            // is retried after the next one.
+            if let Err(err) = self.inner.checkpoint(&schemas).await {
+                warn!(error = %err, "checkpoint failed");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl LocalStore {
+    async fn checkpoint(&self, schemas: &BTreeMap<String, serde_json::Value>) -> BraindbResult<()> {
+        let mut files = Vec::with_capacity(schemas.len());
+        for (collection, schema) in schemas {
+            let documents = self
+                .memory
+                .scan_documents(ScanDocumentsRequest {
+                    collection: collection.clone(),
+                    offset: 0,
+                    limit: usize::MAX,
+                })
+                .await?;
+            files.push(DataFile {
+                collection: collection.clone(),
+                schema: schema.clone(),
+                documents,
+            });
+        }
+        let collections = files.len();
+        let dir = self.dir.join(COLLECTIONS_DIR);
+        let wal = self.wal.clone();
+        tokio::task::spawn_blocking(move || {
+            for file in &files {
+                write_data_file(&dir, file)?;
+            }
+            sync_dir(&dir)?;
+            wal.lock().reset()?;
+            Ok::<_, anyhow::Error>(())
+        })
+        .await
+        .map_err(storage)?
+        .map_err(storage)?;
+        info!(collections, "checkpoint written");
+        Ok(())
+    }
+}
+
+/// Applies one logged mutation to the in-memory collections.
+async fn apply(
+    memory: &NullBraindbClient,
+    schemas: &mut BTreeMap<String, serde_json::Value>,
+    op: WalOp,
+) -> BraindbResult<()> {
+    match op {
+        WalOp::CreateCollection(request) => {
+            schemas.insert(request.collection.clone(), request.schema.clone());
+            memory.create_collection(request).await
+        }
+        WalOp::Upsert(request) => {
+            schemas
+                .entry(request.collection.clone())
+                .or_insert(serde_json::Value::Null);
+            memory.upsert_documents(request).await
+        }
+        WalOp::Delete(request) => memory.delete_documents(request).await,
+    }
+}
+
+/// Data files are named after the hex-encoded collection name, since
+/// namespaced names contain characters that are awkward in paths.
+fn data_file_path(dir: &Path, collection: &str) -> PathBuf {
+    let name: String = collection
+        .bytes()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    dir.join(format!("{name}.json"))
+}
+
+/// Replaces a data file atomically: written to a temporary file, synced,
+/// then renamed over the old one.
+fn write_data_file(dir: &Path, file: &DataFile) -> anyhow::Result<()> {
+    let path = data_file_path(dir, &file.collection);
+    let tmp = path.with_extension("json.tmp");
+    let mut out = std::fs::File::create(&tmp)?;
+    serde_json::to_writer(std::io::BufWriter::new(&mut out), file)?;
+    out.sync_all()?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+/// Loads the data files, removing temporary files left by a checkpoint
+/// that did not finish.
+fn read_data_files(dir: &Path) -> anyhow::Result<Vec<DataFile>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => {
+                let bytes = std::fs::read(&path)?;
+                files.push(serde_json::from_slice(&bytes).map_err(|err| {
+                    anyhow::anyhow!("corrupt data file {}: {err}", path.display())
+                })?);
+            }
+            Some("tmp") => std::fs::remove_file(&path)?,
+            _ => {}
+        }
+    }
+    files.sort_by(|a: &DataFile, b: &DataFile| a.collection.cmp(&b.collection));
+    Ok(files)
+}
+
+/// Makes renames in `dir` durable.
+fn sync_dir(dir: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    std::fs::File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+/// Flushes the log on an interval until the store is dropped.
+fn spawn_flusher(wal: Weak<Mutex<Wal>>, every: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(every);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let Some(wal) = wal.upgrade() else {
+                break;
+            };
+            match tokio::task::spawn_blocking(move || wal.lock().sync()).await {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => warn!(error = %err, "failed to flush write-ahead log"),
+                Err(err) => warn!(error = %err, "write-ahead log flush panicked"),
+            }
+        }
+    });
+}
+
+#[async_trait]
+impl BraindbClient for LocalBraindbClient {
+    #[instrument(skip_all, fields(collection = %request.collection))]
+    async fn create_collection(&self, request: CreateCollectionRequest) -> BraindbResult<()> {
+        {
+            // Indexing re-creates the collection on every request; only
+            // changes are logged.
+            let schemas = self.inner.schemas.lock().await;
+            if schemas.get(&request.collection) == Some(&request.schema) {
+                return Ok(());
+            }
+        }
+        self.write(WalOp::CreateCollection(request)).await
+    }
+
+    #[instrument(skip_all, fields(collection = %request.collection, count = request.documents.len()))]
+    async fn upsert_documents(&self, request: UpsertDocumentsRequest) -> BraindbResult<()> {
+        self.write(WalOp::Upsert(request)).await
+    }
+
+    #[instrument(skip_all, fields(collection = %request.collection, count = request.ids.len()))]
+    async fn delete_documents(&self, request: DeleteDocumentsRequest) -> BraindbResult<()> {
+        self.write(WalOp::Delete(request)).await
+    }
+
+    async fn hybrid_query(&self, request: HybridQueryRequest) -> BraindbResult<Vec<QueryResult>> {
+        self.inner.memory.hybrid_query(request).await
+    }
+
+    async fn scan_documents(
+        &self,
+        request: ScanDocumentsRequest,
+    ) -> BraindbResult<Vec<DocumentRecord>> {
+        self.inner.memory.scan_documents(request).await
+    }
+
+    async fn stats(&self) -> BraindbResult<StatsResponse> {
+        self.inner.memory.stats().await
+    }
+}
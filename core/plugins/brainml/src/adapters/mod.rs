@@ -1,2 +1,3 @@
 pub mod braindb;
 pub mod llm;
+pub mod local;
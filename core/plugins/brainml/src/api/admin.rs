@@ -1,16 +1,34 @@
 use super::errors::ApiError;
+use super::tenant::Tenant;
 use super::AppState;
-use crate::core::schema::{AdminStatus, TrainRequest, TrainResponse};
+use crate::core::schema::{AdminRequest, AdminStatus, StatsResponse, TrainRequest, TrainResponse};
 use axum::extract::State;
 use axum::routing::{get, post};
 use axum::Json;
 use tracing::instrument;
 use utoipa::path;
 
+pub fn capabilities() -> Vec<String> {
+    vec![
+        "brainml.index".into(),
+        "brainml.ingest".into(),
+        "brainml.bulk".into(),
+        "brainml.query".into(),
+        "brainml.train".into(),
+        "brainml.stats".into(),
+        "brainml.admin".into(),
+        "brainml.feedback".into(),
+        "brainml.analytics".into(),
+        "brainml.audit".into(),
+    ]
+}
+
 pub fn routes() -> axum::Router<AppState> {
     axum::Router::new()
+        .route("/api/v1/brainml/admin", post(admin_handler))
         .route("/api/v1/brainml/admin/status", get(status_handler))
         .route("/api/v1/brainml/train", post(train_handler))
+        .route("/api/v1/brainml/stats", get(stats_handler))
 }
 
 #[utoipa::path(
@@ -19,20 +37,32 @@ pub fn routes() -> axum::Router<AppState> {
     responses((status = 200, description = "Admin status", body = AdminStatus)),
     tag = "brainml"
 )]
-#[instrument(skip_all)]
-pub async fn status_handler(State(state): State<AppState>) -> Result<Json<AdminStatus>, ApiError> {
-    let uptime = state.start_time.elapsed().as_secs();
-    Ok(Json(AdminStatus {
-        version: env!("CARGO_PKG_VERSION").to_string(),
-        uptime_seconds: uptime,
-        capabilities: vec![
-            "brainml.index".into(),
-            "brainml.query".into(),
-            "brainml.train".into(),
-            "brainml.stats".into(),
-            "brainml.admin".into(),
-        ],
-    }))
+#[instrument(skip_all, fields(tenant = %tenant.id))]
+pub async fn status_handler(
+    State(state): State<AppState>,
+    Tenant(tenant): Tenant,
+) -> Result<Json<AdminStatus>, ApiError> {
+    Ok(Json(state.admin_status_for(&tenant)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/brainml/admin",
+    request_body = AdminRequest,
+    responses((status = 200, description = "Admin action applied", body = AdminStatus)),
+    tag = "brainml"
+)]
+#[instrument(skip_all, fields(tenant = %tenant.id, action = ?payload.action))]
+pub async fn admin_handler(
+    State(state): State<AppState>,
+    Tenant(tenant): Tenant,
+    Json(payload): Json<AdminRequest>,
+) -> Result<Json<AdminStatus>, ApiError> {
+    let status = state
+        .process_admin_for(&tenant, payload)
+        .await
+        .map_err(ApiError::from)?;
+    Ok(Json(status))
 }
 
 #[utoipa::path(
@@ -42,14 +72,36 @@ pub async fn status_handler(State(state): State<AppState>) -> Result<Json<AdminS
     responses((status = 200, description = "Pipeline training started", body = TrainResponse)),
     tag = "brainml"
 )]
-#[instrument(skip_all, fields(pipeline = %payload.pipeline))]
+#[instrument(skip_all, fields(tenant = %tenant.id, pipeline = %payload.pipeline))]
 pub async fn train_handler(
     State(state): State<AppState>,
+    Tenant(tenant): Tenant,
     Json(payload): Json<TrainRequest>,
 ) -> Result<Json<TrainResponse>, ApiError> {
     if payload.pipeline.trim().is_empty() {
         return Err(ApiError::Invalid("pipeline name required".into()));
     }
-    let response = state.process_train(payload).await.map_err(ApiError::from)?;
+    let response = state
+        .process_train_for(&tenant, payload)
+        .await
+        .map_err(ApiError::from)?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/brainml/stats",
+    responses((status = 200, description = "Collection and tenant statistics", body = StatsResponse)),
+    tag = "brainml"
+)]
+#[instrument(skip_all, fields(tenant = %tenant.id))]
+pub async fn stats_handler(
+    State(state): State<AppState>,
+    Tenant(tenant): Tenant,
+) -> Result<Json<StatsResponse>, ApiError> {
+    let response = state
+        .process_stats_for(&tenant)
+        .await
+        .map_err(ApiError::from)?;
     Ok(Json(response))
 }
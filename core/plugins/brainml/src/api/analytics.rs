@@ -0,0 +1,61 @@
+use super::errors::ApiError;
+use super::tenant::Tenant;
+use super::AppState;
+use crate::core::schema::{
+    AnalyticsReport, AnalyticsReportRequest, FeedbackRequest, FeedbackResponse,
+};
+use axum::extract::{Query, State};
+use axum::routing::{get, post};
+use axum::Json;
+use tracing::instrument;
+
+pub fn routes() -> axum::Router<AppState> {
+    axum::Router::new()
+        .route("/api/v1/brainml/feedback", post(feedback_handler))
+        .route("/api/v1/brainml/analytics/report", get(report_handler))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/brainml/feedback",
+    request_body = FeedbackRequest,
+    responses((status = 200, description = "Feedback recorded", body = FeedbackResponse)),
+    tag = "brainml"
+)]
+#[instrument(skip_all, fields(collection = %payload.collection))]
+pub async fn feedback_handler(
+    State(state): State<AppState>,
+    Tenant(tenant): Tenant,
+    Json(payload): Json<FeedbackRequest>,
+) -> Result<Json<FeedbackResponse>, ApiError> {
+    if payload.query_id.trim().is_empty() || payload.document_id.trim().is_empty() {
+        return Err(ApiError::Invalid(
+            "queryId and documentId are required".into(),
+        ));
+    }
+    let response = state
+        .process_feedback_for(&tenant, payload)
+        .await
+        .map_err(ApiError::from)?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/brainml/analytics/report",
+    params(AnalyticsReportRequest),
+    responses((status = 200, description = "Click-through and zero-result rates", body = AnalyticsReport)),
+    tag = "brainml"
+)]
+#[instrument(skip_all)]
+pub async fn report_handler(
+    State(state): State<AppState>,
+    Tenant(tenant): Tenant,
+    Query(params): Query<AnalyticsReportRequest>,
+) -> Result<Json<AnalyticsReport>, ApiError> {
+    let report = state
+        .process_analytics_report_for(&tenant, params)
+        .await
+        .map_err(ApiError::from)?;
+    Ok(Json(report))
+}
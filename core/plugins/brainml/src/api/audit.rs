@@ -0,0 +1,32 @@
+use super::errors::ApiError;
+use super::tenant::Tenant;
+use super::AppState;
+use crate::core::schema::{AuditQueryRequest, AuditQueryResponse};
+use axum::extract::{Query, State};
+use axum::routing::get;
+use axum::Json;
+use tracing::instrument;
+
+pub fn routes() -> axum::Router<AppState> {
+    axum::Router::new().route("/api/v1/brainml/audit", get(audit_handler))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/brainml/audit",
+    params(AuditQueryRequest),
+    responses((status = 200, description = "Audit log entries and chain verification", body = AuditQueryResponse)),
+    tag = "brainml"
+)]
+#[instrument(skip_all, fields(tenant = %tenant.id))]
+pub async fn audit_handler(
+    State(state): State<AppState>,
+    Tenant(tenant): Tenant,
+    Query(params): Query<AuditQueryRequest>,
+) -> Result<Json<AuditQueryResponse>, ApiError> {
+    let response = state
+        .process_audit_for(&tenant, params)
+        .await
+        .map_err(ApiError::from)?;
+    Ok(Json(response))
+}
@@ -0,0 +1,67 @@
+use super::errors::ApiError;
+use super::tenant::Tenant;
+use super::AppState;
+use crate::core::schema::BulkOptions;
+use axum::body::{Body, Bytes};
+use axum::extract::{DefaultBodyLimit, Query, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use futures_util::StreamExt;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{instrument, Instrument};
+
+/// Events buffered ahead of a slow reader before indexing pauses.
+const EVENT_BUFFER: usize = 16;
+
+pub fn routes() -> axum::Router<AppState> {
+    axum::Router::new()
+        .route("/api/v1/brainml/bulk", post(bulk_handler))
+        .layer(DefaultBodyLimit::disable())
+}
+
+/// Streams NDJSON documents, one `DocumentInput` per line, into a
+/// collection. The response is NDJSON as well: a progress event after each
+/// indexed batch and a summary last.
+#[utoipa::path(
+    post,
+    path = "/api/v1/brainml/bulk",
+    params(BulkOptions),
+    request_body(content = String, content_type = "application/x-ndjson", description = "One document per line"),
+    responses((status = 200, description = "Progress events followed by a summary", body = BulkEvent, content_type = "application/x-ndjson")),
+    tag = "brainml"
+)]
+#[instrument(skip_all, fields(collection = %options.collection))]
+pub async fn bulk_handler(
+    State(state): State<AppState>,
+    Tenant(tenant): Tenant,
+    Query(options): Query<BulkOptions>,
+    body: Body,
+) -> Result<Response, ApiError> {
+    if options.collection.trim().is_empty() {
+        return Err(ApiError::Invalid("collection is required".into()));
+    }
+    state
+        .writable_collection(&tenant, &options.collection)
+        .map_err(ApiError::from)?;
+    let (events, receiver) = mpsc::channel(EVENT_BUFFER);
+    tokio::spawn(
+        async move {
+            state
+                .bulk_index_stream(&tenant, options, body.into_data_stream(), events)
+                .await;
+        }
+        .in_current_span(),
+    );
+    let lines = ReceiverStream::new(receiver).map(|event| {
+        let mut line = serde_json::to_vec(&event)?;
+        line.push(b'\n');
+        Ok::<_, serde_json::Error>(Bytes::from(line))
+    });
+    Ok((
+        [(CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(lines),
+    )
+        .into_response())
+}
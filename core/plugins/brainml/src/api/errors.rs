@@ -1,4 +1,20 @@
-use axum::http::StatusCode;
+use crate::adapters::braindb::BraindbError;
+use crate::adapters::llm::LlmError;
+use crate::core::aggregations::AggregationError;
+use crate::core::aliases::AliasError;
+use crate::core::analytics::AnalyticsError;
+use crate::core::audit::AuditError;
+use crate::core::bulk::BulkError;
+use crate::core::embeddings::UnknownVectorField;
+use crate::core::errors::{ErrorCode, ErrorDetail};
+use crate::core::ingest::IngestError;
+use crate::core::limits::LimitError;
+use crate::core::pagination::PaginationError;
+use crate::core::ranker::RankerError;
+use crate::core::scoring::ScoringError;
+use crate::core::tenancy::TenantError;
+use axum::http::header::RETRY_AFTER;
+use axum::http::{HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
 use serde::Serialize;
 use thiserror::Error;
@@ -7,33 +23,164 @@ use thiserror::Error;
 pub enum ApiError {
     #[error("invalid request: {0}")]
     Invalid(String),
+    #[error("validation failed: {0}")]
+    Unprocessable(String),
     #[error("not found: {0}")]
     NotFound(String),
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("quota exceeded: {0}")]
+    QuotaExceeded(String),
+    /// The request is valid but the feature it needs is switched off.
+    #[error("{0}")]
+    Unsupported(String),
+    #[error("{message}")]
+    RateLimited {
+        code: ErrorCode,
+        message: String,
+        retry_after_secs: u64,
+    },
+    /// braindb or the LLM service failed; `code` says how.
+    #[error("{message}")]
+    Upstream { code: ErrorCode, message: String },
     #[error("internal error: {0}")]
     Internal(String),
 }
 
+impl ApiError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ApiError::Invalid(_) => ErrorCode::InvalidRequest,
+            ApiError::Unprocessable(_) => ErrorCode::ValidationFailed,
+            ApiError::NotFound(_) => ErrorCode::NotFound,
+            ApiError::Unauthorized(_) => ErrorCode::Unauthorized,
+            ApiError::Forbidden(_) => ErrorCode::Forbidden,
+            ApiError::QuotaExceeded(_) => ErrorCode::QuotaExceeded,
+            ApiError::Unsupported(_) => ErrorCode::Unsupported,
+            ApiError::RateLimited { code, .. } | ApiError::Upstream { code, .. } => *code,
+            ApiError::Internal(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+/// HTTP status for an error code.
+pub fn status_for(code: ErrorCode) -> StatusCode {
+    match code {
+        ErrorCode::InvalidRequest => StatusCode::BAD_REQUEST,
+        ErrorCode::ValidationFailed => StatusCode::UNPROCESSABLE_ENTITY,
+        ErrorCode::NotFound => StatusCode::NOT_FOUND,
+        ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+        ErrorCode::Forbidden => StatusCode::FORBIDDEN,
+        ErrorCode::QuotaExceeded | ErrorCode::RateLimited | ErrorCode::Overloaded => {
+            StatusCode::TOO_MANY_REQUESTS
+        }
+        ErrorCode::Unsupported => StatusCode::NOT_IMPLEMENTED,
+        ErrorCode::UpstreamError => StatusCode::BAD_GATEWAY,
+        ErrorCode::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        ErrorCode::Timeout => StatusCode::GATEWAY_TIMEOUT,
+        ErrorCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct ErrorBody {
     pub error: String,
+    pub code: ErrorCode,
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        let status = match &self {
-            ApiError::Invalid(_) => StatusCode::BAD_REQUEST,
-            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
-            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        let code = self.code();
+        let retry_after = match &self {
+            ApiError::RateLimited {
+                retry_after_secs, ..
+            } => Some(*retry_after_secs),
+            _ => None,
         };
         let body = axum::Json(ErrorBody {
             error: self.to_string(),
+            code,
         });
-        (status, body).into_response()
+        let mut response = (status_for(code), body).into_response();
+        if let Some(secs) = retry_after {
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(secs));
+        }
+        response
+    }
+}
+
+impl From<ApiError> for ErrorDetail {
+    fn from(error: ApiError) -> Self {
+        ErrorDetail::new(error.code(), error.to_string())
+    }
+}
+
+impl From<LimitError> for ApiError {
+    fn from(error: LimitError) -> Self {
+        ApiError::RateLimited {
+            code: error.code(),
+            retry_after_secs: error.retry_after().as_secs_f64().ceil().max(1.0) as u64,
+            message: error.to_string(),
+        }
     }
 }
 
 impl From<anyhow::Error> for ApiError {
     fn from(error: anyhow::Error) -> Self {
-        ApiError::Internal(error.to_string())
+        if let Some(braindb) = error.downcast_ref::<BraindbError>() {
+            return ApiError::Upstream {
+                code: braindb.code(),
+                message: error.to_string(),
+            };
+        }
+        if let Some(llm) = error.downcast_ref::<LlmError>() {
+            return ApiError::Upstream {
+                code: llm.code(),
+                message: error.to_string(),
+            };
+        }
+        match error.downcast_ref::<AliasError>() {
+            Some(AliasError::UnknownAlias(_) | AliasError::UnknownCollection(_)) => {
+                return ApiError::NotFound(error.to_string())
+            }
+            Some(AliasError::Invalid { .. }) => return ApiError::Unprocessable(error.to_string()),
+            Some(AliasError::Persist(_)) | None => {}
+        }
+        if matches!(
+            error.downcast_ref::<AnalyticsError>(),
+            Some(AnalyticsError::Disabled)
+        ) || matches!(
+            error.downcast_ref::<AuditError>(),
+            Some(AuditError::Disabled)
+        ) {
+            return ApiError::Unsupported(error.to_string());
+        }
+        match error.downcast_ref::<BulkError>() {
+            Some(BulkError::UnknownStream(_)) => return ApiError::NotFound(error.to_string()),
+            Some(_) => return ApiError::Unprocessable(error.to_string()),
+            None => {}
+        }
+        match error.downcast_ref::<TenantError>() {
+            Some(TenantError::QuotaExceeded { .. }) => ApiError::QuotaExceeded(error.to_string()),
+            Some(TenantError::Unauthorized(_)) => ApiError::Unauthorized(error.to_string()),
+            Some(TenantError::Forbidden(_)) => ApiError::Forbidden(error.to_string()),
+            Some(TenantError::InvalidCollection(_) | TenantError::InvalidTenant(_)) => {
+                ApiError::Unprocessable(error.to_string())
+            }
+            None if error.is::<UnknownVectorField>()
+                || error.is::<PaginationError>()
+                || error.is::<RankerError>()
+                || error.is::<ScoringError>()
+                || error.is::<AggregationError>()
+                || error.is::<IngestError>() =>
+            {
+                ApiError::Unprocessable(error.to_string())
+            }
+            None => ApiError::Internal(error.to_string()),
+        }
     }
 }
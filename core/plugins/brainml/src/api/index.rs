@@ -1,4 +1,5 @@
 use super::errors::ApiError;
+use super::tenant::Tenant;
 use super::AppState;
 use crate::core::schema::{IndexRequest, QueryResponse};
 use axum::extract::State;
@@ -21,11 +22,15 @@ pub fn routes() -> axum::Router<AppState> {
 #[instrument(skip_all, fields(collection = %payload.collection, docs = payload.documents.len()))]
 pub async fn index_handler(
     State(state): State<AppState>,
+    Tenant(tenant): Tenant,
     Json(payload): Json<IndexRequest>,
 ) -> Result<Json<QueryResponse>, ApiError> {
     if payload.collection.trim().is_empty() {
         return Err(ApiError::Invalid("collection is required".into()));
     }
-    let response = state.process_index(payload).await.map_err(ApiError::from)?;
+    let response = state
+        .process_index_for(&tenant, payload)
+        .await
+        .map_err(ApiError::from)?;
     Ok(Json(response))
 }
@@ -0,0 +1,82 @@
+use super::errors::ApiError;
+use super::tenant::Tenant;
+use super::AppState;
+use crate::core::ingest::SourceFile;
+use crate::core::schema::{IngestRequest, IngestResponse};
+use axum::extract::{DefaultBodyLimit, Multipart, State};
+use axum::routing::post;
+use axum::Json;
+use tracing::instrument;
+
+/// Upper bound for a whole upload; individual files are checked against
+/// `ingest.max_file_bytes`.
+const MAX_UPLOAD_BYTES: usize = 256 * 1024 * 1024;
+
+pub fn routes() -> axum::Router<AppState> {
+    axum::Router::new()
+        .route("/api/v1/brainml/ingest", post(ingest_handler))
+        .layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES))
+}
+
+/// Accepts `multipart/form-data` with a `collection` field, optional
+/// `metadata` (JSON), `embed` and `fts` fields, and one or more file parts.
+#[utoipa::path(
+    post,
+    path = "/api/v1/brainml/ingest",
+    request_body(content = String, content_type = "multipart/form-data", description = "Files to ingest"),
+    responses((status = 200, description = "Files ingested", body = IngestResponse)),
+    tag = "brainml"
+)]
+#[instrument(skip_all)]
+pub async fn ingest_handler(
+    State(state): State<AppState>,
+    Tenant(tenant): Tenant,
+    mut multipart: Multipart,
+) -> Result<Json<IngestResponse>, ApiError> {
+    let invalid =
+        |err: axum::extract::multipart::MultipartError| ApiError::Invalid(err.to_string());
+    let mut request = IngestRequest::default();
+    let mut files = Vec::new();
+    while let Some(field) = multipart.next_field().await.map_err(invalid)? {
+        if let Some(file_name) = field.file_name().map(str::to_string) {
+            let content_type = field.content_type().map(str::to_string);
+            let bytes = field.bytes().await.map_err(invalid)?;
+            files.push(SourceFile {
+                path: file_name,
+                content_type,
+                bytes: bytes.to_vec(),
+            });
+            continue;
+        }
+        let name = field.name().unwrap_or_default().to_string();
+        let value = field.text().await.map_err(invalid)?;
+        match name.as_str() {
+            "collection" => request.collection = value,
+            "metadata" => {
+                request.metadata = serde_json::from_str(&value)
+                    .map_err(|err| ApiError::Invalid(format!("metadata: {err}")))?;
+            }
+            "embed" => request.embed = parse_flag(&name, &value)?,
+            "fts" => request.fts = parse_flag(&name, &value)?,
+            _ => return Err(ApiError::Invalid(format!("unexpected field {name}"))),
+        }
+    }
+    if request.collection.trim().is_empty() {
+        return Err(ApiError::Invalid("collection is required".into()));
+    }
+    if files.is_empty() {
+        return Err(ApiError::Invalid("at least one file is required".into()));
+    }
+    let response = state
+        .ingest_files_for(&tenant, &request, files)
+        .await
+        .map_err(ApiError::from)?;
+    Ok(Json(response))
+}
+
+fn parse_flag(name: &str, value: &str) -> Result<bool, ApiError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| ApiError::Invalid(format!("{name} must be true or false")))
+}
@@ -0,0 +1,55 @@
+use super::errors::ApiError;
+use super::tenant::tenant_from_headers;
+use super::AppState;
+use axum::body::{Body, HttpBody};
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use futures_util::StreamExt;
+
+/// Bus capability whose limits apply to an HTTP path. Health and OpenAPI
+/// routes are not limited.
+pub fn capability_for_path(path: &str) -> Option<&'static str> {
+    let capability = match path.strip_prefix("/api/v1/brainml/")? {
+        "query" => "brainml.query",
+        "index" => "brainml.index",
+        "ingest" => "brainml.ingest",
+        "bulk" => "brainml.bulk",
+        "train" => "brainml.train",
+        "stats" => "brainml.stats",
+        "admin" | "admin/status" => "brainml.admin",
+        "feedback" => "brainml.feedback",
+        "analytics/report" => "brainml.analytics",
+        "audit" => "brainml.audit",
+        _ => return None,
+    };
+    Some(capability)
+}
+
+/// Middleware holding a limiter permit for the duration of the request.
+pub async fn admit(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(capability) = capability_for_path(request.uri().path()) else {
+        return next.run(request).await;
+    };
+    let tenant = match tenant_from_headers(&state, request.headers()) {
+        Ok(tenant) => tenant,
+        Err(err) => return ApiError::from(anyhow::Error::new(err)).into_response(),
+    };
+    match state.admit(&tenant, capability).await {
+        Ok(permit) => {
+            let response = next.run(request).await;
+            if response.body().size_hint().exact().is_some() {
+                return response;
+            }
+            // Streamed responses keep working after the handler returns, so
+            // the permit is released once the body is done.
+            response.map(|body| {
+                Body::from_stream(body.into_data_stream().map(move |chunk| {
+                    let _ = &permit;
+                    chunk
+                }))
+            })
+        }
+        Err(err) => ApiError::from(err).into_response(),
+    }
+}
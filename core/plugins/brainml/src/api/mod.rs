@@ -1,62 +1,276 @@
 pub mod admin;
+pub mod analytics;
+pub mod audit;
+pub mod bulk;
 pub mod errors;
 pub mod health;
 pub mod index;
+pub mod ingest;
+pub mod limits;
 pub mod openapi;
+pub mod propagation;
 pub mod query;
+pub mod tenant;
 
-use crate::core::embeddings::embed_documents;
+use crate::adapters::braindb::DeleteDocumentsRequest;
+use crate::adapters::braindb::ScanDocumentsRequest;
+use crate::adapters::llm::{ChatMessage, ChatRequest};
+use crate::core::aggregations;
+use crate::core::aliases::{AliasChange, AliasError, AliasRegistry};
+use crate::core::analytics::{
+    AnalyticsError, AnalyticsEvent, FeedbackLogEntry, QueryLog, QueryLogEntry,
+};
+use crate::core::audit::{AuditError, AuditEvent, AuditLog};
+use crate::core::bulk::{BulkError, BulkStreams, BulkTally, Line, LineReader};
+use crate::core::cache::{CacheKey, QueryCache};
+use crate::core::config::{BrainmlConfig, SharedConfig, SyncSource};
+use crate::core::embeddings::{embed_documents, model_for_field, UnknownVectorField};
+use crate::core::evaluation::{self, EvaluationError, EvaluationOptions, EvaluationReport, Qrels};
+use crate::core::expiry::{self, ExpirySweep};
+use crate::core::highlight;
+use crate::core::ingest::{chunk_id, IngestError, SourceFile};
+use crate::core::limits::{Admission, LimitError, LimitPermit, Limiter};
+use crate::core::migrator::EmbeddingMigrator;
+use crate::core::pagination::{self, Page, PageSlice};
 use crate::core::pipeline::PipelineManager;
-use crate::core::ranker::reciprocal_rank_fusion;
+use crate::core::ranker::{self, reciprocal_rank_fusion};
 use crate::core::retriever::{build_records, ensure_collection, hybrid_query, upsert_documents};
+use crate::core::rewrite::{self, TermDictionaries, TermDictionary};
+use crate::core::schema::QueryResult;
 use crate::core::schema::{
-    AdminStatus, IndexRequest, QueryRequest, QueryResponse, TrainRequest, TrainResponse,
+    AdminAction, AdminRequest, AdminStatus, AliasUpdate, AnalyticsReport, AnalyticsReportRequest,
+    AuditQueryRequest, AuditQueryResponse, BulkEvent, BulkOptions, BulkProgress, BulkRequest,
+    CollectionAlias, CollectionStats, DocumentInput, ExpiryReport, FeedbackRequest,
+    FeedbackResponse, IndexRequest, IngestRequest, IngestResponse, NamedVector, QueryDebug,
+    QueryRequest, QueryResponse, StatsResponse, SyncReport, TrainRequest, TrainResponse,
 };
-use crate::core::scoring::normalize_scores;
+use crate::core::scoring::{self, normalize_scores};
+use crate::core::sync::{self, DirectorySync, ManifestEntry, SyncManifest, SyncPlan};
+use crate::core::tenancy::{split_namespace, TenantContext, TenantError, TenantRegistry};
 use crate::{adapters::braindb::BraindbClient, adapters::llm::LlmClient};
 use axum::extract::State;
 use axum::response::IntoResponse;
 use axum::{Json, Router};
+use chrono::Utc;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
-use tracing::instrument;
+use tracing::{error, info, instrument, warn};
+
+/// Documents fetched per page while building a term dictionary.
+const DICTIONARY_SCAN_BATCH: usize = 500;
 
 #[derive(Clone)]
 pub struct AppState {
     pub braindb: Arc<dyn BraindbClient>,
     pub llm: Arc<dyn LlmClient>,
     pub pipeline: PipelineManager,
-    pub config: crate::core::config::BrainmlConfig,
+    pub config: SharedConfig,
+    pub tenants: TenantRegistry,
+    pub migrator: EmbeddingMigrator,
+    pub sync: DirectorySync,
+    pub analytics: QueryLog,
+    pub dictionaries: TermDictionaries,
+    pub cache: QueryCache,
+    pub limiter: Limiter,
+    pub aliases: AliasRegistry,
+    pub expiry: ExpirySweep,
+    pub bulk: BulkStreams,
+    pub audit: AuditLog,
     pub start_time: std::time::Instant,
 }
 
 pub fn router(state: AppState) -> Router {
     Router::new()
         .merge(index::routes())
+        .merge(ingest::routes())
+        .merge(bulk::routes())
         .merge(query::routes())
         .merge(admin::routes())
+        .merge(analytics::routes())
+        .merge(audit::routes())
         .merge(health::routes())
         .merge(openapi::routes())
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            limits::admit,
+        ))
+        .layer(axum::middleware::from_fn(propagation::propagate))
         .with_state(state)
 }
 
 impl AppState {
-    #[instrument(skip_all, fields(collection = %request.collection))]
+    pub fn new(
+        braindb: Arc<dyn BraindbClient>,
+        llm: Arc<dyn LlmClient>,
+        config: impl Into<SharedConfig>,
+    ) -> Self {
+        Self {
+            braindb,
+            llm,
+            pipeline: PipelineManager::default(),
+            config: config.into(),
+            tenants: TenantRegistry::default(),
+            migrator: EmbeddingMigrator::default(),
+            sync: DirectorySync::default(),
+            analytics: QueryLog::default(),
+            dictionaries: TermDictionaries::default(),
+            cache: QueryCache::default(),
+            limiter: Limiter::default(),
+            aliases: AliasRegistry::default(),
+            expiry: ExpirySweep::default(),
+            bulk: BulkStreams::default(),
+            audit: AuditLog::default(),
+            start_time: std::time::Instant::now(),
+        }
+    }
+
+    pub fn default_tenant(&self) -> TenantContext {
+        TenantContext::default_tenant(&self.config.load().tenancy)
+    }
+
+    /// Maps a tenant-visible collection name or alias onto the physical
+    /// collection it addresses.
+    pub fn resolve_collection(
+        &self,
+        tenant: &TenantContext,
+        collection: &str,
+    ) -> Result<String, anyhow::Error> {
+        Ok(self.aliases.resolve(&tenant.scope(collection)?))
+    }
+
+    /// Maps a tenant-visible collection name onto the physical collection a
+    /// write goes to. Writes must name the collection itself, so no
+    /// collection can be created under the name of an alias.
+    pub fn writable_collection(
+        &self,
+        tenant: &TenantContext,
+        collection: &str,
+    ) -> Result<String, anyhow::Error> {
+        let physical = tenant.scope(collection)?;
+        if self.aliases.is_alias(&physical) {
+            return Err(AliasError::Invalid {
+                alias: collection.to_string(),
+                reason: "writes must name a collection, not an alias".into(),
+            }
+            .into());
+        }
+        Ok(physical)
+    }
+
+    /// Applies the configured rate and concurrency limits to one request;
+    /// the returned permit holds its slot until dropped.
+    pub async fn admit(
+        &self,
+        tenant: &TenantContext,
+        capability: &str,
+    ) -> Result<LimitPermit, LimitError> {
+        let config = self.config.load();
+        self.limiter
+            .acquire(&config.limits, &tenant.id, capability)
+            .await
+    }
+
+    /// Admits or queues a request without waiting, see
+    /// [`Limiter::try_acquire`].
+    pub fn try_admit(
+        &self,
+        tenant: &TenantContext,
+        capability: &str,
+    ) -> Result<Admission, LimitError> {
+        let config = self.config.load();
+        self.limiter
+            .try_acquire(&config.limits, &tenant.id, capability)
+    }
+
     pub async fn process_index(
         &self,
         request: IndexRequest,
     ) -> Result<QueryResponse, anyhow::Error> {
-        ensure_collection(self.braindb.as_ref(), &request.collection).await?;
+        self.process_index_for(&self.default_tenant(), request)
+            .await
+    }
+
+    #[instrument(skip_all, fields(tenant = %tenant.id, collection = %request.collection))]
+    pub async fn process_index_for(
+        &self,
+        tenant: &TenantContext,
+        request: IndexRequest,
+    ) -> Result<QueryResponse, anyhow::Error> {
+        let event = AuditEvent::new(tenant, "brainml.index")
+            .collection(request.collection.as_str())
+            .documents(request.documents.len() as u64);
+        let result = self.index_for(tenant, request).await;
+        self.audit(event, result.as_ref().err()).await;
+        result
+    }
+
+    /// Indexes without an audit entry, for callers that record their own.
+    async fn index_for(
+        &self,
+        tenant: &TenantContext,
+        mut request: IndexRequest,
+    ) -> Result<QueryResponse, anyhow::Error> {
+        let config = self.config.load();
+        let requested = std::mem::take(&mut request.collection);
+        request.collection = self.writable_collection(tenant, &requested)?;
+        let incoming_bytes: u64 = request
+            .documents
+            .iter()
+            .map(|doc| (doc.text.len() + doc.metadata.to_string().len()) as u64)
+            .sum();
+        let quota = config.tenancy.quota_for(&tenant.id);
+        let current = if quota.max_documents.is_some() || quota.max_storage_bytes.is_some() {
+            let stats = self.braindb.stats().await?;
+            visible_collections(tenant, stats.collections)
+        } else {
+            Vec::new()
+        };
+        self.tenants.check_index(
+            tenant,
+            quota,
+            &current,
+            request.documents.len(),
+            incoming_bytes,
+        )?;
+        ensure_collection(
+            self.braindb.as_ref(),
+            &request.collection,
+            config
+                .quantization
+                .settings_for(&request.collection, &requested),
+        )
+        .await?;
         let embeddings = if request.embed {
             embed_documents(
                 self.llm.as_ref(),
                 &request.documents,
-                self.config.embedding_model.as_deref(),
+                config.embedding_model.as_deref(),
             )
             .await?
         } else {
             vec![Vec::new(); request.documents.len()]
         };
-        let records = build_records(&request.documents, &embeddings);
+        let mut records = build_records(
+            &request.documents,
+            &embeddings,
+            config.embedding_model.as_deref(),
+        );
+        if request.embed {
+            for field in selected_vector_fields(&config, &request.vector_fields)? {
+                let model = config.vector_fields[&field].model.clone();
+                let vectors =
+                    embed_documents(self.llm.as_ref(), &request.documents, Some(&model)).await?;
+                for (record, values) in records.iter_mut().zip(vectors) {
+                    record.vectors.insert(
+                        field.clone(),
+                        NamedVector {
+                            model: model.clone(),
+                            values,
+                        },
+                    );
+                }
+            }
+        }
         upsert_documents(
             self.braindb.as_ref(),
             crate::adapters::braindb::UpsertDocumentsRequest {
@@ -65,51 +279,1365 @@ impl AppState {
             },
         )
         .await?;
+        self.dictionaries.invalidate(&request.collection);
+        self.cache.invalidate(&request.collection);
         Ok(QueryResponse {
             results: Vec::new(),
             latency_ms: 0,
+            ..Default::default()
         })
     }
 
-    #[instrument(skip_all, fields(collection = %request.collection))]
+    #[instrument(skip_all, fields(tenant = %tenant.id, collection = %request.collection))]
+    pub async fn process_ingest_for(
+        &self,
+        tenant: &TenantContext,
+        mut request: IngestRequest,
+    ) -> Result<IngestResponse, anyhow::Error> {
+        let files = std::mem::take(&mut request.files)
+            .into_iter()
+            .map(SourceFile::from_request)
+            .collect::<Result<Vec<_>, _>>()?;
+        self.ingest_files_for(tenant, &request, files).await
+    }
+
+    /// Extracts text from raw files, chunks it and indexes one document per
+    /// chunk. `request.files` is ignored in favour of `files`.
+    #[instrument(skip_all, fields(tenant = %tenant.id, collection = %request.collection, files = files.len()))]
+    pub async fn ingest_files_for(
+        &self,
+        tenant: &TenantContext,
+        request: &IngestRequest,
+        files: Vec<SourceFile>,
+    ) -> Result<IngestResponse, anyhow::Error> {
+        let result = self.ingest_files(tenant, request, files).await;
+        let mut event =
+            AuditEvent::new(tenant, "brainml.ingest").collection(request.collection.as_str());
+        if let Ok(response) = &result {
+            event = event.documents(response.documents as u64);
+        }
+        self.audit(event, result.as_ref().err()).await;
+        result
+    }
+
+    async fn ingest_files(
+        &self,
+        tenant: &TenantContext,
+        request: &IngestRequest,
+        files: Vec<SourceFile>,
+    ) -> Result<IngestResponse, anyhow::Error> {
+        let config = self.config.load();
+        let settings = &config.ingest;
+        let mut documents = Vec::new();
+        let mut summaries = Vec::new();
+        for file in &files {
+            let extracted = crate::core::ingest::extract(file, settings.max_file_bytes)?;
+            let chunks = crate::core::ingest::chunk_text(
+                &extracted.text,
+                settings.chunk_size,
+                settings.chunk_overlap,
+            );
+            documents.extend(crate::core::ingest::to_documents(
+                file,
+                &extracted,
+                &chunks,
+                &request.metadata,
+            ));
+            summaries.push(crate::core::ingest::summary(file, &extracted, chunks.len()));
+        }
+        let indexed = documents.len();
+        if !documents.is_empty() {
+            self.index_for(
+                tenant,
+                IndexRequest {
+                    collection: request.collection.clone(),
+                    documents,
+                    embed: request.embed,
+                    fts: request.fts,
+                    vector_fields: Vec::new(),
+                },
+            )
+            .await?;
+        }
+        Ok(IngestResponse {
+            collection: request.collection.clone(),
+            files: summaries,
+            documents: indexed,
+        })
+    }
+
+    /// Indexes an NDJSON byte stream in batches of `bulk.batch_size`,
+    /// sending a progress event after every batch and a summary at the end.
+    /// Input is read only as fast as batches are indexed and events are
+    /// consumed; the ingest stops if `events` is dropped.
+    #[instrument(skip_all, fields(tenant = %tenant.id, collection = %options.collection))]
+    pub async fn bulk_index_stream<S, E>(
+        &self,
+        tenant: &TenantContext,
+        options: BulkOptions,
+        mut body: S,
+        events: tokio::sync::mpsc::Sender<BulkEvent>,
+    ) where
+        S: futures_util::Stream<Item = Result<bytes::Bytes, E>> + Unpin,
+        E: std::fmt::Display,
+    {
+        use futures_util::StreamExt;
+
+        let started = std::time::Instant::now();
+        let config = self.config.load();
+        let batch_size = config.bulk.batch_size;
+        let mut reader = LineReader::new(config.bulk.max_line_bytes);
+        let mut tally = BulkTally::default();
+        let mut batch: Vec<DocumentInput> = Vec::with_capacity(batch_size);
+        let mut error = None;
+        'read: loop {
+            let (lines, done) = match body.next().await {
+                Some(Ok(chunk)) => (reader.feed(&chunk), false),
+                Some(Err(err)) => {
+                    error = Some(format!("failed to read request body: {err}"));
+                    break;
+                }
+                None => (reader.finish().into_iter().collect(), true),
+            };
+            for line in lines {
+                match line {
+                    Line::Document(document) => batch.push(document),
+                    Line::Rejected(rejected) => tally.reject(rejected),
+                }
+                if batch.len() < batch_size {
+                    continue;
+                }
+                let documents = std::mem::replace(&mut batch, Vec::with_capacity(batch_size));
+                let count = documents.len();
+                if let Err(err) = self
+                    .index_bulk_batch(tenant, &options, documents, &mut tally)
+                    .await
+                {
+                    tally.record_failed_batch(count);
+                    error = Some(err.to_string());
+                    break 'read;
+                }
+                if events
+                    .send(BulkEvent::Progress(tally.progress(None)))
+                    .await
+                    .is_err()
+                {
+                    // Still recorded, since batches were already indexed.
+                    error = Some("client disconnected".to_string());
+                    break 'read;
+                }
+            }
+            if done {
+                break;
+            }
+        }
+        if error.is_none() && !batch.is_empty() {
+            let count = batch.len();
+            if let Err(err) = self
+                .index_bulk_batch(tenant, &options, batch, &mut tally)
+                .await
+            {
+                tally.record_failed_batch(count);
+                error = Some(err.to_string());
+            }
+        }
+        if let Some(error) = &error {
+            warn!(error = %error, indexed = tally.indexed, "bulk ingest stopped early");
+        }
+        let event = AuditEvent::new(tenant, "brainml.bulk")
+            .collection(options.collection.as_str())
+            .documents(tally.indexed);
+        self.audit(event, error.as_ref()).await;
+        let summary = tally.summary(None, &options.collection, error, started);
+        let _ = events.send(BulkEvent::Summary(summary)).await;
+    }
+
+    async fn index_bulk_batch(
+        &self,
+        tenant: &TenantContext,
+        options: &BulkOptions,
+        documents: Vec<DocumentInput>,
+        tally: &mut BulkTally,
+    ) -> Result<(), anyhow::Error> {
+        let count = documents.len();
+        self.index_for(
+            tenant,
+            IndexRequest {
+                collection: options.collection.clone(),
+                documents,
+                embed: options.embed,
+                fts: options.fts,
+                vector_fields: Vec::new(),
+            },
+        )
+        .await?;
+        tally.record_batch(count);
+        Ok(())
+    }
+
+    pub async fn process_bulk(&self, request: BulkRequest) -> Result<BulkEvent, anyhow::Error> {
+        self.process_bulk_for(&self.default_tenant(), request).await
+    }
+
+    /// Runs one step of a chunked bus ingest. Chunks of a stream are indexed
+    /// one at a time, so a client waiting for each response cannot outrun
+    /// indexing.
+    #[instrument(skip_all, fields(tenant = %tenant.id))]
+    pub async fn process_bulk_for(
+        &self,
+        tenant: &TenantContext,
+        request: BulkRequest,
+    ) -> Result<BulkEvent, anyhow::Error> {
+        let mut event = AuditEvent::new(tenant, "brainml.bulk");
+        event = match &request {
+            BulkRequest::Open(options) => {
+                event.action("open").collection(options.collection.as_str())
+            }
+            BulkRequest::Chunk(chunk) => event
+                .action("chunk")
+                .documents(chunk.documents.len() as u64),
+            BulkRequest::Close(_) => event.action("close"),
+        };
+        let result = self.bulk_step(tenant, request).await;
+        match &result {
+            Ok(BulkEvent::Summary(summary)) => {
+                event = event
+                    .collection(summary.collection.as_str())
+                    .documents(summary.indexed);
+            }
+            Ok(BulkEvent::Progress(_)) | Err(_) => {}
+        }
+        self.audit(event, result.as_ref().err()).await;
+        result
+    }
+
+    async fn bulk_step(
+        &self,
+        tenant: &TenantContext,
+        request: BulkRequest,
+    ) -> Result<BulkEvent, anyhow::Error> {
+        let config = self.config.load();
+        let settings = &config.bulk;
+        let purged = self
+            .bulk
+            .purge_idle(std::time::Duration::from_secs(settings.idle_timeout_secs));
+        if purged > 0 {
+            info!(purged, "dropped idle bulk streams");
+        }
+        match request {
+            BulkRequest::Open(options) => {
+                if options.collection.trim().is_empty() {
+                    return Err(BulkError::MissingCollection.into());
+                }
+                self.writable_collection(tenant, &options.collection)?;
+                let stream_id = self.bulk.open(tenant.clone(), options);
+                Ok(BulkEvent::Progress(BulkProgress {
+                    stream_id: Some(stream_id),
+                    ..Default::default()
+                }))
+            }
+            BulkRequest::Chunk(chunk) => {
+                if chunk.documents.len() > settings.max_chunk_documents {
+                    return Err(BulkError::ChunkTooLarge {
+                        documents: chunk.documents.len(),
+                        max: settings.max_chunk_documents,
+                    }
+                    .into());
+                }
+                let stream = self.bulk.get(chunk.stream_id, tenant).await?;
+                let mut stream = stream.lock().await;
+                if chunk.seq != stream.next_seq {
+                    return Err(BulkError::OutOfOrder {
+                        expected: stream.next_seq,
+                        got: chunk.seq,
+                    }
+                    .into());
+                }
+                stream.last_active = std::time::Instant::now();
+                let options = stream.options.clone();
+                // A failed chunk is retried whole, so it only counts once
+                // every batch of it is indexed.
+                let mut tally = stream.tally.clone();
+                let mut documents = chunk.documents;
+                while !documents.is_empty() {
+                    let rest = documents.split_off(documents.len().min(settings.batch_size));
+                    self.index_bulk_batch(tenant, &options, documents, &mut tally)
+                        .await?;
+                    documents = rest;
+                }
+                stream.tally = tally;
+                stream.next_seq += 1;
+                stream.last_active = std::time::Instant::now();
+                Ok(BulkEvent::Progress(
+                    stream.tally.progress(Some(chunk.stream_id)),
+                ))
+            }
+            BulkRequest::Close(reference) => {
+                let stream = self.bulk.close(reference.stream_id, tenant).await?;
+                let stream = stream.lock().await;
+                Ok(BulkEvent::Summary(stream.tally.summary(
+                    Some(reference.stream_id),
+                    &stream.options.collection,
+                    None,
+                    stream.started,
+                )))
+            }
+        }
+    }
+
     pub async fn process_query(
         &self,
         request: QueryRequest,
     ) -> Result<QueryResponse, anyhow::Error> {
+        self.process_query_for(&self.default_tenant(), request)
+            .await
+    }
+
+    #[instrument(skip_all, fields(tenant = %tenant.id, collection = %request.collection))]
+    pub async fn process_query_for(
+        &self,
+        tenant: &TenantContext,
+        request: QueryRequest,
+    ) -> Result<QueryResponse, anyhow::Error> {
+        let started = std::time::Instant::now();
+        let config = self.config.load();
+        self.tenants
+            .check_query(tenant, config.tenancy.quota_for(&tenant.id))?;
+        let collection = request.collection.clone();
+        let filters = request.filters.clone();
+        let original_query = request.query.clone();
         let mut payload = request;
+        payload.collection = self.resolve_collection(tenant, &payload.collection)?;
+        let mut response = self.cached_query(&config, &collection, payload).await?;
+        let latency_ms = started.elapsed().as_millis() as u64;
+        if config.analytics.enabled {
+            let query_id = uuid::Uuid::new_v4().to_string();
+            self.log_event(AnalyticsEvent::Query(QueryLogEntry {
+                query_id: query_id.clone(),
+                timestamp: Utc::now(),
+                tenant: tenant.id.clone(),
+                collection,
+                query: original_query,
+                filters,
+                result_ids: response
+                    .results
+                    .iter()
+                    .map(|result| result.id.clone())
+                    .collect(),
+                latency_ms,
+            }))
+            .await;
+            response.query_id = Some(query_id);
+        }
+        response.latency_ms = latency_ms;
+        Ok(response)
+    }
+
+    /// Serves a query from the result cache when possible and caches fresh
+    /// responses. In semantic mode the query is embedded up front; the
+    /// embedding is reused for retrieval when the query is not rewritten.
+    /// Highlighted and debug requests are only reused on an exact match,
+    /// since their snippets and debug section depend on the query text.
+    async fn cached_query(
+        &self,
+        config: &BrainmlConfig,
+        collection: &str,
+        payload: QueryRequest,
+    ) -> Result<QueryResponse, anyhow::Error> {
+        let settings = &config.query_cache;
+        if !settings.enabled {
+            return self.execute_query(config, collection, payload, None).await;
+        }
+        let key = CacheKey::new(&payload, self.config.generation());
+        if let Some(hit) = self.cache.get(&key, settings) {
+            return Ok(hit);
+        }
+        let semantic = settings.semantic && !payload.highlight.enabled && !payload.debug;
+        let embedding = match payload.query.as_deref() {
+            Some(text) if semantic && payload.vector.is_none() => {
+                let model = model_for_field(config, payload.vector_field.as_deref())?;
+                self.embed_query(text, model).await?
+            }
+            _ => None,
+        };
+        if let Some(embedding) = &embedding {
+            if let Some(hit) = self.cache.get_similar(&key, embedding, settings) {
+                return Ok(hit);
+            }
+        }
+        let generation = self.cache.miss(&payload.collection);
+        let response = self
+            .execute_query(config, collection, payload, embedding.clone())
+            .await?;
+        self.cache
+            .insert(key, generation, embedding, response.clone(), settings);
+        Ok(response)
+    }
+
+    async fn embed_query(
+        &self,
+        text: &str,
+        model: Option<&str>,
+    ) -> Result<Option<Vec<f32>>, anyhow::Error> {
+        Ok(embed_documents(
+            self.llm.as_ref(),
+            &[crate::core::schema::DocumentInput {
+                id: None,
+                text: text.to_string(),
+                metadata: serde_json::Value::Null,
+            }],
+            model,
+        )
+        .await?
+        .into_iter()
+        .next())
+    }
+
+    /// Rewrites, retrieves, ranks and pages a query. `query_embedding` is
+    /// the embedding of `payload.query` as sent, if already computed.
+    async fn execute_query(
+        &self,
+        config: &BrainmlConfig,
+        collection: &str,
+        mut payload: QueryRequest,
+        query_embedding: Option<Vec<f32>>,
+    ) -> Result<QueryResponse, anyhow::Error> {
+        let original_query = payload.query.clone();
+        let rewrite = match payload.query.as_deref() {
+            Some(text) if !text.trim().is_empty() => Some(
+                self.rewrite_query(config, collection, &payload.collection, text)
+                    .await?,
+            ),
+            _ => None,
+        };
+        if let Some(rewrite) = &rewrite {
+            payload.query = rewrite.rewritten_query.clone();
+        }
+        let model = model_for_field(config, payload.vector_field.as_deref())?;
         let vector = if payload.vector.is_none() && payload.hybrid {
-            if let Some(query) = &payload.query {
-                embed_documents(
-                    self.llm.as_ref(),
-                    &[crate::core::schema::DocumentInput {
-                        id: None,
-                        text: query.clone(),
-                        metadata: serde_json::Value::Null,
-                    }],
-                    self.config.embedding_model.as_deref(),
-                )
-                .await?
-                .into_iter()
-                .next()
-            } else {
-                None
+            match &payload.query {
+                Some(_) if query_embedding.is_some() && payload.query == original_query => {
+                    query_embedding
+                }
+                Some(query) => self.embed_query(query, model).await?,
+                None => None,
             }
         } else {
             payload.vector.clone()
         };
-        let mut results = hybrid_query(self.braindb.as_ref(), payload, vector).await?;
-        results = reciprocal_rank_fusion(results, self.config.collection_defaults.rrf_k);
+        if let Some(options) = &payload.diversify {
+            ranker::validate(options)?;
+        }
+        let page = Page::from_request(&payload, config.collection_defaults.max_result_window)?;
+        let query = payload.query.clone();
+        let fields = payload.fields.clone();
+        let highlight_options = payload.highlight.clone();
+        let diversify = payload.diversify.clone();
+        let vector_field = payload.vector_field.clone();
+        let debug = payload.debug;
+        let recency = payload.recency.clone();
+        let aggregation_requests = std::mem::take(&mut payload.aggregations);
+        payload.top_k = page.fetch_size();
+        let variants = rewrite
+            .as_ref()
+            .map(|rewrite| rewrite.variants.clone())
+            .unwrap_or_default();
+        let (mut results, mut fetched) = self
+            .retrieve_variants(payload, &variants, vector.clone(), model)
+            .await?;
+        // The result past the window only shows that the window is full.
+        let max_window = config.collection_defaults.max_result_window;
+        let aggregated = &results[..results.len().min(max_window)];
+        let aggregations = aggregations::aggregate(&aggregation_requests, aggregated)?;
+        let aggregations_truncated =
+            !aggregation_requests.is_empty() && results.len() > max_window;
+        if let Some(boost) = &recency {
+            scoring::apply_recency(&mut results, boost, Utc::now())?;
+        }
+        pagination::stable_sort(&mut results);
+        let retrieval_scores: HashMap<String, f32> = results
+            .iter()
+            .map(|result| (result.id.clone(), result.score))
+            .collect();
+        results = reciprocal_rank_fusion(results, config.collection_defaults.rrf_k);
         normalize_scores(&mut results);
+        if let Some(options) = &diversify {
+            let candidates = results.len();
+            results = ranker::diversify(results, options, vector_field.as_deref(), page.window())?;
+            // Diversification stopped at the window, so more hits may follow.
+            if results.len() == page.window() && candidates > results.len() {
+                fetched = page.fetch_size();
+            }
+        }
+        let PageSlice {
+            mut results,
+            total_hits,
+            next_cursor,
+        } = pagination::slice(&page, results, &retrieval_scores, fetched);
+        highlight::annotate(
+            self.llm.as_ref(),
+            &mut results,
+            query.as_deref(),
+            vector.as_deref(),
+            model,
+            &highlight_options,
+        )
+        .await?;
+        highlight::project(&mut results, &fields);
         Ok(QueryResponse {
-            latency_ms: 0,
             results,
+            total_hits,
+            next_cursor,
+            debug: debug.then(|| {
+                rewrite.unwrap_or_else(|| QueryDebug {
+                    query: original_query,
+                    ..Default::default()
+                })
+            }),
+            aggregations,
+            aggregations_truncated,
+            ..Default::default()
         })
     }
 
+    /// Searches every query variant and keeps the best score per document.
+    /// `fetched` is the longest single result list so pagination can tell
+    /// whether the window was exhausted.
+    async fn retrieve_variants(
+        &self,
+        payload: QueryRequest,
+        variants: &[String],
+        vector: Option<Vec<f32>>,
+        model: Option<&str>,
+    ) -> Result<(Vec<QueryResult>, usize), anyhow::Error> {
+        if variants.len() <= 1 {
+            let results = hybrid_query(self.braindb.as_ref(), payload, vector, model).await?;
+            let fetched = results.len();
+            return Ok((results, fetched));
+        }
+        let top_k = payload.top_k;
+        let lists = futures_util::future::try_join_all(variants.iter().map(|variant| {
+            let mut request = payload.clone();
+            request.query = Some(variant.clone());
+            hybrid_query(self.braindb.as_ref(), request, vector.clone(), model)
+        }))
+        .await?;
+        let fetched = lists.iter().map(Vec::len).max().unwrap_or(0);
+        let mut best: HashMap<String, QueryResult> = HashMap::new();
+        for result in lists.into_iter().flatten() {
+            match best.get(&result.id) {
+                Some(existing) if existing.score >= result.score => {}
+                _ => {
+                    best.insert(result.id.clone(), result);
+                }
+            }
+        }
+        let mut merged: Vec<QueryResult> = best.into_values().collect();
+        pagination::stable_sort(&mut merged);
+        merged.truncate(top_k);
+        Ok((merged, fetched))
+    }
+
+    /// Applies spelling correction, synonyms and LLM expansion to a
+    /// full-text query. `collection` is the name the client used, `scoped`
+    /// the tenant-scoped one.
+    async fn rewrite_query(
+        &self,
+        config: &BrainmlConfig,
+        collection: &str,
+        scoped: &str,
+        query: &str,
+    ) -> Result<QueryDebug, anyhow::Error> {
+        let settings = &config.query_rewrite;
+        let (rewritten, corrections) = if settings.typo_tolerance.enabled {
+            let dictionary = self.term_dictionary(scoped).await?;
+            rewrite::correct_query(query, &dictionary, &settings.typo_tolerance)
+        } else {
+            (query.to_string(), Vec::new())
+        };
+        let synonyms = settings
+            .synonyms
+            .get(collection)
+            .map(|groups| rewrite::synonym_variants(&rewritten, groups))
+            .unwrap_or_default();
+        let expansions = if settings.expansion.enabled {
+            self.expand_query(config, &rewritten).await
+        } else {
+            Vec::new()
+        };
+        let variants =
+            rewrite::merge_variants(&rewritten, synonyms.into_iter().chain(expansions.clone()));
+        Ok(QueryDebug {
+            query: Some(query.to_string()),
+            rewritten_query: Some(rewritten),
+            corrections,
+            variants,
+            expansions,
+        })
+    }
+
+    /// Alternative phrasings from the chat model. Expansion is best effort:
+    /// failures are logged and the query runs without it.
+    async fn expand_query(&self, config: &BrainmlConfig, query: &str) -> Vec<String> {
+        let settings = &config.query_rewrite.expansion;
+        let request = ChatRequest {
+            messages: vec![
+                ChatMessage {
+                    role: "system".into(),
+                    content: rewrite::EXPANSION_PROMPT
+                        .replace("{max}", &settings.max_expansions.to_string()),
+                },
+                ChatMessage {
+                    role: "user".into(),
+                    content: query.to_string(),
+                },
+            ],
+            model: settings.model.clone(),
+        };
+        match self.llm.chat(request).await {
+            Ok(response) => rewrite::parse_expansions(
+                response.content().unwrap_or_default(),
+                settings.max_expansions,
+            ),
+            Err(err) => {
+                warn!(error = %err, "query expansion failed");
+                Vec::new()
+            }
+        }
+    }
+
+    /// Term dictionary of a scoped collection, scanned on first use after
+    /// each write.
+    async fn term_dictionary(&self, scoped: &str) -> Result<Arc<TermDictionary>, anyhow::Error> {
+        if let Some(dictionary) = self.dictionaries.get(scoped) {
+            return Ok(dictionary);
+        }
+        let generation = self.dictionaries.generation();
+        let mut dictionary = TermDictionary::default();
+        let mut offset = 0;
+        loop {
+            let batch = self
+                .braindb
+                .scan_documents(ScanDocumentsRequest {
+                    collection: scoped.to_string(),
+                    offset,
+                    limit: DICTIONARY_SCAN_BATCH,
+                })
+                .await?;
+            if batch.is_empty() {
+                break;
+            }
+            offset += batch.len();
+            for record in &batch {
+                dictionary.add(&record.text);
+            }
+        }
+        let dictionary = Arc::new(dictionary);
+        self.dictionaries
+            .insert(scoped, generation, dictionary.clone());
+        Ok(dictionary)
+    }
+
+    /// Runs every qrels query through `process_query` and scores the ranked
+    /// ids. The corpus must already be indexed in `qrels.collection`.
+    #[instrument(skip_all, fields(collection = %qrels.collection, k = options.k))]
+    pub async fn evaluate(
+        &self,
+        qrels: &Qrels,
+        options: &EvaluationOptions,
+    ) -> Result<EvaluationReport, anyhow::Error> {
+        if options.k == 0 {
+            return Err(EvaluationError::InvalidK.into());
+        }
+        qrels.validate()?;
+        let mut per_query = Vec::with_capacity(qrels.queries.len());
+        for query in &qrels.queries {
+            let response = self
+                .process_query(QueryRequest {
+                    collection: qrels.collection.clone(),
+                    query: Some(query.query.clone()),
+                    top_k: options.k,
+                    hybrid: options.hybrid,
+                    filters: query.filters.clone(),
+                    fields: Vec::new(),
+                    ..Default::default()
+                })
+                .await?;
+            let retrieved = response
+                .results
+                .into_iter()
+                .map(|result| result.id)
+                .collect();
+            per_query.push(evaluation::score_query(
+                &query.id,
+                retrieved,
+                &query.relevant.grades(),
+                options.k,
+            ));
+        }
+        Ok(evaluation::summarize(options.k, per_query))
+    }
+
+    /// Records whether a result of a logged query was relevant.
+    #[instrument(skip_all, fields(tenant = %tenant.id, collection = %request.collection))]
+    pub async fn process_feedback_for(
+        &self,
+        tenant: &TenantContext,
+        request: FeedbackRequest,
+    ) -> Result<FeedbackResponse, anyhow::Error> {
+        if !self.config.load().analytics.enabled {
+            return Err(AnalyticsError::Disabled.into());
+        }
+        tenant.scope(&request.collection)?;
+        self.log_event(AnalyticsEvent::Feedback(FeedbackLogEntry {
+            query_id: request.query_id,
+            timestamp: Utc::now(),
+            tenant: tenant.id.clone(),
+            collection: request.collection,
+            document_id: request.document_id,
+            relevant: request.relevant,
+        }))
+        .await;
+        Ok(FeedbackResponse { recorded: true })
+    }
+
+    /// Computes click-through and zero-result rates from the query log.
+    #[instrument(skip_all, fields(tenant = %tenant.id))]
+    pub async fn process_analytics_report_for(
+        &self,
+        tenant: &TenantContext,
+        request: AnalyticsReportRequest,
+    ) -> Result<AnalyticsReport, anyhow::Error> {
+        let config = self.config.load().analytics.clone();
+        if !config.enabled {
+            return Err(AnalyticsError::Disabled.into());
+        }
+        let log = self.analytics.clone();
+        let events = tokio::task::spawn_blocking(move || log.read(&config)).await??;
+        Ok(crate::core::analytics::report(
+            &events, &tenant.id, &request,
+        ))
+    }
+
+    /// Appends to the query log. Failures are logged rather than returned so
+    /// analytics never fail a query.
+    async fn log_event(&self, event: AnalyticsEvent) {
+        let config = self.config.load().analytics.clone();
+        let log = self.analytics.clone();
+        let written = tokio::task::spawn_blocking(move || log.append(&config, &event)).await;
+        match written {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => warn!(error = %err, "failed to append to query log"),
+            Err(err) => warn!(error = %err, "query log writer panicked"),
+        }
+    }
+
+    /// Returns the collections visible to `tenant` together with per-tenant
+    /// usage. The default tenant acts as the operator and sees usage for
+    /// every tenant namespace.
+    #[instrument(skip_all, fields(tenant = %tenant.id))]
+    pub async fn process_stats_for(
+        &self,
+        tenant: &TenantContext,
+    ) -> Result<StatsResponse, anyhow::Error> {
+        let raw = self.braindb.stats().await?;
+        let collections = visible_collections(tenant, raw.collections.clone());
+        let tenants = if tenant.is_default() && self.config.load().tenancy.enabled {
+            let mut grouped: BTreeMap<String, Vec<CollectionStats>> = BTreeMap::new();
+            grouped.entry(tenant.id.clone()).or_default();
+            for stats in raw.collections {
+                let (owner, name) = match split_namespace(&stats.name) {
+                    Some((owner, name)) => (owner.to_string(), name.to_string()),
+                    None => (tenant.id.clone(), stats.name.clone()),
+                };
+                grouped.entry(owner).or_default().push(CollectionStats {
+                    name,
+                    document_count: stats.document_count,
+                    embedding_dimensions: stats.embedding_dimensions,
+                    quantization: stats.quantization,
+                    vector_bytes: stats.vector_bytes,
+                    memory_bytes: stats.memory_bytes,
+                });
+            }
+            grouped
+                .iter()
+                .map(|(owner, collections)| self.tenants.stats(owner, collections))
+                .collect()
+        } else {
+            vec![self.tenants.stats(&tenant.id, &collections)]
+        };
+        Ok(StatsResponse {
+            collections,
+            tenants,
+        })
+    }
+
+    pub async fn process_admin(&self, request: AdminRequest) -> Result<AdminStatus, anyhow::Error> {
+        self.process_admin_for(&self.default_tenant(), request)
+            .await
+    }
+
+    /// Applies an administrative action and reports the resulting status.
+    /// Alias changes apply to the calling tenant's collections; the actions
+    /// that affect every tenant are reserved for the operator.
+    #[instrument(skip_all, fields(tenant = %tenant.id, action = ?request.action))]
+    pub async fn process_admin_for(
+        &self,
+        tenant: &TenantContext,
+        request: AdminRequest,
+    ) -> Result<AdminStatus, anyhow::Error> {
+        if request.action == AdminAction::Status {
+            return Ok(self.admin_status_for(tenant));
+        }
+        let action = serde_json::to_value(request.action)?;
+        let event =
+            AuditEvent::new(tenant, "brainml.admin").action(action.as_str().unwrap_or_default());
+        let result = self.apply_admin(tenant, request).await;
+        self.audit(event, result.as_ref().err()).await;
+        result
+    }
+
+    async fn apply_admin(
+        &self,
+        tenant: &TenantContext,
+        request: AdminRequest,
+    ) -> Result<AdminStatus, anyhow::Error> {
+        if request.action.is_operator_only() && !tenant.is_default() {
+            return Err(TenantError::Forbidden(
+                "this admin action is reserved for the operator".to_string(),
+            )
+            .into());
+        }
+        match request.action {
+            AdminAction::Status => {}
+            AdminAction::MigrateEmbeddings => {
+                self.start_migration(tenant, request.batch_size);
+            }
+            AdminAction::ReloadConfig => {
+                self.reload_config()?;
+            }
+            AdminAction::SyncDirectories => {
+                let state = self.clone();
+                tokio::spawn(async move { state.sync_all().await });
+            }
+            AdminAction::UpdateAliases => {
+                self.update_aliases(tenant, &request.aliases).await?;
+            }
+            AdminAction::ExpireDocuments => {
+                let state = self.clone();
+                tokio::spawn(async move { state.expire_documents().await });
+            }
+        }
+        Ok(self.admin_status_for(tenant))
+    }
+
+    /// Applies alias changes as one atomic swap. Targets must be existing
+    /// collections of the tenant.
+    async fn update_aliases(
+        &self,
+        tenant: &TenantContext,
+        updates: &[AliasUpdate],
+    ) -> Result<(), anyhow::Error> {
+        let changes = updates
+            .iter()
+            .map(|update| {
+                Ok(AliasChange {
+                    alias: tenant.scope(&update.alias)?,
+                    collection: update
+                        .collection
+                        .as_deref()
+                        .map(|collection| tenant.scope(collection))
+                        .transpose()?,
+                })
+            })
+            .collect::<Result<Vec<_>, anyhow::Error>>()?;
+        let collections: HashSet<String> = self
+            .braindb
+            .stats()
+            .await?
+            .collections
+            .into_iter()
+            .map(|stats| stats.name)
+            .collect();
+        let path = self.config.load().aliases.path.clone();
+        let aliases = self.aliases.clone();
+        tokio::task::spawn_blocking(move || aliases.update(&changes, &collections, &path))
+            .await??;
+        info!(changes = updates.len(), "collection aliases updated");
+        Ok(())
+    }
+
+    /// Aliases of `tenant`'s collections under their tenant-visible names.
+    pub fn aliases_for(&self, tenant: &TenantContext) -> Vec<CollectionAlias> {
+        self.aliases
+            .list()
+            .into_iter()
+            .filter_map(|alias| {
+                Some(CollectionAlias {
+                    alias: tenant.unscope(&alias.alias)?.to_string(),
+                    collection: tenant.unscope(&alias.collection)?.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// Re-reads the configuration source and swaps it in. A changed
+    /// embedding model kicks off the embedding migration when enabled.
+    #[instrument(skip_all)]
+    pub fn reload_config(&self) -> Result<Arc<BrainmlConfig>, anyhow::Error> {
+        let previous = self.config.reload()?;
+        let current = self.config.load();
+        let models_changed = previous.embedding_model != current.embedding_model
+            || previous.vector_fields != current.vector_fields;
+        if models_changed && current.migration.auto_start {
+            self.start_migration(&self.default_tenant(), None);
+        }
+        Ok(current)
+    }
+
+    pub fn admin_status(&self) -> AdminStatus {
+        self.admin_status_for(&self.default_tenant())
+    }
+
+    /// Service status. Tenants other than the default one see only their
+    /// own migrations, sync reports and aliases, and none of the active
+    /// config, the last expiry sweep or the limiter state, which all span
+    /// every tenant.
+    pub fn admin_status_for(&self, tenant: &TenantContext) -> AdminStatus {
+        let operator = tenant.is_default();
+        let migrations = self
+            .migrator
+            .progress()
+            .into_iter()
+            .filter_map(|mut progress| {
+                if !operator {
+                    progress.collection = tenant.unscope(&progress.collection)?.to_string();
+                }
+                Some(progress)
+            })
+            .collect();
+        let sync = self
+            .sync
+            .reports()
+            .into_iter()
+            .filter(|report| operator || report.tenant.as_ref() == Some(&tenant.id))
+            .collect();
+        AdminStatus {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            uptime_seconds: self.start_time.elapsed().as_secs(),
+            capabilities: admin::capabilities(),
+            migration_running: self.migrator.is_running(),
+            migrations,
+            config_generation: self.config.generation(),
+            active_config: if operator {
+                self.config.load().redacted()
+            } else {
+                serde_json::Value::Null
+            },
+            sync_running: self.sync.is_running(),
+            sync,
+            query_cache: self.cache.stats(),
+            limits: operator.then(|| self.limiter.stats()),
+            aliases: self.aliases_for(tenant),
+            expiry_running: self.expiry.is_running(),
+            expiry: self.expiry.last_report().filter(|_| operator),
+        }
+    }
+
+    /// Starts the background re-embedding of the tenant's stale records
+    /// unless a migration is already running.
+    pub fn start_migration(&self, tenant: &TenantContext, batch_size: Option<usize>) -> bool {
+        let config = self.config.load();
+        let batch_size = batch_size.unwrap_or(config.migration.batch_size);
+        self.migrator
+            .start(
+                self.braindb.clone(),
+                self.llm.clone(),
+                self.cache.clone(),
+                config.as_ref().clone(),
+                tenant.clone(),
+                batch_size,
+            )
+            .is_some()
+    }
+
+    /// Deletes documents older than their collection's TTL unless a sweep is
+    /// already running. Returns `None` when one was.
+    pub async fn expire_documents(&self) -> Option<ExpiryReport> {
+        if !self.expiry.begin() {
+            return None;
+        }
+        let report = match self.sweep_expired().await {
+            Ok(report) => report,
+            Err(err) => {
+                error!(error = %err, "expiry sweep failed");
+                ExpiryReport {
+                    collections: 0,
+                    scanned: 0,
+                    expired: 0,
+                    error: Some(err.to_string()),
+                    finished_at: Utc::now(),
+                }
+            }
+        };
+        self.expiry.record(report.clone());
+        self.expiry.finish();
+        Some(report)
+    }
+
+    #[instrument(skip_all)]
+    async fn sweep_expired(&self) -> Result<ExpiryReport, anyhow::Error> {
+        let config = self.config.load();
+        let settings = &config.expiry;
+        let mut report = ExpiryReport {
+            collections: 0,
+            scanned: 0,
+            expired: 0,
+            error: None,
+            finished_at: Utc::now(),
+        };
+        if settings.ttl_secs.is_empty() {
+            return Ok(report);
+        }
+        let now = Utc::now();
+        let aliases = self.aliases.list();
+        for collection in self.braindb.stats().await?.collections {
+            let Some(ttl) = expiry::ttl_for(&settings.ttl_secs, &collection.name, &aliases) else {
+                continue;
+            };
+            report.collections += 1;
+            let cutoff = now - chrono::Duration::seconds(ttl.min(i64::MAX as u64) as i64);
+            let mut ids = Vec::new();
+            let mut offset = 0;
+            loop {
+                let batch = self
+                    .braindb
+                    .scan_documents(ScanDocumentsRequest {
+                        collection: collection.name.clone(),
+                        offset,
+                        limit: expiry::SCAN_BATCH,
+                    })
+                    .await?;
+                offset += batch.len();
+                report.scanned += batch.len();
+                ids.extend(expiry::expired_ids(&batch, settings.field, cutoff));
+                if batch.len() < expiry::SCAN_BATCH {
+                    break;
+                }
+            }
+            if ids.is_empty() {
+                continue;
+            }
+            report.expired += ids.len();
+            let event = owner_event(&config, &collection.name, "brainml.expiry")
+                .action("delete")
+                .documents(ids.len() as u64);
+            let deleted = self
+                .braindb
+                .delete_documents(DeleteDocumentsRequest {
+                    collection: collection.name.clone(),
+                    ids,
+                })
+                .await;
+            self.audit(event, deleted.as_ref().err()).await;
+            deleted?;
+            self.dictionaries.invalidate(&collection.name);
+            self.cache.invalidate(&collection.name);
+        }
+        report.finished_at = Utc::now();
+        info!(
+            collections = report.collections,
+            expired = report.expired,
+            "expiry sweep finished"
+        );
+        Ok(report)
+    }
+
+    /// Runs one sync pass over every configured source unless a pass is
+    /// already in progress.
+    pub async fn sync_all(&self) -> Vec<SyncReport> {
+        if !self.sync.begin() {
+            return Vec::new();
+        }
+        let sources = self.config.load().sync.sources.clone();
+        let mut reports = Vec::new();
+        for source in &sources {
+            let report = match self.sync_source(source).await {
+                Ok(report) => report,
+                Err(err) => {
+                    error!(root = %source.path.display(), error = %err, "directory sync failed");
+                    SyncReport {
+                        collection: source.collection.clone(),
+                        root: source.path.display().to_string(),
+                        tenant: source.tenant.clone(),
+                        scanned: 0,
+                        unchanged: 0,
+                        upserted: 0,
+                        deleted: 0,
+                        failed: 0,
+                        error: Some(err.to_string()),
+                        finished_at: Utc::now(),
+                    }
+                }
+            };
+            self.sync.record(report.clone());
+            reports.push(report);
+        }
+        self.sync.finish();
+        reports
+    }
+
+    /// Brings a collection in line with a directory: changed files are
+    /// re-ingested, chunks of removed or shrunk files are deleted and the
+    /// manifest is persisted so unchanged files are skipped after a restart.
+    #[instrument(skip_all, fields(root = %source.path.display(), collection = %source.collection))]
+    pub async fn sync_source(&self, source: &SyncSource) -> Result<SyncReport, anyhow::Error> {
+        let config = self.config.load();
+        let tenant = match &source.tenant {
+            Some(id) => TenantContext::named(&config.tenancy, id)?,
+            None => TenantContext::default_tenant(&config.tenancy),
+        };
+        let manifest_path = sync::manifest_path(&config.sync.manifest_dir, source);
+        let mut manifest = SyncManifest::load(&manifest_path, source)?;
+        let scanned = {
+            let source = source.clone();
+            tokio::task::spawn_blocking(move || sync::scan(&source)).await??
+        };
+        let plan = sync::plan(&manifest, scanned);
+        let mut report = SyncReport {
+            collection: source.collection.clone(),
+            root: source.path.display().to_string(),
+            tenant: source.tenant.clone(),
+            scanned: plan.changed.len() + plan.unchanged,
+            unchanged: plan.unchanged,
+            upserted: 0,
+            deleted: 0,
+            failed: 0,
+            error: None,
+            finished_at: Utc::now(),
+        };
+        let applied = self
+            .apply_sync_plan(&tenant, source, plan, &mut manifest, &mut report)
+            .await;
+        manifest.save(&manifest_path)?;
+        applied?;
+        report.finished_at = Utc::now();
+        info!(
+            scanned = report.scanned,
+            upserted = report.upserted,
+            deleted = report.deleted,
+            failed = report.failed,
+            "directory sync finished"
+        );
+        Ok(report)
+    }
+
+    async fn apply_sync_plan(
+        &self,
+        tenant: &TenantContext,
+        source: &SyncSource,
+        plan: SyncPlan,
+        manifest: &mut SyncManifest,
+        report: &mut SyncReport,
+    ) -> Result<(), anyhow::Error> {
+        let request = IngestRequest {
+            collection: source.collection.clone(),
+            embed: source.embed,
+            fts: source.fts,
+            ..Default::default()
+        };
+        for file in plan.changed {
+            let previous = manifest
+                .files
+                .get(&file.path)
+                .map(|entry| entry.chunks)
+                .unwrap_or(0);
+            let bytes = tokio::fs::read(&file.absolute).await?;
+            let ingested = self
+                .ingest_files_for(
+                    tenant,
+                    &request,
+                    vec![SourceFile {
+                        path: file.path.clone(),
+                        content_type: None,
+                        bytes,
+                    }],
+                )
+                .await;
+            let chunks = match ingested {
+                Ok(response) => {
+                    report.upserted += 1;
+                    response.documents
+                }
+                Err(err) if err.is::<IngestError>() => {
+                    warn!(path = %file.path, error = %err, "skipping file that cannot be ingested");
+                    report.failed += 1;
+                    0
+                }
+                Err(err) => return Err(err),
+            };
+            self.delete_chunks(tenant, &source.collection, &file.path, chunks..previous)
+                .await?;
+            manifest.files.insert(
+                file.path,
+                ManifestEntry {
+                    hash: file.hash,
+                    chunks,
+                },
+            );
+        }
+        for (path, chunks) in plan.removed {
+            self.delete_chunks(tenant, &source.collection, &path, 0..chunks)
+                .await?;
+            manifest.files.remove(&path);
+            report.deleted += 1;
+        }
+        Ok(())
+    }
+
+    async fn delete_chunks(
+        &self,
+        tenant: &TenantContext,
+        collection: &str,
+        path: &str,
+        chunks: std::ops::Range<usize>,
+    ) -> Result<(), anyhow::Error> {
+        if chunks.is_empty() {
+            return Ok(());
+        }
+        let event = AuditEvent::new(tenant, "brainml.sync")
+            .action("delete")
+            .collection(collection)
+            .documents(chunks.len() as u64);
+        let physical = self.writable_collection(tenant, collection)?;
+        let deleted = self
+            .braindb
+            .delete_documents(DeleteDocumentsRequest {
+                collection: physical.clone(),
+                ids: chunks.map(|idx| chunk_id(path, idx)).collect(),
+            })
+            .await;
+        self.audit(event, deleted.as_ref().err()).await;
+        deleted?;
+        self.dictionaries.invalidate(&physical);
+        self.cache.invalidate(&physical);
+        Ok(())
+    }
+
     pub async fn process_train(
         &self,
         request: TrainRequest,
     ) -> Result<TrainResponse, anyhow::Error> {
-        self.pipeline.train(request).await
+        self.process_train_for(&self.default_tenant(), request)
+            .await
+    }
+
+    #[instrument(skip_all, fields(tenant = %tenant.id, pipeline = %request.pipeline))]
+    pub async fn process_train_for(
+        &self,
+        tenant: &TenantContext,
+        request: TrainRequest,
+    ) -> Result<TrainResponse, anyhow::Error> {
+        let event = AuditEvent::new(tenant, "brainml.train").action(request.pipeline.as_str());
+        let result = self.pipeline.train(request).await;
+        self.audit(event, result.as_ref().err()).await;
+        result
+    }
+
+    /// Returns audit log entries, newest first. Tenants other than the
+    /// default one only see their own entries and no chain verification.
+    #[instrument(skip_all, fields(tenant = %tenant.id))]
+    pub async fn process_audit_for(
+        &self,
+        tenant: &TenantContext,
+        mut request: AuditQueryRequest,
+    ) -> Result<AuditQueryResponse, anyhow::Error> {
+        let config = self.config.load().audit.clone();
+        if !config.enabled {
+            return Err(AuditError::Disabled.into());
+        }
+        let log = self.audit.clone();
+        let (entries, chain) = tokio::task::spawn_blocking(move || log.read(&config)).await??;
+        if !chain.valid {
+            warn!(
+                broken_at = chain.broken_at,
+                reason = chain.reason.as_deref(),
+                "audit log chain does not verify"
+            );
+        }
+        if !tenant.is_default() {
+            request.tenant = Some(tenant.id.clone());
+        }
+        let (entries, total) = crate::core::audit::query(entries, &request);
+        Ok(AuditQueryResponse {
+            entries,
+            total,
+            chain: tenant.is_default().then_some(chain),
+        })
+    }
+
+    /// Appends to the audit log when it is enabled. Failures are logged
+    /// rather than returned: by now the operation has already happened.
+    async fn audit(&self, event: AuditEvent, error: Option<impl ToString>) {
+        let config = self.config.load().audit.clone();
+        if !config.enabled {
+            return;
+        }
+        let error = error.map(|err| err.to_string());
+        let log = self.audit.clone();
+        let written = tokio::task::spawn_blocking(move || log.append(&config, event, error)).await;
+        match written {
+            Ok(Ok(_)) => {}
+            Ok(Err(err)) => error!(error = %err, "failed to append to audit log"),
+            Err(err) => error!(error = %err, "audit log writer panicked"),
+        }
+    }
+}
+
+/// An audit event for a background operation on a physical collection,
+/// attributed to the tenant whose namespace holds it.
+fn owner_event(config: &BrainmlConfig, physical: &str, capability: &str) -> AuditEvent {
+    let (tenant, name) = match split_namespace(physical) {
+        Some((owner, name)) => (
+            TenantContext::named(&config.tenancy, owner)
+                .unwrap_or_else(|_| TenantContext::default_tenant(&config.tenancy)),
+            name,
+        ),
+        None => (TenantContext::default_tenant(&config.tenancy), physical),
+    };
+    AuditEvent::new(&tenant, capability).collection(name)
+}
+
+fn visible_collections(
+    tenant: &TenantContext,
+    collections: Vec<crate::adapters::braindb::CollectionStats>,
+) -> Vec<CollectionStats> {
+    collections
+        .into_iter()
+        .filter_map(|stats| {
+            tenant.unscope(&stats.name).map(|name| CollectionStats {
+                name: name.to_string(),
+                document_count: stats.document_count,
+                embedding_dimensions: stats.embedding_dimensions,
+                quantization: stats.quantization,
+                vector_bytes: stats.vector_bytes,
+                memory_bytes: stats.memory_bytes,
+            })
+        })
+        .collect()
+}
+
+fn selected_vector_fields(
+    config: &BrainmlConfig,
+    requested: &[String],
+) -> Result<Vec<String>, anyhow::Error> {
+    if requested.is_empty() {
+        return Ok(config.vector_fields.keys().cloned().collect());
+    }
+    for field in requested {
+        if !config.vector_fields.contains_key(field) {
+            return Err(UnknownVectorField(field.clone()).into());
+        }
     }
+    Ok(requested.to_vec())
 }
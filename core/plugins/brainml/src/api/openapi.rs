@@ -1,6 +1,17 @@
+use crate::core::errors::{ErrorCode, ErrorDetail};
+use crate::core::ingest::FileFormat;
+use crate::core::quantization::Quantization;
 use crate::core::schema::{
-    AdminStatus, HealthResponse, IndexRequest, QueryRequest, QueryResponse, TrainRequest,
-    TrainResponse,
+    AdminAction, AdminRequest, AdminStatus, Aggregation, AggregationBucket, AggregationResult,
+    AliasUpdate, AnalyticsReport, AnalyticsReportRequest, AuditChainStatus, AuditEntry,
+    AuditOutcome, AuditQueryRequest, AuditQueryResponse, AuditRecord, BulkChunk, BulkEvent,
+    BulkLineError, BulkOptions, BulkProgress, BulkRequest, BulkStreamRef, BulkSummary, CacheStats,
+    CalendarInterval, CollectionAlias, CollectionAnalytics, CollectionStats, DateField, DateRange,
+    DecayFunction, DiversifyOptions, DocumentField, ExpiryReport, FeedbackRequest,
+    FeedbackResponse, HealthResponse, HighlightOptions, IndexRequest, IngestFile, IngestRequest,
+    IngestResponse, IngestedFile, LimitStats, MigrationProgress, MigrationState, QueryDebug,
+    QueryRequest, QueryResponse, RecencyBoost, Snippet, SnippetKind, StatsResponse, SyncReport,
+    TenantStats, TermCorrection, TotalHits, TotalHitsRelation, TrainRequest, TrainResponse,
 };
 use axum::routing::get;
 use utoipa::{OpenApi, ToSchema};
@@ -10,7 +21,7 @@ use utoipa_swagger_ui::SwaggerUi;
 #[openapi(
     paths(),
     components(
-        schemas(IndexRequest, QueryRequest, QueryResponse, TrainRequest, TrainResponse, AdminStatus, HealthResponse)
+        schemas(IndexRequest, QueryRequest, QueryResponse, TrainRequest, TrainResponse, AdminStatus, HealthResponse, StatsResponse, CollectionStats, TenantStats, AdminRequest, AdminAction, MigrationProgress, MigrationState, DocumentField, HighlightOptions, Snippet, SnippetKind, TotalHits, TotalHitsRelation, DiversifyOptions, IngestRequest, IngestFile, IngestResponse, IngestedFile, FileFormat, SyncReport, FeedbackRequest, FeedbackResponse, AnalyticsReportRequest, AnalyticsReport, CollectionAnalytics, QueryDebug, TermCorrection, CacheStats, LimitStats, ErrorCode, ErrorDetail, AliasUpdate, CollectionAlias, DateField, DateRange, DecayFunction, RecencyBoost, ExpiryReport, Aggregation, CalendarInterval, AggregationResult, AggregationBucket, BulkOptions, BulkRequest, BulkChunk, BulkStreamRef, BulkEvent, BulkProgress, BulkSummary, BulkLineError, Quantization, AuditQueryRequest, AuditQueryResponse, AuditEntry, AuditRecord, AuditOutcome, AuditChainStatus)
     ),
     tags((name = "brainml", description = "BrainML plugin API"))
 )]
@@ -0,0 +1,34 @@
+use crate::util::trace_context::{self, TRACEPARENT};
+use axum::extract::Request;
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+use tracing::{field, info_span, Instrument};
+
+/// Middleware running each request in a server span that continues the
+/// caller's `traceparent` header. The response carries the span's own
+/// `traceparent` so callers can find it.
+pub async fn propagate(request: Request, next: Next) -> Response {
+    let traceparent = request
+        .headers()
+        .get(TRACEPARENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let span = info_span!(
+        "http.request",
+        otel.name = %format_args!("{} {}", request.method(), request.uri().path()),
+        otel.kind = "server",
+        http.method = %request.method(),
+        http.target = %request.uri().path(),
+        http.status_code = field::Empty,
+        traceparent = traceparent.as_deref(),
+    );
+    let mut response = next.run(request).instrument(span.clone()).await;
+    span.record("http.status_code", response.status().as_u16());
+    if let Some(context) = span.in_scope(trace_context::current) {
+        if let Ok(value) = HeaderValue::from_str(&context.to_string()) {
+            response.headers_mut().insert(TRACEPARENT, value);
+        }
+    }
+    response
+}
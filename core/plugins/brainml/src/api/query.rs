@@ -1,4 +1,5 @@
 use super::errors::ApiError;
+use super::tenant::Tenant;
 use super::AppState;
 use crate::core::schema::{QueryRequest, QueryResponse};
 use axum::extract::State;
@@ -21,11 +22,15 @@ pub fn routes() -> axum::Router<AppState> {
 #[instrument(skip_all, fields(collection = %payload.collection, top_k = payload.top_k))]
 pub async fn query_handler(
     State(state): State<AppState>,
+    Tenant(tenant): Tenant,
     Json(mut payload): Json<QueryRequest>,
 ) -> Result<Json<QueryResponse>, ApiError> {
     if payload.collection.trim().is_empty() {
         return Err(ApiError::Invalid("collection is required".into()));
     }
-    let response = state.process_query(payload).await.map_err(ApiError::from)?;
+    let response = state
+        .process_query_for(&tenant, payload)
+        .await
+        .map_err(ApiError::from)?;
     Ok(Json(response))
 }
@@ -0,0 +1,43 @@
+use super::errors::ApiError;
+use super::AppState;
+use crate::core::tenancy::{TenantContext, TenantError};
+use async_trait::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::header::AUTHORIZATION;
+use axum::http::request::Parts;
+use axum::http::HeaderMap;
+
+/// Resolves the calling tenant from the bearer token listed in
+/// `tenancy.tokens`; the configured tenant header may only repeat it.
+pub struct Tenant(pub TenantContext);
+
+/// Resolves the tenant of an HTTP request, see [`TenantContext::resolve`].
+pub fn tenant_from_headers(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<TenantContext, TenantError> {
+    let config = state.config.load();
+    let config = &config.tenancy;
+    let explicit = headers
+        .get(config.header.as_str())
+        .and_then(|value| value.to_str().ok());
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    TenantContext::resolve(config, explicit, token)
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for Tenant {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        tenant_from_headers(state, &parts.headers)
+            .map(Tenant)
+            .map_err(|err| ApiError::from(anyhow::Error::new(err)))
+    }
+}
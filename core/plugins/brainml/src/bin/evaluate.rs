@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Result;
+use clap::Parser;
+
+use brainml::adapters::braindb::NullBraindbClient;
+use brainml::adapters::llm::NullLlmClient;
+use brainml::api::AppState;
+use brainml::core::config::{BrainmlConfig, BrainmlConfigLoader};
+use brainml::core::evaluation::{EvaluationOptions, Qrels};
+
+/// Scores retrieval quality offline: indexes the qrels corpus into the
+/// in-memory store, runs every query and prints recall@k, MRR and nDCG as
+/// JSON.
+#[derive(Debug, Parser)]
+#[command(name = "brainml-evaluate", version)]
+struct Cli {
+    /// Qrels file with queries, judged document ids and the corpus.
+    #[arg(long)]
+    qrels: PathBuf,
+    /// Config whose ranking settings are evaluated; defaults otherwise.
+    #[arg(long)]
+    config: Option<PathBuf>,
+    #[arg(short, long, default_value_t = 10)]
+    k: usize,
+    /// Embed queries and documents and fuse vector scores.
+    #[arg(long)]
+    hybrid: bool,
+    /// Exit with status 1 when mean recall@k falls below this value.
+    #[arg(long)]
+    min_recall: Option<f64>,
+    /// Exit with status 1 when MRR falls below this value.
+    #[arg(long)]
+    min_mrr: Option<f64>,
+    /// Exit with status 1 when mean nDCG@k falls below this value.
+    #[arg(long)]
+    min_ndcg: Option<f64>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let config = match &cli.config {
+        Some(path) => BrainmlConfigLoader::load(path)?,
+        None => BrainmlConfig::default(),
+    };
+    let qrels = Qrels::load(&cli.qrels)?;
+    let state = AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        Arc::new(NullLlmClient),
+        config,
+    );
+    if let Some(request) = qrels.index_request(cli.hybrid) {
+        state.process_index(request).await?;
+    }
+    let report = state
+        .evaluate(
+            &qrels,
+            &EvaluationOptions {
+                k: cli.k,
+                hybrid: cli.hybrid,
+            },
+        )
+        .await?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+
+    let failures: Vec<String> = [
+        ("recall", report.recall, cli.min_recall),
+        ("mrr", report.mrr, cli.min_mrr),
+        ("ndcg", report.ndcg, cli.min_ndcg),
+    ]
+    .into_iter()
+    .filter_map(|(name, value, min)| {
+        min.filter(|min| value < *min)
+            .map(|min| format!("{name} {value:.4} < {min:.4}"))
+    })
+    .collect();
+    if !failures.is_empty() {
+        eprintln!("evaluation below threshold: {}", failures.join(", "));
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,291 @@
+use crate::core::schema::{
+    Aggregation, AggregationBucket, AggregationResult, CalendarInterval, DocumentRecord,
+    QueryResult,
+};
+use chrono::{DateTime, Datelike, Duration, DurationRound, NaiveDate, NaiveTime, Utc};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use thiserror::Error;
+
+/// Upper bound on aggregations per query.
+pub const MAX_AGGREGATIONS: usize = 20;
+
+#[derive(Debug, Error)]
+pub enum AggregationError {
+    #[error("at most {max} aggregations per query, got {requested}")]
+    TooMany { requested: usize, max: usize },
+    #[error("aggregation {name}: {reason}")]
+    Invalid { name: String, reason: String },
+}
+
+pub fn validate(requests: &BTreeMap<String, Aggregation>) -> Result<(), AggregationError> {
+    if requests.len() > MAX_AGGREGATIONS {
+        return Err(AggregationError::TooMany {
+            requested: requests.len(),
+            max: MAX_AGGREGATIONS,
+        });
+    }
+    for (name, request) in requests {
+        let invalid = |reason: &str| AggregationError::Invalid {
+            name: name.clone(),
+            reason: reason.to_string(),
+        };
+        let field = match request {
+            Aggregation::Terms { field, size } => {
+                if *size == 0 {
+                    return Err(invalid("size must be at least 1"));
+                }
+                field
+            }
+            Aggregation::Histogram { field, interval } => {
+                if !interval.is_finite() || *interval <= 0.0 {
+                    return Err(invalid("interval must be positive"));
+                }
+                field
+            }
+            Aggregation::DateHistogram { field, .. }
+            | Aggregation::Min { field }
+            | Aggregation::Max { field }
+            | Aggregation::Avg { field } => field,
+        };
+        if field.is_empty() {
+            return Err(invalid("field must not be empty"));
+        }
+    }
+    Ok(())
+}
+
+/// Computes every requested aggregation over `results`.
+pub fn aggregate(
+    requests: &BTreeMap<String, Aggregation>,
+    results: &[QueryResult],
+) -> Result<BTreeMap<String, AggregationResult>, AggregationError> {
+    validate(requests)?;
+    Ok(requests
+        .iter()
+        .map(|(name, request)| (name.clone(), run(request, results)))
+        .collect())
+}
+
+fn run(request: &Aggregation, results: &[QueryResult]) -> AggregationResult {
+    let documents = results.iter().map(|result| &result.document);
+    match request {
+        Aggregation::Terms { field, size } => terms(documents, field, *size),
+        Aggregation::Histogram { field, interval } => histogram(documents, field, *interval),
+        Aggregation::DateHistogram { field, interval } => {
+            date_histogram(documents, field, *interval)
+        }
+        Aggregation::Min { field } => metric(documents, field, |values| {
+            values.iter().copied().reduce(f64::min)
+        }),
+        Aggregation::Max { field } => metric(documents, field, |values| {
+            values.iter().copied().reduce(f64::max)
+        }),
+        Aggregation::Avg { field } => metric(documents, field, |values| {
+            (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+        }),
+    }
+}
+
+fn terms<'a>(
+    documents: impl Iterator<Item = &'a DocumentRecord>,
+    field: &str,
+    size: usize,
+) -> AggregationResult {
+    let mut doc_count = 0;
+    let mut counts: HashMap<String, (Value, usize)> = HashMap::new();
+    for document in documents {
+        let values: Vec<&Value> = field_values(document, field)
+            .into_iter()
+            .filter(|value| !value.is_object())
+            .collect();
+        if values.is_empty() {
+            continue;
+        }
+        doc_count += 1;
+        let mut seen = Vec::new();
+        for value in values {
+            let key = term_key(value);
+            if seen.contains(&key) {
+                continue;
+            }
+            seen.push(key.clone());
+            counts.entry(key).or_insert_with(|| (value.clone(), 0)).1 += 1;
+        }
+    }
+    let mut buckets: Vec<(String, Value, usize)> = counts
+        .into_iter()
+        .map(|(key, (value, count))| (key, value, count))
+        .collect();
+    buckets.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+    buckets.truncate(size);
+    AggregationResult {
+        doc_count,
+        buckets: Some(
+            buckets
+                .into_iter()
+                .map(|(_, key, doc_count)| AggregationBucket { key, doc_count })
+                .collect(),
+        ),
+        value: None,
+    }
+}
+
+fn term_key(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn histogram<'a>(
+    documents: impl Iterator<Item = &'a DocumentRecord>,
+    field: &str,
+    interval: f64,
+) -> AggregationResult {
+    bucketed(documents, |document| {
+        numbers(document, field)
+            .into_iter()
+            .map(|value| (value / interval).floor() as i64)
+            .collect()
+    })
+    .map_keys(|slot| Value::from(slot as f64 * interval))
+}
+
+fn date_histogram<'a>(
+    documents: impl Iterator<Item = &'a DocumentRecord>,
+    field: &str,
+    interval: CalendarInterval,
+) -> AggregationResult {
+    bucketed(documents, |document| {
+        timestamps(document, field)
+            .into_iter()
+            .map(|at| truncate(at, interval).timestamp())
+            .collect()
+    })
+    .map_keys(|secs| {
+        let start = DateTime::from_timestamp(secs, 0).unwrap_or_default();
+        Value::from(start.to_rfc3339())
+    })
+}
+
+/// Buckets keyed by an ordered slot; each document counts once per slot.
+struct Bucketed {
+    doc_count: usize,
+    slots: BTreeMap<i64, usize>,
+}
+
+impl Bucketed {
+    fn map_keys(self, key: impl Fn(i64) -> Value) -> AggregationResult {
+        AggregationResult {
+            doc_count: self.doc_count,
+            buckets: Some(
+                self.slots
+                    .into_iter()
+                    .map(|(slot, doc_count)| AggregationBucket {
+                        key: key(slot),
+                        doc_count,
+                    })
+                    .collect(),
+            ),
+            value: None,
+        }
+    }
+}
+
+fn bucketed<'a>(
+    documents: impl Iterator<Item = &'a DocumentRecord>,
+    slots_of: impl Fn(&DocumentRecord) -> Vec<i64>,
+) -> Bucketed {
+    let mut bucketed = Bucketed {
+        doc_count: 0,
+        slots: BTreeMap::new(),
+    };
+    for document in documents {
+        let mut slots = slots_of(document);
+        if slots.is_empty() {
+            continue;
+        }
+        slots.sort_unstable();
+        slots.dedup();
+        bucketed.doc_count += 1;
+        for slot in slots {
+            *bucketed.slots.entry(slot).or_insert(0) += 1;
+        }
+    }
+    bucketed
+}
+
+fn metric<'a>(
+    documents: impl Iterator<Item = &'a DocumentRecord>,
+    field: &str,
+    reduce: impl Fn(&[f64]) -> Option<f64>,
+) -> AggregationResult {
+    let mut doc_count = 0;
+    let mut values = Vec::new();
+    for document in documents {
+        let numbers = numbers(document, field);
+        if !numbers.is_empty() {
+            doc_count += 1;
+            values.extend(numbers);
+        }
+    }
+    AggregationResult {
+        doc_count,
+        buckets: None,
+        value: reduce(&values),
+    }
+}
+
+/// Values of a metadata field, with arrays flattened. An exact key match
+/// wins over a dotted path into nested objects.
+fn field_values<'a>(document: &'a DocumentRecord, field: &str) -> Vec<&'a Value> {
+    let value = document.metadata.get(field).or_else(|| {
+        field
+            .split('.')
+            .try_fold(&document.metadata, |value, key| value.get(key))
+    });
+    match value {
+        None | Some(Value::Null) => Vec::new(),
+        Some(Value::Array(items)) => items.iter().filter(|item| !item.is_null()).collect(),
+        Some(value) => vec![value],
+    }
+}
+
+fn numbers(document: &DocumentRecord, field: &str) -> Vec<f64> {
+    field_values(document, field)
+        .into_iter()
+        .filter_map(Value::as_f64)
+        .collect()
+}
+
+fn timestamps(document: &DocumentRecord, field: &str) -> Vec<DateTime<Utc>> {
+    match field {
+        "created_at" => vec![document.created_at],
+        "updated_at" => vec![document.updated_at],
+        _ => field_values(document, field)
+            .into_iter()
+            .filter_map(Value::as_str)
+            .filter_map(|text| DateTime::parse_from_rfc3339(text).ok())
+            .map(|at| at.with_timezone(&Utc))
+            .collect(),
+    }
+}
+
+/// Start of the calendar interval containing `at`, in UTC.
+pub fn truncate(at: DateTime<Utc>, interval: CalendarInterval) -> DateTime<Utc> {
+    let date = at.date_naive();
+    let day = match interval {
+        CalendarInterval::Minute => {
+            return at.duration_trunc(Duration::minutes(1)).unwrap_or(at);
+        }
+        CalendarInterval::Hour => return at.duration_trunc(Duration::hours(1)).unwrap_or(at),
+        CalendarInterval::Day => date,
+        CalendarInterval::Week => {
+            date - Duration::days(i64::from(date.weekday().num_days_from_monday()))
+        }
+        CalendarInterval::Month => date.with_day(1).unwrap_or(date),
+        CalendarInterval::Year => NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap_or(date),
+    };
+    day.and_time(NaiveTime::MIN).and_utc()
+}
@@ -0,0 +1,141 @@
+use crate::core::schema::CollectionAlias;
+use parking_lot::{Mutex, RwLock};
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AliasError {
+    #[error("invalid alias {alias}: {reason}")]
+    Invalid { alias: String, reason: String },
+    #[error("alias {0} does not exist")]
+    UnknownAlias(String),
+    #[error("collection {0} does not exist")]
+    UnknownCollection(String),
+    #[error("failed to persist aliases: {0}")]
+    Persist(#[from] io::Error),
+}
+
+/// One change in an alias update, in physical collection names. A `None`
+/// target removes the alias.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AliasChange {
+    pub alias: String,
+    pub collection: Option<String>,
+}
+
+/// Names that resolve to a physical collection. Keys and targets are
+/// tenant-scoped physical names, so tenants never see each other's aliases.
+#[derive(Clone, Default)]
+pub struct AliasRegistry {
+    aliases: Arc<RwLock<BTreeMap<String, String>>>,
+    /// Serialises updates, so the file is written without holding up
+    /// readers of `aliases`.
+    updates: Arc<Mutex<()>>,
+}
+
+impl AliasRegistry {
+    /// Replaces the aliases with those persisted at `path`. A missing file
+    /// leaves no aliases.
+    pub fn load(&self, path: &Path) -> io::Result<()> {
+        let aliases = match std::fs::read(path) {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(err),
+        };
+        *self.aliases.write() = aliases;
+        Ok(())
+    }
+
+    /// The collection `name` points at, or `name` itself when it is not an
+    /// alias.
+    pub fn resolve(&self, name: &str) -> String {
+        self.aliases
+            .read()
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    pub fn is_alias(&self, name: &str) -> bool {
+        self.aliases.read().contains_key(name)
+    }
+
+    /// Applies every change or none. Readers see either the old or the new
+    /// set of aliases, never a mix, which makes repointing an alias a
+    /// zero-downtime swap. Aliases may not shadow an existing collection or
+    /// point at another alias. The result is written to `path` before it
+    /// becomes visible; this blocks, but queries resolving aliases in the
+    /// meantime are not held up.
+    pub fn update(
+        &self,
+        changes: &[AliasChange],
+        collections: &HashSet<String>,
+        path: &Path,
+    ) -> Result<(), AliasError> {
+        let _update = self.updates.lock();
+        let mut updated = self.aliases.read().clone();
+        for change in changes {
+            let invalid = |reason: &str| AliasError::Invalid {
+                alias: change.alias.clone(),
+                reason: reason.to_string(),
+            };
+            if change.alias.trim().is_empty() {
+                return Err(invalid("name is empty"));
+            }
+            match &change.collection {
+                Some(target) => {
+                    if collections.contains(&change.alias) {
+                        return Err(invalid("a collection with this name exists"));
+                    }
+                    if !collections.contains(target) {
+                        return Err(AliasError::UnknownCollection(target.clone()));
+                    }
+                    updated.insert(change.alias.clone(), target.clone());
+                }
+                None => {
+                    if updated.remove(&change.alias).is_none() {
+                        return Err(AliasError::UnknownAlias(change.alias.clone()));
+                    }
+                }
+            }
+        }
+        if let Some((alias, _)) = updated
+            .iter()
+            .find(|(_, target)| updated.contains_key(*target))
+        {
+            return Err(AliasError::Invalid {
+                alias: alias.clone(),
+                reason: "aliases cannot point at other aliases".into(),
+            });
+        }
+        save(&updated, path)?;
+        *self.aliases.write() = updated;
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<CollectionAlias> {
+        self.aliases
+            .read()
+            .iter()
+            .map(|(alias, collection)| CollectionAlias {
+                alias: alias.clone(),
+                collection: collection.clone(),
+            })
+            .collect()
+    }
+}
+
+fn save(aliases: &BTreeMap<String, String>, path: &Path) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    let contents = serde_json::to_vec_pretty(aliases)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(&tmp, path)
+}
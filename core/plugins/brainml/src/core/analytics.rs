@@ -0,0 +1,220 @@
+use crate::core::config::AnalyticsConfig;
+use crate::core::schema::{
+    AnalyticsReport, AnalyticsReportRequest, CollectionAnalytics, QueryFilter,
+};
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AnalyticsError {
+    #[error("query analytics are disabled")]
+    Disabled,
+}
+
+/// One line of the query log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AnalyticsEvent {
+    Query(QueryLogEntry),
+    Feedback(FeedbackLogEntry),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryLogEntry {
+    pub query_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub tenant: String,
+    pub collection: String,
+    #[serde(default)]
+    pub query: Option<String>,
+    #[serde(default)]
+    pub filters: Vec<QueryFilter>,
+    pub result_ids: Vec<String>,
+    pub latency_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedbackLogEntry {
+    pub query_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub tenant: String,
+    pub collection: String,
+    pub document_id: String,
+    pub relevant: bool,
+}
+
+/// Appends analytics events to a size-rotated NDJSON file. Writes are
+/// serialised so rotation never interleaves with an append.
+#[derive(Clone, Default)]
+pub struct QueryLog {
+    lock: Arc<Mutex<()>>,
+}
+
+impl QueryLog {
+    pub fn append(&self, config: &AnalyticsConfig, event: &AnalyticsEvent) -> io::Result<()> {
+        let mut line = serde_json::to_vec(event)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        line.push(b'\n');
+        let _guard = self.lock.lock();
+        if let Some(parent) = config.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let size = match std::fs::metadata(&config.path) {
+            Ok(meta) => meta.len(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => 0,
+            Err(err) => return Err(err),
+        };
+        if size > 0 && size + line.len() as u64 > config.max_file_bytes {
+            rotate(config)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.path)?;
+        file.write_all(&line)
+    }
+
+    /// Reads every retained event, oldest file first. Lines that fail to
+    /// parse, such as a write cut short by a crash, are skipped.
+    pub fn read(&self, config: &AnalyticsConfig) -> io::Result<Vec<AnalyticsEvent>> {
+        let _guard = self.lock.lock();
+        let mut events = Vec::new();
+        for index in (0..config.max_files).rev() {
+            let file = match std::fs::File::open(rotated_path(&config.path, index)) {
+                Ok(file) => file,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+            for line in io::BufReader::new(file).lines() {
+                if let Ok(event) = serde_json::from_str(&line?) {
+                    events.push(event);
+                }
+            }
+        }
+        Ok(events)
+    }
+}
+
+/// `queries.ndjson` for the active file, `queries.ndjson.<n>` for older ones.
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        return path.to_path_buf();
+    }
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{index}"));
+    PathBuf::from(name)
+}
+
+fn rotate(config: &AnalyticsConfig) -> io::Result<()> {
+    let oldest = rotated_path(&config.path, config.max_files.saturating_sub(1));
+    match std::fs::remove_file(&oldest) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err),
+    }
+    for index in (0..config.max_files.saturating_sub(1)).rev() {
+        let from = rotated_path(&config.path, index);
+        if from.exists() {
+            std::fs::rename(&from, rotated_path(&config.path, index + 1))?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct Totals {
+    queries: usize,
+    zero_results: usize,
+    latency_ms: u64,
+    clicked: HashSet<String>,
+    relevant: usize,
+    irrelevant: usize,
+}
+
+/// Aggregates `tenant`'s events into per-collection rates. Feedback only
+/// counts when it refers to a query inside the report window.
+pub fn report(
+    events: &[AnalyticsEvent],
+    tenant: &str,
+    request: &AnalyticsReportRequest,
+) -> AnalyticsReport {
+    let wanted = |entry_tenant: &str, collection: &str| {
+        entry_tenant == tenant
+            && request
+                .collection
+                .as_deref()
+                .is_none_or(|wanted| wanted == collection)
+    };
+    let mut totals: BTreeMap<String, Totals> = BTreeMap::new();
+    let mut queries: HashMap<&str, &str> = HashMap::new();
+    for event in events {
+        let AnalyticsEvent::Query(entry) = event else {
+            continue;
+        };
+        if !wanted(&entry.tenant, &entry.collection)
+            || request.since.is_some_and(|since| entry.timestamp < since)
+        {
+            continue;
+        }
+        queries.insert(&entry.query_id, &entry.collection);
+        let collection = totals.entry(entry.collection.clone()).or_default();
+        collection.queries += 1;
+        collection.latency_ms += entry.latency_ms;
+        if entry.result_ids.is_empty() {
+            collection.zero_results += 1;
+        }
+    }
+    for event in events {
+        let AnalyticsEvent::Feedback(entry) = event else {
+            continue;
+        };
+        if !wanted(&entry.tenant, &entry.collection) {
+            continue;
+        }
+        let Some(collection) = queries
+            .get(entry.query_id.as_str())
+            .and_then(|collection| totals.get_mut(*collection))
+        else {
+            continue;
+        };
+        if entry.relevant {
+            collection.relevant += 1;
+            collection.clicked.insert(entry.query_id.clone());
+        } else {
+            collection.irrelevant += 1;
+        }
+    }
+    let rate = |count: usize, total: usize| {
+        if total == 0 {
+            0.0
+        } else {
+            count as f64 / total as f64
+        }
+    };
+    AnalyticsReport {
+        collections: totals
+            .into_iter()
+            .map(|(collection, totals)| CollectionAnalytics {
+                collection,
+                queries: totals.queries,
+                zero_result_queries: totals.zero_results,
+                zero_result_rate: rate(totals.zero_results, totals.queries),
+                clicked_queries: totals.clicked.len(),
+                click_through_rate: rate(totals.clicked.len(), totals.queries),
+                relevant_feedback: totals.relevant,
+                irrelevant_feedback: totals.irrelevant,
+                mean_latency_ms: if totals.queries == 0 {
+                    0.0
+                } else {
+                    totals.latency_ms as f64 / totals.queries as f64
+                },
+            })
+            .collect(),
+    }
+}
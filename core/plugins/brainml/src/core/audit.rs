@@ -0,0 +1,364 @@
+use crate::core::config::AuditConfig;
+use crate::core::schema::{
+    AuditChainStatus, AuditEntry, AuditOutcome, AuditQueryRequest, AuditRecord,
+};
+use crate::core::tenancy::TenantContext;
+use chrono::Utc;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use thiserror::Error;
+use tracing::warn;
+
+/// `prevHash` of the first entry.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Entries returned by a query without a `limit`.
+const DEFAULT_QUERY_LIMIT: usize = 100;
+
+#[derive(Debug, Error)]
+pub enum AuditError {
+    #[error("the audit log is disabled")]
+    Disabled,
+}
+
+/// An operation to record; the log assigns its position, time and hashes.
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    pub tenant: String,
+    pub caller: Option<String>,
+    pub capability: String,
+    pub action: Option<String>,
+    pub collection: Option<String>,
+    pub documents: Option<u64>,
+}
+
+impl AuditEvent {
+    pub fn new(tenant: &TenantContext, capability: &str) -> Self {
+        Self {
+            tenant: tenant.id.clone(),
+            caller: tenant.caller().map(str::to_string),
+            capability: capability.to_string(),
+            action: None,
+            collection: None,
+            documents: None,
+        }
+    }
+
+    pub fn action(mut self, action: impl Into<String>) -> Self {
+        self.action = Some(action.into());
+        self
+    }
+
+    pub fn collection(mut self, collection: impl Into<String>) -> Self {
+        self.collection = Some(collection.into());
+        self
+    }
+
+    pub fn documents(mut self, documents: u64) -> Self {
+        self.documents = Some(documents);
+        self
+    }
+}
+
+/// Hex SHA-256 of the record as written to the log.
+pub fn record_hash(record: &AuditRecord) -> String {
+    let bytes = serde_json::to_vec(record).unwrap_or_default();
+    Sha256::digest(&bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Position and hash of the newest entry of a log file.
+struct ChainHead {
+    path: PathBuf,
+    seq: u64,
+    hash: String,
+}
+
+/// The newest entry as last written to the head file beside the log.
+#[derive(Debug, Serialize, Deserialize)]
+struct RecordedHead {
+    seq: u64,
+    hash: String,
+}
+
+/// Appends audit entries to an NDJSON file. Each entry carries the hash of
+/// the one before it, so editing, removing or reordering entries breaks the
+/// chain from that point on. The newest entry is kept in memory so an
+/// append does not re-read the file, and in a head file next to the log so
+/// that removing the newest entries is detected as well.
+#[derive(Clone, Default)]
+pub struct AuditLog {
+    head: Arc<Mutex<Option<ChainHead>>>,
+}
+
+impl AuditLog {
+    pub fn append(
+        &self,
+        config: &AuditConfig,
+        event: AuditEvent,
+        error: Option<String>,
+    ) -> io::Result<AuditEntry> {
+        let mut head = self.head.lock();
+        if !matches!(&*head, Some(head) if head.path == config.path) {
+            *head = Some(load_head(&config.path)?);
+        }
+        let head = head.as_mut().expect("chain head loaded above");
+        let record = AuditRecord {
+            seq: head.seq + 1,
+            timestamp: Utc::now(),
+            tenant: event.tenant,
+            caller: event.caller,
+            capability: event.capability,
+            action: event.action,
+            collection: event.collection,
+            documents: event.documents,
+            outcome: if error.is_some() {
+                AuditOutcome::Failure
+            } else {
+                AuditOutcome::Success
+            },
+            error,
+            prev_hash: head.hash.clone(),
+        };
+        let entry = AuditEntry {
+            hash: record_hash(&record),
+            record,
+        };
+        let mut line = serde_json::to_vec(&entry)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        line.push(b'\n');
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.path)?;
+        file.write_all(&line)?;
+        file.sync_data()?;
+        head.seq = entry.record.seq;
+        head.hash = entry.hash.clone();
+        write_recorded_head(
+            &config.path,
+            &RecordedHead {
+                seq: head.seq,
+                hash: head.hash.clone(),
+            },
+        )?;
+        Ok(entry)
+    }
+
+    /// Reads every entry that parses and verifies the chain over all lines
+    /// and against the head file.
+    pub fn read(&self, config: &AuditConfig) -> io::Result<(Vec<AuditEntry>, AuditChainStatus)> {
+        // Held so a concurrent append is never read half written.
+        let _head = self.head.lock();
+        let text = match std::fs::read_to_string(&config.path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+        let lines: Vec<&str> = text.lines().collect();
+        let mut status = verify_chain(&lines);
+        if status.valid {
+            if let Some(recorded) = read_recorded_head(&config.path)? {
+                verify_head(&lines, &recorded, &mut status);
+            }
+        }
+        let entries = lines
+            .iter()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        Ok((entries, status))
+    }
+}
+
+/// Checks that each line is an entry whose hash matches its record and
+/// whose `prevHash` and `seq` follow the line before it.
+pub fn verify_chain(lines: &[&str]) -> AuditChainStatus {
+    let mut status = AuditChainStatus {
+        valid: true,
+        entries: lines.len() as u64,
+        ..Default::default()
+    };
+    let mut prev_hash = GENESIS_HASH.to_string();
+    for (idx, line) in lines.iter().enumerate() {
+        let seq = idx as u64 + 1;
+        let reason = match serde_json::from_str::<AuditEntry>(line) {
+            Err(err) => Some(format!("unreadable entry: {err}")),
+            Ok(entry) if entry.record.seq != seq => {
+                Some(format!("expected entry {seq}, found {}", entry.record.seq))
+            }
+            Ok(entry) if entry.record.prev_hash != prev_hash => {
+                Some("previous hash does not match the entry before".to_string())
+            }
+            Ok(entry) if record_hash(&entry.record) != entry.hash => {
+                Some("entry does not match its hash".to_string())
+            }
+            Ok(entry) => {
+                prev_hash = entry.hash;
+                None
+            }
+        };
+        if let Some(reason) = reason {
+            status.valid = false;
+            status.broken_at = Some(seq);
+            status.reason = Some(reason);
+            return status;
+        }
+    }
+    if !lines.is_empty() {
+        status.head_hash = Some(prev_hash);
+    }
+    status
+}
+
+/// Checks a verified chain against the head file. An entry may follow the
+/// recorded head when the process stopped between appending it and
+/// updating the file, but the recorded entry itself must still be there.
+fn verify_head(lines: &[&str], recorded: &RecordedHead, status: &mut AuditChainStatus) {
+    if recorded.seq == 0 {
+        return;
+    }
+    let reason = match lines.get(recorded.seq as usize - 1) {
+        None => Some(format!(
+            "log ends at entry {} but its head file records entry {}",
+            lines.len(),
+            recorded.seq
+        )),
+        Some(line) => serde_json::from_str::<AuditEntry>(line)
+            .ok()
+            .filter(|entry| entry.hash != recorded.hash)
+            .map(|_| "entry does not match the recorded head".to_string()),
+    };
+    if let Some(reason) = reason {
+        status.valid = false;
+        status.broken_at = Some(recorded.seq.min(lines.len() as u64 + 1));
+        status.reason = Some(reason);
+    }
+}
+
+/// Entries matching `request`, newest first, and how many matched before
+/// the limit.
+pub fn query(entries: Vec<AuditEntry>, request: &AuditQueryRequest) -> (Vec<AuditEntry>, usize) {
+    let mut matching: Vec<AuditEntry> = entries
+        .into_iter()
+        .filter(|entry| {
+            let record = &entry.record;
+            matches(&request.tenant, Some(&record.tenant))
+                && matches(&request.capability, Some(&record.capability))
+                && matches(&request.collection, record.collection.as_ref())
+                && request
+                    .outcome
+                    .is_none_or(|outcome| record.outcome == outcome)
+                && request.since.is_none_or(|since| record.timestamp >= since)
+                && request.until.is_none_or(|until| record.timestamp < until)
+        })
+        .collect();
+    let total = matching.len();
+    matching.reverse();
+    matching.truncate(request.limit.unwrap_or(DEFAULT_QUERY_LIMIT));
+    (matching, total)
+}
+
+fn matches(wanted: &Option<String>, value: Option<&String>) -> bool {
+    wanted.as_ref().is_none_or(|wanted| value == Some(wanted))
+}
+
+/// Reads the newest entry of the log at `path`. A final line without a
+/// newline is a write cut short by a crash and is dropped, so the next
+/// append continues the chain from the last complete entry. When the head
+/// file records a later entry than the log ends with, the chain continues
+/// from the recorded one instead so that the missing entries stay visible
+/// as a break.
+fn load_head(path: &Path) -> io::Result<ChainHead> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    let complete = bytes
+        .iter()
+        .rposition(|byte| *byte == b'\n')
+        .map_or(0, |idx| idx + 1);
+    if complete < bytes.len() {
+        warn!(
+            path = %path.display(),
+            dropped = bytes.len() - complete,
+            "truncating torn audit log entry"
+        );
+        file.set_len(complete as u64)?;
+        file.sync_all()?;
+    }
+    let mut head = ChainHead {
+        path: path.to_path_buf(),
+        seq: 0,
+        hash: GENESIS_HASH.to_string(),
+    };
+    if let Some(last) = bytes[..complete]
+        .split(|byte| *byte == b'\n')
+        .rfind(|line| !line.is_empty())
+    {
+        let entry: AuditEntry = serde_json::from_slice(last).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("last audit log entry is unreadable: {err}"),
+            )
+        })?;
+        head.seq = entry.record.seq;
+        head.hash = entry.hash;
+    }
+    if let Some(recorded) = read_recorded_head(path)? {
+        if recorded.seq > head.seq {
+            warn!(
+                path = %path.display(),
+                log = head.seq,
+                recorded = recorded.seq,
+                "audit log ends before its recorded head"
+            );
+            head.seq = recorded.seq;
+            head.hash = recorded.hash;
+        }
+    }
+    Ok(head)
+}
+
+/// `audit.ndjson` keeps its head in `audit.ndjson.head`.
+fn head_path(log: &Path) -> PathBuf {
+    let mut name = log.as_os_str().to_owned();
+    name.push(".head");
+    PathBuf::from(name)
+}
+
+fn read_recorded_head(log: &Path) -> io::Result<Option<RecordedHead>> {
+    let bytes = match std::fs::read(head_path(log)) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    serde_json::from_slice(&bytes)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Replaces the head file through a synced temporary file, so it always
+/// holds either the previous head or the new one.
+fn write_recorded_head(log: &Path, head: &RecordedHead) -> io::Result<()> {
+    let path = head_path(log);
+    let tmp = path.with_extension("head.tmp");
+    let bytes =
+        serde_json::to_vec(head).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let mut file = std::fs::File::create(&tmp)?;
+    file.write_all(&bytes)?;
+    file.sync_all()?;
+    std::fs::rename(&tmp, &path)
+}
@@ -0,0 +1,243 @@
+use crate::core::schema::{BulkLineError, BulkOptions, BulkProgress, BulkSummary, DocumentInput};
+use crate::core::tenancy::TenantContext;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Rejected lines listed in a summary; later ones are only counted.
+pub const MAX_REPORTED_ERRORS: usize = 100;
+
+#[derive(Debug, Error)]
+pub enum BulkError {
+    #[error("unknown bulk stream {0}")]
+    UnknownStream(Uuid),
+    #[error("chunk {got} is out of order, expected {expected}")]
+    OutOfOrder { expected: u64, got: u64 },
+    #[error("chunk of {documents} documents exceeds the maximum of {max}")]
+    ChunkTooLarge { documents: usize, max: usize },
+    #[error("collection is required")]
+    MissingCollection,
+}
+
+pub enum Line {
+    Document(DocumentInput),
+    Rejected(BulkLineError),
+}
+
+/// Splits an NDJSON byte stream into documents, holding at most one
+/// partial line. Blank lines are skipped; lines longer than
+/// `max_line_bytes` are rejected without being buffered.
+pub struct LineReader {
+    buffer: Vec<u8>,
+    max_line_bytes: usize,
+    line: u64,
+    oversized: bool,
+}
+
+impl LineReader {
+    pub fn new(max_line_bytes: usize) -> Self {
+        Self {
+            buffer: Vec::new(),
+            max_line_bytes,
+            line: 0,
+            oversized: false,
+        }
+    }
+
+    pub fn feed(&mut self, mut chunk: &[u8]) -> Vec<Line> {
+        let mut lines = Vec::new();
+        while let Some(end) = chunk.iter().position(|&byte| byte == b'\n') {
+            self.append(&chunk[..end]);
+            lines.extend(self.complete());
+            chunk = &chunk[end + 1..];
+        }
+        self.append(chunk);
+        lines
+    }
+
+    /// Parses a trailing line that has no newline.
+    pub fn finish(&mut self) -> Option<Line> {
+        if self.buffer.is_empty() && !self.oversized {
+            return None;
+        }
+        self.complete()
+    }
+
+    fn append(&mut self, bytes: &[u8]) {
+        if self.oversized {
+            return;
+        }
+        if self.buffer.len() + bytes.len() > self.max_line_bytes {
+            self.oversized = true;
+            self.buffer = Vec::new();
+            return;
+        }
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    fn complete(&mut self) -> Option<Line> {
+        self.line += 1;
+        let line = self.line;
+        let rejected = |message: String| Some(Line::Rejected(BulkLineError { line, message }));
+        if std::mem::take(&mut self.oversized) {
+            return rejected(format!("line exceeds {} bytes", self.max_line_bytes));
+        }
+        let bytes = std::mem::take(&mut self.buffer);
+        if bytes.iter().all(u8::is_ascii_whitespace) {
+            return None;
+        }
+        match serde_json::from_slice::<DocumentInput>(&bytes) {
+            Ok(document) => Some(Line::Document(document)),
+            Err(err) => rejected(err.to_string()),
+        }
+    }
+}
+
+/// Running totals of one bulk ingest.
+#[derive(Debug, Clone, Default)]
+pub struct BulkTally {
+    pub received: u64,
+    pub indexed: u64,
+    pub failed: u64,
+    pub batches: u64,
+    pub errors: Vec<BulkLineError>,
+}
+
+impl BulkTally {
+    pub fn reject(&mut self, error: BulkLineError) {
+        self.received += 1;
+        self.failed += 1;
+        if self.errors.len() < MAX_REPORTED_ERRORS {
+            self.errors.push(error);
+        }
+    }
+
+    pub fn record_batch(&mut self, documents: usize) {
+        self.received += documents as u64;
+        self.indexed += documents as u64;
+        self.batches += 1;
+    }
+
+    pub fn record_failed_batch(&mut self, documents: usize) {
+        self.received += documents as u64;
+        self.failed += documents as u64;
+    }
+
+    pub fn progress(&self, stream_id: Option<Uuid>) -> BulkProgress {
+        BulkProgress {
+            stream_id,
+            received: self.received,
+            indexed: self.indexed,
+            failed: self.failed,
+            batches: self.batches,
+        }
+    }
+
+    pub fn summary(
+        &self,
+        stream_id: Option<Uuid>,
+        collection: &str,
+        error: Option<String>,
+        started: Instant,
+    ) -> BulkSummary {
+        BulkSummary {
+            stream_id,
+            collection: collection.to_string(),
+            received: self.received,
+            indexed: self.indexed,
+            failed: self.failed,
+            batches: self.batches,
+            errors: self.errors.clone(),
+            error,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+/// A chunked ingest opened over the bus.
+pub struct BulkStream {
+    pub tenant: TenantContext,
+    pub options: BulkOptions,
+    pub next_seq: u64,
+    pub tally: BulkTally,
+    pub started: Instant,
+    pub last_active: Instant,
+}
+
+/// Open bus streams. Each stream has its own lock so that its chunks are
+/// indexed one at a time while other streams proceed.
+#[derive(Clone, Default)]
+pub struct BulkStreams {
+    streams: Arc<Mutex<HashMap<Uuid, Arc<tokio::sync::Mutex<BulkStream>>>>>,
+}
+
+impl BulkStreams {
+    pub fn open(&self, tenant: TenantContext, options: BulkOptions) -> Uuid {
+        let id = Uuid::new_v4();
+        let now = Instant::now();
+        self.streams.lock().insert(
+            id,
+            Arc::new(tokio::sync::Mutex::new(BulkStream {
+                tenant,
+                options,
+                next_seq: 0,
+                tally: BulkTally::default(),
+                started: now,
+                last_active: now,
+            })),
+        );
+        id
+    }
+
+    /// Looks up a stream opened by `tenant`. Other tenants' streams are
+    /// reported as unknown.
+    pub async fn get(
+        &self,
+        id: Uuid,
+        tenant: &TenantContext,
+    ) -> Result<Arc<tokio::sync::Mutex<BulkStream>>, BulkError> {
+        let stream = self
+            .streams
+            .lock()
+            .get(&id)
+            .cloned()
+            .ok_or(BulkError::UnknownStream(id))?;
+        if stream.lock().await.tenant != *tenant {
+            return Err(BulkError::UnknownStream(id));
+        }
+        Ok(stream)
+    }
+
+    pub async fn close(
+        &self,
+        id: Uuid,
+        tenant: &TenantContext,
+    ) -> Result<Arc<tokio::sync::Mutex<BulkStream>>, BulkError> {
+        let stream = self.get(id, tenant).await?;
+        self.streams.lock().remove(&id);
+        Ok(stream)
+    }
+
+    /// Drops streams idle for longer than `idle`. Streams busy indexing a
+    /// chunk are kept.
+    pub fn purge_idle(&self, idle: Duration) -> usize {
+        let mut streams = self.streams.lock();
+        let before = streams.len();
+        streams.retain(|_, stream| match stream.try_lock() {
+            Ok(stream) => stream.last_active.elapsed() <= idle,
+            Err(_) => true,
+        });
+        before - streams.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.streams.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use chrono::Utc;
 use futures_util::{SinkExt, StreamExt};
@@ -11,9 +11,16 @@ use tokio::{
 };
 use tokio_tungstenite::WebSocketStream;
 use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream};
-use tracing::{error, info, instrument};
+use tracing::{error, info, info_span, instrument, Instrument};
 use uuid::Uuid;
 
+use crate::core::errors::{ErrorCode, ErrorDetail};
+use crate::core::limits::Admission;
+use crate::util::trace_context;
+
+/// How long an outbound invocation waits for the peer's response.
+pub const INVOKE_TIMEOUT: Duration = Duration::from_secs(120);
+
 type WebSocketWriter =
     futures_util::stream::SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
 
@@ -40,6 +47,8 @@ pub enum OutgoingMessage {
         requestId: Uuid,
         capability: String,
         payload: serde_json::Value,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        traceparent: Option<String>,
     },
     #[serde(rename = "log")]
     Log {
@@ -59,7 +68,9 @@ pub enum OutgoingMessage {
         requestId: Uuid,
         success: bool,
         data: Option<serde_json::Value>,
-        error: Option<String>,
+        error: Option<ErrorDetail>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        traceparent: Option<String>,
     },
 }
 
@@ -72,13 +83,18 @@ pub enum IncomingMessage {
         capability: String,
         payload: serde_json::Value,
         token: Option<String>,
+        #[serde(default)]
+        traceparent: Option<String>,
     },
+    /// `error` is an [`ErrorDetail`] or, from older peers, a plain string.
     #[serde(rename = "response")]
     Response {
         requestId: Uuid,
         success: bool,
         data: Option<serde_json::Value>,
-        error: Option<String>,
+        error: Option<serde_json::Value>,
+        #[serde(default)]
+        traceparent: Option<String>,
     },
 }
 
@@ -86,13 +102,19 @@ pub enum IncomingMessage {
 pub enum OutboundCommand {
     Respond {
         request_id: Uuid,
-        payload: Result<serde_json::Value, String>,
+        payload: Result<serde_json::Value, ErrorDetail>,
+        traceparent: Option<String>,
     },
     Invoke {
         request_id: Uuid,
         capability: String,
         payload: serde_json::Value,
-        responder: oneshot::Sender<Result<serde_json::Value, String>>,
+        responder: oneshot::Sender<Result<serde_json::Value, ErrorDetail>>,
+        traceparent: Option<String>,
+    },
+    /// Forgets an invocation that will not wait for its response.
+    Cancel {
+        request_id: Uuid,
     },
     Log {
         level: String,
@@ -104,12 +126,60 @@ pub enum OutboundCommand {
     },
 }
 
-pub type HandlerFuture =
-    std::pin::Pin<Box<dyn std::future::Future<Output = Result<serde_json::Value, String>> + Send>>;
+/// Cancels an outbound invocation when dropped before [`Self::settle`], so
+/// calls that time out or are abandoned do not leave their responder in the
+/// bus's pending map.
+pub struct PendingInvoke {
+    request_id: Uuid,
+    sender: mpsc::Sender<OutboundCommand>,
+    settled: bool,
+}
+
+impl PendingInvoke {
+    pub fn new(request_id: Uuid, sender: mpsc::Sender<OutboundCommand>) -> Self {
+        Self {
+            request_id,
+            sender,
+            settled: false,
+        }
+    }
+
+    /// The response arrived and the bus has already dropped the entry.
+    pub fn settle(mut self) {
+        self.settled = true;
+    }
+}
+
+impl Drop for PendingInvoke {
+    fn drop(&mut self) {
+        if self.settled {
+            return;
+        }
+        let command = OutboundCommand::Cancel {
+            request_id: self.request_id,
+        };
+        if let Err(mpsc::error::TrySendError::Full(command)) = self.sender.try_send(command) {
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                let sender = self.sender.clone();
+                runtime.spawn(async move {
+                    let _ = sender.send(command).await;
+                });
+            }
+        }
+    }
+}
+
+pub type HandlerFuture = std::pin::Pin<
+    Box<dyn std::future::Future<Output = Result<serde_json::Value, ErrorDetail>> + Send>,
+>;
 
 pub type Handler =
     dyn Fn(Uuid, String, serde_json::Value, Option<String>) -> HandlerFuture + Send + Sync;
 
+/// Admission control run for each incoming request before a task is
+/// spawned for it. Receives the capability and the caller's token.
+pub type Admit = dyn Fn(&str, Option<&str>) -> Result<Admission, ErrorDetail> + Send + Sync;
+
 pub fn channel() -> (
     mpsc::Sender<OutboundCommand>,
     mpsc::Receiver<OutboundCommand>,
@@ -127,6 +197,7 @@ pub async fn start_bus(
     mut receiver: mpsc::Receiver<OutboundCommand>,
     sender: mpsc::Sender<OutboundCommand>,
     handlers: Arc<HashMap<String, Arc<Handler>>>,
+    admit: Arc<Admit>,
 ) -> Result<JoinHandle<()>, BusError> {
     let (ws_stream, _) = connect_async(bus_url)
         .await
@@ -145,8 +216,9 @@ pub async fn start_bus(
         .await
         .map_err(|err| BusError::Connection(format!("{err}")))?;
 
-    let pending: Arc<Mutex<HashMap<Uuid, oneshot::Sender<Result<serde_json::Value, String>>>>> =
-        Arc::new(Mutex::new(HashMap::new()));
+    let pending: Arc<
+        Mutex<HashMap<Uuid, oneshot::Sender<Result<serde_json::Value, ErrorDetail>>>>,
+    > = Arc::new(Mutex::new(HashMap::new()));
 
     let pending_map = Arc::clone(&pending);
     let handle = tokio::spawn(async move {
@@ -154,7 +226,7 @@ pub async fn start_bus(
             tokio::select! {
                 Some(cmd) = receiver.recv() => {
                     match cmd {
-                        OutboundCommand::Respond { request_id, payload } => {
+                        OutboundCommand::Respond { request_id, payload, traceparent } => {
                             let (success, data, error_message) = match payload {
                                 Ok(data) => (true, Some(data), None),
                                 Err(err) => (false, None, Some(err)),
@@ -164,23 +236,31 @@ pub async fn start_bus(
                                 success,
                                 data,
                                 error: error_message,
+                                traceparent,
                             }).await {
                                 error!(%request_id, error = %err, "failed to send response");
                             }
                         }
-                        OutboundCommand::Invoke { request_id, capability, payload, responder } => {
+                        OutboundCommand::Invoke { request_id, capability, payload, responder, traceparent } => {
                             pending_map.lock().await.insert(request_id, responder);
                             if let Err(err) = send_outgoing(&mut writer, &OutgoingMessage::Request {
                                 requestId: request_id,
                                 capability,
                                 payload,
+                                traceparent,
                             }).await {
                                 error!(%request_id, error = %err, "failed to send invoke request");
                                 if let Some(sender) = pending_map.lock().await.remove(&request_id) {
-                                    let _ = sender.send(Err(format!("transport error: {err}")));
+                                    let _ = sender.send(Err(ErrorDetail::new(
+                                        ErrorCode::Unavailable,
+                                        format!("transport error: {err}"),
+                                    )));
                                 }
                             }
                         }
+                        OutboundCommand::Cancel { request_id } => {
+                            pending_map.lock().await.remove(&request_id);
+                        }
                         OutboundCommand::Log { level, message } => {
                             let log = OutgoingMessage::Log {
                                 plugin: plugin.clone(),
@@ -206,35 +286,69 @@ pub async fn start_bus(
                 }
                 Some(Ok(Message::Text(text))) = reader.next() => {
                     match serde_json::from_str::<IncomingMessage>(&text) {
-                        Ok(IncomingMessage::Request { requestId, capability, payload, token }) => {
+                        Ok(IncomingMessage::Request { requestId, capability, payload, token, traceparent }) => {
                             if let Some(handler) = handlers.get(&capability) {
+                                // Rejected requests are answered here, so a
+                                // flood does not pile up tasks.
+                                let admission = match admit(&capability, token.as_deref()) {
+                                    Ok(admission) => admission,
+                                    Err(err) => {
+                                        if let Err(err) = send_outgoing(&mut writer, &OutgoingMessage::Response {
+                                            requestId,
+                                            success: false,
+                                            data: None,
+                                            error: Some(err),
+                                            traceparent,
+                                        }).await {
+                                            error!(%requestId, error = %err, "failed to send response");
+                                        }
+                                        continue;
+                                    }
+                                };
                                 let handler = handler.clone();
                                 let sender = sender.clone();
+                                let span = info_span!(
+                                    "bus.request",
+                                    otel.name = %capability,
+                                    otel.kind = "server",
+                                    %capability,
+                                    traceparent = traceparent.as_deref(),
+                                );
                                 tokio::spawn(async move {
-                                    let result = handler(requestId, capability.clone(), payload.clone(), token).await;
+                                    let result = match admission.permit().await {
+                                        Ok(_permit) => handler(requestId, capability.clone(), payload.clone(), token).await,
+                                        Err(err) => Err(err.into()),
+                                    };
                                     let command = OutboundCommand::Respond {
                                         request_id: requestId,
                                         payload: result,
+                                        traceparent: trace_context::current_traceparent(),
                                     };
                                     if let Err(err) = sender.send(command).await {
                                         error!(%requestId, error = %err, "failed to send handler response");
                                     }
-                                });
+                                }.instrument(span));
                             } else {
                                 let _ = sender
                                     .send(OutboundCommand::Respond {
                                         request_id: requestId,
-                                        payload: Err(format!("no handler for {capability}")),
+                                        payload: Err(ErrorDetail::new(
+                                            ErrorCode::Unsupported,
+                                            format!("no handler for {capability}"),
+                                        )),
+                                        traceparent,
                                     })
                                     .await;
                             }
                         }
-                        Ok(IncomingMessage::Response { requestId, success, data, error }) => {
+                        Ok(IncomingMessage::Response { requestId, success, data, error, .. }) => {
                             if let Some(responder) = pending_map.lock().await.remove(&requestId) {
                                 let result = if success {
                                     Ok(data.unwrap_or(serde_json::Value::Null))
                                 } else {
-                                    Err(error.unwrap_or_else(|| "unknown error".to_string()))
+                                    Err(error.map(ErrorDetail::from_bus).unwrap_or_else(|| {
+                                        ErrorDetail::new(ErrorCode::UpstreamError, "unknown error")
+                                    }))
                                 };
                                 if responder.send(result).is_err() {
                                     error!(%requestId, "failed to deliver invoke response");
@@ -0,0 +1,172 @@
+use crate::core::config::QueryCacheConfig;
+use crate::core::distance::cosine;
+use crate::core::schema::{CacheStats, QueryRequest, QueryResponse};
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Identifies a cacheable request. `exact` covers the whole normalised
+/// request; `shape` leaves out the query text so semantic lookups only
+/// match requests that differ in wording alone.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub collection: String,
+    pub exact: String,
+    pub shape: String,
+}
+
+impl CacheKey {
+    /// `request.collection` must already be tenant-scoped. The config
+    /// generation is part of the key so reloads never serve results ranked
+    /// under old settings.
+    pub fn new(request: &QueryRequest, config_generation: u64) -> Self {
+        let mut normalized = request.clone();
+        normalized.query = request.query.as_deref().map(normalize_query);
+        let exact = format!(
+            "{config_generation}:{}",
+            serde_json::to_string(&normalized).unwrap_or_default()
+        );
+        normalized.query = None;
+        let shape = format!(
+            "{config_generation}:{}",
+            serde_json::to_string(&normalized).unwrap_or_default()
+        );
+        Self {
+            collection: request.collection.clone(),
+            exact,
+            shape,
+        }
+    }
+}
+
+/// Lower-cases the query and collapses runs of whitespace.
+pub fn normalize_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+struct CacheEntry {
+    shape: String,
+    embedding: Option<Vec<f32>>,
+    response: QueryResponse,
+    inserted: Instant,
+}
+
+#[derive(Default)]
+struct CacheState {
+    /// Least recently used first.
+    entries: IndexMap<(String, String), CacheEntry>,
+    /// Bumped on every write to a collection; inserts computed before the
+    /// write are dropped.
+    generations: HashMap<String, u64>,
+    stats: CacheStats,
+}
+
+#[derive(Clone, Default)]
+pub struct QueryCache {
+    state: Arc<Mutex<CacheState>>,
+}
+
+impl QueryCache {
+    /// Exact lookup; refreshes the entry's position on a hit.
+    pub fn get(&self, key: &CacheKey, config: &QueryCacheConfig) -> Option<QueryResponse> {
+        let mut state = self.state.lock();
+        let id = (key.collection.clone(), key.exact.clone());
+        let entry = state.entries.shift_remove(&id)?;
+        if entry.inserted.elapsed() >= Duration::from_secs(config.ttl_secs) {
+            return None;
+        }
+        let response = entry.response.clone();
+        state.entries.insert(id, entry);
+        state.stats.hits += 1;
+        Some(response)
+    }
+
+    /// Most similar unexpired entry with the same request shape whose query
+    /// embedding clears the configured threshold.
+    pub fn get_similar(
+        &self,
+        key: &CacheKey,
+        embedding: &[f32],
+        config: &QueryCacheConfig,
+    ) -> Option<QueryResponse> {
+        let mut state = self.state.lock();
+        let ttl = Duration::from_secs(config.ttl_secs);
+        let (index, _) = state
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, ((collection, _), entry))| {
+                *collection == key.collection
+                    && entry.shape == key.shape
+                    && entry.inserted.elapsed() < ttl
+            })
+            .filter_map(|(index, (_, entry))| {
+                let similarity = cosine(entry.embedding.as_deref()?, embedding);
+                (similarity >= config.similarity_threshold).then_some((index, similarity))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))?;
+        let (id, entry) = state.entries.shift_remove_index(index)?;
+        let response = entry.response.clone();
+        state.entries.insert(id, entry);
+        state.stats.hits += 1;
+        state.stats.semantic_hits += 1;
+        Some(response)
+    }
+
+    /// Records a miss and returns the collection's write generation to pass
+    /// to [`Self::insert`].
+    pub fn miss(&self, collection: &str) -> u64 {
+        let mut state = self.state.lock();
+        state.stats.misses += 1;
+        state.generations.get(collection).copied().unwrap_or(0)
+    }
+
+    pub fn insert(
+        &self,
+        key: CacheKey,
+        generation: u64,
+        embedding: Option<Vec<f32>>,
+        response: QueryResponse,
+        config: &QueryCacheConfig,
+    ) {
+        let mut state = self.state.lock();
+        if state.generations.get(&key.collection).copied().unwrap_or(0) != generation {
+            return;
+        }
+        state.entries.insert(
+            (key.collection, key.exact),
+            CacheEntry {
+                shape: key.shape,
+                embedding,
+                response,
+                inserted: Instant::now(),
+            },
+        );
+        while state.entries.len() > config.max_entries {
+            state.entries.shift_remove_index(0);
+        }
+    }
+
+    /// Drops every entry for a collection after a write.
+    pub fn invalidate(&self, collection: &str) {
+        let mut state = self.state.lock();
+        *state.generations.entry(collection.to_string()).or_insert(0) += 1;
+        state
+            .entries
+            .retain(|(entry_collection, _), _| entry_collection != collection);
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let state = self.state.lock();
+        CacheStats {
+            entries: state.entries.len(),
+            ..state.stats.clone()
+        }
+    }
+}
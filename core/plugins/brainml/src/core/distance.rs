@@ -0,0 +1,502 @@
+use std::fmt;
+use std::sync::OnceLock;
+
+/// An implementation of the distance functions. Each function has a
+/// portable scalar version and, on x86_64, AVX2 and SSE4.1 versions; the
+/// free functions below use the fastest one the CPU supports. SIMD versions
+/// sum in a different order, so results may differ in the last few bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kernel {
+    /// 256-bit lanes with fused multiply-add.
+    Avx2,
+    /// 128-bit lanes.
+    Sse41,
+    Scalar,
+}
+
+impl fmt::Display for Kernel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Kernel::Avx2 => "avx2",
+            Kernel::Sse41 => "sse4.1",
+            Kernel::Scalar => "scalar",
+        })
+    }
+}
+
+/// The fastest kernel supported by this CPU, detected once.
+pub fn kernel() -> Kernel {
+    static ACTIVE: OnceLock<Kernel> = OnceLock::new();
+    *ACTIVE.get_or_init(|| {
+        Kernel::available()
+            .into_iter()
+            .next()
+            .unwrap_or(Kernel::Scalar)
+    })
+}
+
+impl Kernel {
+    /// Kernels supported by this CPU, fastest first.
+    pub fn available() -> Vec<Kernel> {
+        [Kernel::Avx2, Kernel::Sse41, Kernel::Scalar]
+            .into_iter()
+            .filter(|kernel| kernel.is_supported())
+            .collect()
+    }
+
+    pub fn is_supported(self) -> bool {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx2 => is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma"),
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Sse41 => is_x86_feature_detected!("sse4.1"),
+            Kernel::Scalar => true,
+            #[cfg(not(target_arch = "x86_64"))]
+            _ => false,
+        }
+    }
+
+    /// This kernel if the CPU supports it, the scalar one otherwise.
+    fn checked(self) -> Kernel {
+        if self.is_supported() {
+            self
+        } else {
+            Kernel::Scalar
+        }
+    }
+
+    /// Dot product over the common prefix of `a` and `b`.
+    pub fn dot(self, a: &[f32], b: &[f32]) -> f32 {
+        let len = a.len().min(b.len());
+        let (a, b) = (&a[..len], &b[..len]);
+        match self.checked() {
+            // SAFETY: `checked` only returns SIMD kernels the CPU supports.
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx2 => unsafe { x86::dot_avx2(a, b) },
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Sse41 => unsafe { x86::dot_sse41(a, b) },
+            _ => scalar::dot(a, b),
+        }
+    }
+
+    /// Cosine similarity; `0.0` when the lengths differ, the vectors are
+    /// empty or either has zero norm.
+    pub fn cosine(self, a: &[f32], b: &[f32]) -> f32 {
+        if a.len() != b.len() || a.is_empty() {
+            return 0.0;
+        }
+        let (dot, norm_a, norm_b) = match self.checked() {
+            // SAFETY: `checked` only returns SIMD kernels the CPU supports.
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx2 => unsafe { x86::cosine_parts_avx2(a, b) },
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Sse41 => unsafe { x86::cosine_parts_sse41(a, b) },
+            _ => scalar::cosine_parts(a, b),
+        };
+        cosine_from_parts(dot, norm_a, norm_b)
+    }
+
+    /// Squared Euclidean distance over the common prefix of `a` and `b`.
+    pub fn l2_squared(self, a: &[f32], b: &[f32]) -> f32 {
+        let len = a.len().min(b.len());
+        let (a, b) = (&a[..len], &b[..len]);
+        match self.checked() {
+            // SAFETY: `checked` only returns SIMD kernels the CPU supports.
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx2 => unsafe { x86::l2_squared_avx2(a, b) },
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Sse41 => unsafe { x86::l2_squared_sse41(a, b) },
+            _ => scalar::l2_squared(a, b),
+        }
+    }
+
+    pub fn l2(self, a: &[f32], b: &[f32]) -> f32 {
+        self.l2_squared(a, b).sqrt()
+    }
+
+    /// Dot product of two int8 vectors over their common prefix.
+    pub fn dot_i8(self, a: &[i8], b: &[i8]) -> i32 {
+        let len = a.len().min(b.len());
+        let (a, b) = (&a[..len], &b[..len]);
+        match self.checked() {
+            // SAFETY: `checked` only returns SIMD kernels the CPU supports.
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx2 => unsafe { x86::dot_i8_avx2(a, b) },
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Sse41 => unsafe { x86::dot_i8_sse41(a, b) },
+            _ => scalar::dot_i8(a, b),
+        }
+    }
+
+    pub fn cosine_i8(self, a: &[i8], b: &[i8]) -> f32 {
+        if a.len() != b.len() || a.is_empty() {
+            return 0.0;
+        }
+        cosine_from_parts(
+            self.dot_i8(a, b) as f32,
+            self.dot_i8(a, a) as f32,
+            self.dot_i8(b, b) as f32,
+        )
+    }
+
+    /// Squared Euclidean distance of two int8 vectors over their common
+    /// prefix.
+    pub fn l2_squared_i8(self, a: &[i8], b: &[i8]) -> i32 {
+        let len = a.len().min(b.len());
+        let (a, b) = (&a[..len], &b[..len]);
+        match self.checked() {
+            // SAFETY: `checked` only returns SIMD kernels the CPU supports.
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx2 => unsafe { x86::l2_squared_i8_avx2(a, b) },
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Sse41 => unsafe { x86::l2_squared_i8_sse41(a, b) },
+            _ => scalar::l2_squared_i8(a, b),
+        }
+    }
+
+    pub fn l2_i8(self, a: &[i8], b: &[i8]) -> f32 {
+        (self.l2_squared_i8(a, b) as f32).sqrt()
+    }
+
+    /// Dot product of a full precision `query` with scalar quantized
+    /// `codes`, each standing for `min + scale * code`.
+    pub fn dot_u8_affine(self, query: &[f32], codes: &[u8], min: f32, scale: f32) -> f32 {
+        let len = query.len().min(codes.len());
+        let (query, codes) = (&query[..len], &codes[..len]);
+        match self.checked() {
+            // SAFETY: `checked` only returns SIMD kernels the CPU supports.
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx2 => unsafe { x86::dot_u8_affine_avx2(query, codes, min, scale) },
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Sse41 => unsafe { x86::dot_u8_affine_sse41(query, codes, min, scale) },
+            _ => scalar::dot_u8_affine(query, codes, min, scale),
+        }
+    }
+}
+
+fn cosine_from_parts(dot: f32, norm_a: f32, norm_b: f32) -> f32 {
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    kernel().dot(a, b)
+}
+
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    kernel().cosine(a, b)
+}
+
+pub fn l2_squared(a: &[f32], b: &[f32]) -> f32 {
+    kernel().l2_squared(a, b)
+}
+
+pub fn l2(a: &[f32], b: &[f32]) -> f32 {
+    kernel().l2(a, b)
+}
+
+pub fn dot_i8(a: &[i8], b: &[i8]) -> i32 {
+    kernel().dot_i8(a, b)
+}
+
+pub fn cosine_i8(a: &[i8], b: &[i8]) -> f32 {
+    kernel().cosine_i8(a, b)
+}
+
+pub fn l2_squared_i8(a: &[i8], b: &[i8]) -> i32 {
+    kernel().l2_squared_i8(a, b)
+}
+
+pub fn l2_i8(a: &[i8], b: &[i8]) -> f32 {
+    kernel().l2_i8(a, b)
+}
+
+pub fn dot_u8_affine(query: &[f32], codes: &[u8], min: f32, scale: f32) -> f32 {
+    kernel().dot_u8_affine(query, codes, min, scale)
+}
+
+mod scalar {
+    pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    pub fn cosine_parts(a: &[f32], b: &[f32]) -> (f32, f32, f32) {
+        a.iter()
+            .zip(b)
+            .fold((0.0, 0.0, 0.0), |(dot, norm_a, norm_b), (x, y)| {
+                (dot + x * y, norm_a + x * x, norm_b + y * y)
+            })
+    }
+
+    pub fn l2_squared(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+    }
+
+    pub fn dot_i8(a: &[i8], b: &[i8]) -> i32 {
+        a.iter().zip(b).map(|(x, y)| *x as i32 * *y as i32).sum()
+    }
+
+    pub fn l2_squared_i8(a: &[i8], b: &[i8]) -> i32 {
+        a.iter()
+            .zip(b)
+            .map(|(x, y)| {
+                let diff = *x as i32 - *y as i32;
+                diff * diff
+            })
+            .sum()
+    }
+
+    pub fn dot_u8_affine(query: &[f32], codes: &[u8], min: f32, scale: f32) -> f32 {
+        query
+            .iter()
+            .zip(codes)
+            .map(|(q, code)| q * (min + scale * *code as f32))
+            .sum()
+    }
+}
+
+/// Callers pass slices of equal length and must only call a function when
+/// the CPU has the features it is compiled for.
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::scalar;
+    use std::arch::x86_64::*;
+
+    #[inline]
+    #[target_feature(enable = "sse4.1")]
+    unsafe fn sum_ps(v: __m128) -> f32 {
+        let shuffled = _mm_movehdup_ps(v);
+        let sums = _mm_add_ps(v, shuffled);
+        let high = _mm_movehl_ps(shuffled, sums);
+        _mm_cvtss_f32(_mm_add_ss(sums, high))
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn sum256_ps(v: __m256) -> f32 {
+        sum_ps(_mm_add_ps(
+            _mm256_castps256_ps128(v),
+            _mm256_extractf128_ps(v, 1),
+        ))
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse4.1")]
+    unsafe fn sum_epi32(v: __m128i) -> i32 {
+        let v = _mm_add_epi32(v, _mm_shuffle_epi32(v, 0b01_00_11_10));
+        let v = _mm_add_epi32(v, _mm_shuffle_epi32(v, 0b10_11_00_01));
+        _mm_cvtsi128_si32(v)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn sum256_epi32(v: __m256i) -> i32 {
+        sum_epi32(_mm_add_epi32(
+            _mm256_castsi256_si128(v),
+            _mm256_extracti128_si256(v, 1),
+        ))
+    }
+
+    #[target_feature(enable = "avx2,fma")]
+    pub unsafe fn dot_avx2(a: &[f32], b: &[f32]) -> f32 {
+        let split = a.len() / 16 * 16;
+        let (mut acc0, mut acc1) = (_mm256_setzero_ps(), _mm256_setzero_ps());
+        for idx in (0..split).step_by(16) {
+            let (pa, pb) = (a.as_ptr().add(idx), b.as_ptr().add(idx));
+            acc0 = _mm256_fmadd_ps(_mm256_loadu_ps(pa), _mm256_loadu_ps(pb), acc0);
+            acc1 = _mm256_fmadd_ps(_mm256_loadu_ps(pa.add(8)), _mm256_loadu_ps(pb.add(8)), acc1);
+        }
+        sum256_ps(_mm256_add_ps(acc0, acc1)) + scalar::dot(&a[split..], &b[split..])
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    pub unsafe fn dot_sse41(a: &[f32], b: &[f32]) -> f32 {
+        let split = a.len() / 8 * 8;
+        let (mut acc0, mut acc1) = (_mm_setzero_ps(), _mm_setzero_ps());
+        for idx in (0..split).step_by(8) {
+            let (pa, pb) = (a.as_ptr().add(idx), b.as_ptr().add(idx));
+            acc0 = _mm_add_ps(acc0, _mm_mul_ps(_mm_loadu_ps(pa), _mm_loadu_ps(pb)));
+            acc1 = _mm_add_ps(
+                acc1,
+                _mm_mul_ps(_mm_loadu_ps(pa.add(4)), _mm_loadu_ps(pb.add(4))),
+            );
+        }
+        sum_ps(_mm_add_ps(acc0, acc1)) + scalar::dot(&a[split..], &b[split..])
+    }
+
+    #[target_feature(enable = "avx2,fma")]
+    pub unsafe fn cosine_parts_avx2(a: &[f32], b: &[f32]) -> (f32, f32, f32) {
+        let split = a.len() / 8 * 8;
+        let mut dot = _mm256_setzero_ps();
+        let mut norm_a = _mm256_setzero_ps();
+        let mut norm_b = _mm256_setzero_ps();
+        for idx in (0..split).step_by(8) {
+            let va = _mm256_loadu_ps(a.as_ptr().add(idx));
+            let vb = _mm256_loadu_ps(b.as_ptr().add(idx));
+            dot = _mm256_fmadd_ps(va, vb, dot);
+            norm_a = _mm256_fmadd_ps(va, va, norm_a);
+            norm_b = _mm256_fmadd_ps(vb, vb, norm_b);
+        }
+        let (tail_dot, tail_a, tail_b) = scalar::cosine_parts(&a[split..], &b[split..]);
+        (
+            sum256_ps(dot) + tail_dot,
+            sum256_ps(norm_a) + tail_a,
+            sum256_ps(norm_b) + tail_b,
+        )
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    pub unsafe fn cosine_parts_sse41(a: &[f32], b: &[f32]) -> (f32, f32, f32) {
+        let split = a.len() / 4 * 4;
+        let mut dot = _mm_setzero_ps();
+        let mut norm_a = _mm_setzero_ps();
+        let mut norm_b = _mm_setzero_ps();
+        for idx in (0..split).step_by(4) {
+            let va = _mm_loadu_ps(a.as_ptr().add(idx));
+            let vb = _mm_loadu_ps(b.as_ptr().add(idx));
+            dot = _mm_add_ps(dot, _mm_mul_ps(va, vb));
+            norm_a = _mm_add_ps(norm_a, _mm_mul_ps(va, va));
+            norm_b = _mm_add_ps(norm_b, _mm_mul_ps(vb, vb));
+        }
+        let (tail_dot, tail_a, tail_b) = scalar::cosine_parts(&a[split..], &b[split..]);
+        (
+            sum_ps(dot) + tail_dot,
+            sum_ps(norm_a) + tail_a,
+            sum_ps(norm_b) + tail_b,
+        )
+    }
+
+    #[target_feature(enable = "avx2,fma")]
+    pub unsafe fn l2_squared_avx2(a: &[f32], b: &[f32]) -> f32 {
+        let split = a.len() / 8 * 8;
+        let mut acc = _mm256_setzero_ps();
+        for idx in (0..split).step_by(8) {
+            let diff = _mm256_sub_ps(
+                _mm256_loadu_ps(a.as_ptr().add(idx)),
+                _mm256_loadu_ps(b.as_ptr().add(idx)),
+            );
+            acc = _mm256_fmadd_ps(diff, diff, acc);
+        }
+        sum256_ps(acc) + scalar::l2_squared(&a[split..], &b[split..])
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    pub unsafe fn l2_squared_sse41(a: &[f32], b: &[f32]) -> f32 {
+        let split = a.len() / 4 * 4;
+        let mut acc = _mm_setzero_ps();
+        for idx in (0..split).step_by(4) {
+            let diff = _mm_sub_ps(
+                _mm_loadu_ps(a.as_ptr().add(idx)),
+                _mm_loadu_ps(b.as_ptr().add(idx)),
+            );
+            acc = _mm_add_ps(acc, _mm_mul_ps(diff, diff));
+        }
+        sum_ps(acc) + scalar::l2_squared(&a[split..], &b[split..])
+    }
+
+    /// Sign-extends 16 int8 values to int16.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn load_i8x16(ptr: *const i8) -> __m256i {
+        _mm256_cvtepi8_epi16(_mm_loadu_si128(ptr as *const __m128i))
+    }
+
+    /// Sign-extends 8 int8 values to int16.
+    #[inline]
+    #[target_feature(enable = "sse4.1")]
+    unsafe fn load_i8x8(ptr: *const i8) -> __m128i {
+        _mm_cvtepi8_epi16(_mm_loadl_epi64(ptr as *const __m128i))
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn dot_i8_avx2(a: &[i8], b: &[i8]) -> i32 {
+        let split = a.len() / 16 * 16;
+        let mut acc = _mm256_setzero_si256();
+        for idx in (0..split).step_by(16) {
+            let products = _mm256_madd_epi16(
+                load_i8x16(a.as_ptr().add(idx)),
+                load_i8x16(b.as_ptr().add(idx)),
+            );
+            acc = _mm256_add_epi32(acc, products);
+        }
+        sum256_epi32(acc) + scalar::dot_i8(&a[split..], &b[split..])
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    pub unsafe fn dot_i8_sse41(a: &[i8], b: &[i8]) -> i32 {
+        let split = a.len() / 8 * 8;
+        let mut acc = _mm_setzero_si128();
+        for idx in (0..split).step_by(8) {
+            let products = _mm_madd_epi16(
+                load_i8x8(a.as_ptr().add(idx)),
+                load_i8x8(b.as_ptr().add(idx)),
+            );
+            acc = _mm_add_epi32(acc, products);
+        }
+        sum_epi32(acc) + scalar::dot_i8(&a[split..], &b[split..])
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn l2_squared_i8_avx2(a: &[i8], b: &[i8]) -> i32 {
+        let split = a.len() / 16 * 16;
+        let mut acc = _mm256_setzero_si256();
+        for idx in (0..split).step_by(16) {
+            let diff = _mm256_sub_epi16(
+                load_i8x16(a.as_ptr().add(idx)),
+                load_i8x16(b.as_ptr().add(idx)),
+            );
+            acc = _mm256_add_epi32(acc, _mm256_madd_epi16(diff, diff));
+        }
+        sum256_epi32(acc) + scalar::l2_squared_i8(&a[split..], &b[split..])
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    pub unsafe fn l2_squared_i8_sse41(a: &[i8], b: &[i8]) -> i32 {
+        let split = a.len() / 8 * 8;
+        let mut acc = _mm_setzero_si128();
+        for idx in (0..split).step_by(8) {
+            let diff = _mm_sub_epi16(
+                load_i8x8(a.as_ptr().add(idx)),
+                load_i8x8(b.as_ptr().add(idx)),
+            );
+            acc = _mm_add_epi32(acc, _mm_madd_epi16(diff, diff));
+        }
+        sum_epi32(acc) + scalar::l2_squared_i8(&a[split..], &b[split..])
+    }
+
+    #[target_feature(enable = "avx2,fma")]
+    pub unsafe fn dot_u8_affine_avx2(query: &[f32], codes: &[u8], min: f32, scale: f32) -> f32 {
+        let split = query.len() / 8 * 8;
+        let (min_v, scale_v) = (_mm256_set1_ps(min), _mm256_set1_ps(scale));
+        let mut acc = _mm256_setzero_ps();
+        for idx in (0..split).step_by(8) {
+            let packed = _mm_loadl_epi64(codes.as_ptr().add(idx) as *const __m128i);
+            let values = _mm256_fmadd_ps(
+                _mm256_cvtepi32_ps(_mm256_cvtepu8_epi32(packed)),
+                scale_v,
+                min_v,
+            );
+            acc = _mm256_fmadd_ps(_mm256_loadu_ps(query.as_ptr().add(idx)), values, acc);
+        }
+        sum256_ps(acc) + scalar::dot_u8_affine(&query[split..], &codes[split..], min, scale)
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    pub unsafe fn dot_u8_affine_sse41(query: &[f32], codes: &[u8], min: f32, scale: f32) -> f32 {
+        let split = query.len() / 4 * 4;
+        let (min_v, scale_v) = (_mm_set1_ps(min), _mm_set1_ps(scale));
+        let mut acc = _mm_setzero_ps();
+        for idx in (0..split).step_by(4) {
+            let packed = (codes.as_ptr().add(idx) as *const i32).read_unaligned();
+            let widened = _mm_cvtepu8_epi32(_mm_cvtsi32_si128(packed));
+            let values = _mm_add_ps(_mm_mul_ps(_mm_cvtepi32_ps(widened), scale_v), min_v);
+            acc = _mm_add_ps(
+                acc,
+                _mm_mul_ps(_mm_loadu_ps(query.as_ptr().add(idx)), values),
+            );
+        }
+        sum_ps(acc) + scalar::dot_u8_affine(&query[split..], &codes[split..], min, scale)
+    }
+}
@@ -1,8 +1,14 @@
 use crate::adapters::llm::{EmbeddingRequest, LlmClient};
+use crate::core::config::BrainmlConfig;
 use crate::core::schema::DocumentInput;
 use anyhow::Result;
+use thiserror::Error;
 use tracing::instrument;
 
+#[derive(Debug, Error)]
+#[error("unknown vector field: {0}")]
+pub struct UnknownVectorField(pub String);
+
 #[instrument(skip_all, fields(batch = inputs.len()))]
 pub async fn embed_documents<C: LlmClient + ?Sized>(
     client: &C,
@@ -16,3 +22,19 @@ pub async fn embed_documents<C: LlmClient + ?Sized>(
     let vectors = client.embed(request).await?;
     Ok(vectors.into_iter().map(|vec| vec.embedding).collect())
 }
+
+/// Resolves the embedding model for a named vector field, or the default
+/// embedding model when no field is given.
+pub fn model_for_field<'a>(
+    config: &'a BrainmlConfig,
+    field: Option<&str>,
+) -> Result<Option<&'a str>, UnknownVectorField> {
+    match field {
+        Some(name) => config
+            .vector_fields
+            .get(name)
+            .map(|spec| Some(spec.model.as_str()))
+            .ok_or_else(|| UnknownVectorField(name.to_string())),
+        None => Ok(config.embedding_model.as_deref()),
+    }
+}
@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use utoipa::ToSchema;
+
+/// Stable error classification shared by HTTP responses, bus responses and
+/// the braindb and LLM adapters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The request could not be parsed.
+    InvalidRequest,
+    /// The request parsed but its values are not acceptable.
+    ValidationFailed,
+    NotFound,
+    /// The caller could not be authenticated or may not act as the
+    /// tenant it named.
+    Unauthorized,
+    /// The caller is authenticated but may not perform the operation.
+    Forbidden,
+    QuotaExceeded,
+    RateLimited,
+    Overloaded,
+    Unsupported,
+    /// A dependency could not be reached.
+    Unavailable,
+    /// A dependency did not answer in time.
+    Timeout,
+    Internal,
+    /// A dependency answered with an error. Codes not known to this
+    /// version are read as this one.
+    #[serde(other)]
+    UpstreamError,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::InvalidRequest => "invalid_request",
+            ErrorCode::ValidationFailed => "validation_failed",
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::Unauthorized => "unauthorized",
+            ErrorCode::Forbidden => "forbidden",
+            ErrorCode::QuotaExceeded => "quota_exceeded",
+            ErrorCode::RateLimited => "rate_limited",
+            ErrorCode::Overloaded => "overloaded",
+            ErrorCode::Unsupported => "unsupported",
+            ErrorCode::Unavailable => "unavailable",
+            ErrorCode::Timeout => "timeout",
+            ErrorCode::Internal => "internal",
+            ErrorCode::UpstreamError => "upstream_error",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Error carried in a bus `response` message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ErrorDetail {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl ErrorDetail {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// Reads the `error` field of a bus response. Peers that still send a
+    /// plain string get [`ErrorCode::UpstreamError`].
+    pub fn from_bus(error: serde_json::Value) -> Self {
+        match error {
+            serde_json::Value::String(message) => Self::new(ErrorCode::UpstreamError, message),
+            other => serde_json::from_value(other.clone())
+                .unwrap_or_else(|_| Self::new(ErrorCode::UpstreamError, other.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for ErrorDetail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for ErrorDetail {}
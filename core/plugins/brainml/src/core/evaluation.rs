@@ -0,0 +1,220 @@
+use crate::core::schema::{DocumentInput, IndexRequest, QueryFilter};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum EvaluationError {
+    #[error("qrels contain no queries")]
+    NoQueries,
+    #[error("query {0} has no relevant documents")]
+    NoRelevantDocuments(String),
+    #[error("k must be at least 1")]
+    InvalidK,
+}
+
+/// Evaluation set: queries with judged documents and, optionally, the
+/// corpus to index before running them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Qrels {
+    pub collection: String,
+    #[serde(default)]
+    pub documents: Vec<DocumentInput>,
+    pub queries: Vec<QrelQuery>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QrelQuery {
+    pub id: String,
+    pub query: String,
+    #[serde(default)]
+    pub filters: Vec<QueryFilter>,
+    pub relevant: Judgements,
+}
+
+/// Either a list of relevant ids or ids mapped to graded relevance. Grades
+/// of zero mark a document as judged but not relevant.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Judgements {
+    Binary(Vec<String>),
+    Graded(BTreeMap<String, u32>),
+}
+
+impl Judgements {
+    pub fn grades(&self) -> BTreeMap<String, u32> {
+        match self {
+            Judgements::Binary(ids) => ids.iter().map(|id| (id.clone(), 1)).collect(),
+            Judgements::Graded(grades) => grades.clone(),
+        }
+    }
+}
+
+impl Qrels {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let contents =
+            std::fs::read(path).with_context(|| format!("reading qrels {}", path.display()))?;
+        let qrels: Self = serde_json::from_slice(&contents)
+            .with_context(|| format!("parsing qrels {}", path.display()))?;
+        qrels.validate()?;
+        Ok(qrels)
+    }
+
+    pub fn validate(&self) -> Result<(), EvaluationError> {
+        if self.queries.is_empty() {
+            return Err(EvaluationError::NoQueries);
+        }
+        for query in &self.queries {
+            if query.relevant.grades().values().all(|grade| *grade == 0) {
+                return Err(EvaluationError::NoRelevantDocuments(query.id.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Request indexing the bundled corpus, if there is one.
+    pub fn index_request(&self, embed: bool) -> Option<IndexRequest> {
+        if self.documents.is_empty() {
+            return None;
+        }
+        Some(IndexRequest {
+            collection: self.collection.clone(),
+            documents: self.documents.clone(),
+            embed,
+            fts: true,
+            vector_fields: Vec::new(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvaluationOptions {
+    /// Cut-off for every metric.
+    pub k: usize,
+    /// Embeds queries and fuses vector scores with full-text matches.
+    pub hybrid: bool,
+}
+
+impl Default for EvaluationOptions {
+    fn default() -> Self {
+        Self {
+            k: 10,
+            hybrid: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryMetrics {
+    pub id: String,
+    pub recall: f64,
+    pub reciprocal_rank: f64,
+    pub ndcg: f64,
+    pub retrieved: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluationReport {
+    pub k: usize,
+    pub queries: usize,
+    /// Mean recall@k.
+    pub recall: f64,
+    /// Mean reciprocal rank of the first relevant result within k.
+    pub mrr: f64,
+    /// Mean nDCG@k.
+    pub ndcg: f64,
+    pub per_query: Vec<QueryMetrics>,
+}
+
+fn relevant_count(grades: &BTreeMap<String, u32>) -> usize {
+    grades.values().filter(|grade| **grade > 0).count()
+}
+
+fn grade(grades: &BTreeMap<String, u32>, id: &str) -> u32 {
+    grades.get(id).copied().unwrap_or(0)
+}
+
+/// Share of relevant documents found in the first `k` results.
+pub fn recall_at_k(retrieved: &[String], grades: &BTreeMap<String, u32>, k: usize) -> f64 {
+    let relevant = relevant_count(grades);
+    if relevant == 0 {
+        return 0.0;
+    }
+    let found = retrieved
+        .iter()
+        .take(k)
+        .filter(|id| grade(grades, id) > 0)
+        .count();
+    found as f64 / relevant as f64
+}
+
+pub fn reciprocal_rank(retrieved: &[String], grades: &BTreeMap<String, u32>, k: usize) -> f64 {
+    retrieved
+        .iter()
+        .take(k)
+        .position(|id| grade(grades, id) > 0)
+        .map_or(0.0, |position| 1.0 / (position + 1) as f64)
+}
+
+/// nDCG with exponential gain (`2^grade - 1`) and a log2 position discount.
+pub fn ndcg_at_k(retrieved: &[String], grades: &BTreeMap<String, u32>, k: usize) -> f64 {
+    let dcg = |grades_in_order: &mut dyn Iterator<Item = u32>| -> f64 {
+        grades_in_order
+            .take(k)
+            .enumerate()
+            .map(|(position, grade)| {
+                (2f64.powi(grade as i32) - 1.0) / ((position + 2) as f64).log2()
+            })
+            .sum()
+    };
+    let mut ideal: Vec<u32> = grades
+        .values()
+        .copied()
+        .filter(|grade| *grade > 0)
+        .collect();
+    ideal.sort_unstable_by(|a, b| b.cmp(a));
+    let ideal = dcg(&mut ideal.into_iter());
+    if ideal == 0.0 {
+        return 0.0;
+    }
+    dcg(&mut retrieved.iter().map(|id| grade(grades, id))) / ideal
+}
+
+pub fn score_query(
+    id: &str,
+    retrieved: Vec<String>,
+    grades: &BTreeMap<String, u32>,
+    k: usize,
+) -> QueryMetrics {
+    QueryMetrics {
+        id: id.to_string(),
+        recall: recall_at_k(&retrieved, grades, k),
+        reciprocal_rank: reciprocal_rank(&retrieved, grades, k),
+        ndcg: ndcg_at_k(&retrieved, grades, k),
+        retrieved,
+    }
+}
+
+/// Macro-averages per-query metrics.
+pub fn summarize(k: usize, per_query: Vec<QueryMetrics>) -> EvaluationReport {
+    let mean = |metric: fn(&QueryMetrics) -> f64| {
+        if per_query.is_empty() {
+            0.0
+        } else {
+            per_query.iter().map(metric).sum::<f64>() / per_query.len() as f64
+        }
+    };
+    EvaluationReport {
+        k,
+        queries: per_query.len(),
+        recall: mean(|metrics| metrics.recall),
+        mrr: mean(|metrics| metrics.reciprocal_rank),
+        ndcg: mean(|metrics| metrics.ndcg),
+        per_query,
+    }
+}
@@ -0,0 +1,74 @@
+use crate::core::schema::{CollectionAlias, DateField, DocumentRecord, ExpiryReport};
+use crate::core::tenancy::split_namespace;
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Documents fetched per page while looking for expired ones.
+pub const SCAN_BATCH: usize = 500;
+
+/// TTL of a physical collection: the one configured for its tenant-visible
+/// name, or else for an alias pointing at it. `aliases` are in physical
+/// names.
+pub fn ttl_for(
+    ttl_secs: &BTreeMap<String, u64>,
+    physical: &str,
+    aliases: &[CollectionAlias],
+) -> Option<u64> {
+    let visible = |name: &str| {
+        split_namespace(name)
+            .map(|(_, name)| name.to_string())
+            .unwrap_or_else(|| name.to_string())
+    };
+    ttl_secs.get(&visible(physical)).copied().or_else(|| {
+        aliases
+            .iter()
+            .filter(|alias| alias.collection == physical)
+            .find_map(|alias| ttl_secs.get(&visible(&alias.alias)).copied())
+    })
+}
+
+/// Ids of documents whose `field` is older than `cutoff`.
+pub fn expired_ids(
+    documents: &[DocumentRecord],
+    field: DateField,
+    cutoff: DateTime<Utc>,
+) -> Vec<String> {
+    documents
+        .iter()
+        .filter(|document| field.of(document) < cutoff)
+        .map(|document| document.id.clone())
+        .collect()
+}
+
+/// Tracks the expiry sweep and its last report.
+#[derive(Clone, Default)]
+pub struct ExpirySweep {
+    last: Arc<RwLock<Option<ExpiryReport>>>,
+    running: Arc<AtomicBool>,
+}
+
+impl ExpirySweep {
+    /// Marks a sweep as running. Returns `false` if one already is.
+    pub fn begin(&self) -> bool {
+        !self.running.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn finish(&self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    pub fn record(&self, report: ExpiryReport) {
+        *self.last.write() = Some(report);
+    }
+
+    pub fn last_report(&self) -> Option<ExpiryReport> {
+        self.last.read().clone()
+    }
+}
@@ -0,0 +1,240 @@
+use crate::adapters::llm::LlmClient;
+use crate::core::distance::cosine;
+use crate::core::embeddings::embed_documents;
+use crate::core::schema::{
+    DocumentField, DocumentInput, HighlightOptions, QueryResult, Snippet, SnippetKind,
+};
+use anyhow::Result;
+use std::ops::Range;
+use tracing::instrument;
+
+/// Lower-cased alphanumeric terms of a query.
+pub fn query_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = words(query)
+        .into_iter()
+        .map(|range| query[range].to_lowercase())
+        .collect();
+    terms.sort();
+    terms.dedup();
+    terms
+}
+
+/// Byte ranges of the alphanumeric words in `text`.
+pub(crate) fn words(text: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    for (idx, ch) in text.char_indices() {
+        match (ch.is_alphanumeric(), start) {
+            (true, None) => start = Some(idx),
+            (false, Some(begin)) => {
+                spans.push(begin..idx);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(begin) = start {
+        spans.push(begin..text.len());
+    }
+    spans
+}
+
+/// Builds fragments around words that start with one of `terms`, wrapping
+/// each matched word in the configured tags. The document text is HTML
+/// escaped, so only the tags are markup.
+pub fn term_snippets(text: &str, terms: &[String], options: &HighlightOptions) -> Vec<Snippet> {
+    if terms.is_empty() || options.max_fragments == 0 {
+        return Vec::new();
+    }
+    let matches: Vec<Range<usize>> = words(text)
+        .into_iter()
+        .filter(|range| {
+            let word = text[range.clone()].to_lowercase();
+            terms.iter().any(|term| word.starts_with(term.as_str()))
+        })
+        .collect();
+    let half = options.fragment_size / 2;
+    let mut fragments: Vec<Range<usize>> = Vec::new();
+    for hit in &matches {
+        if fragments.len() >= options.max_fragments {
+            break;
+        }
+        if fragments
+            .iter()
+            .any(|fragment| fragment.contains(&hit.start))
+        {
+            continue;
+        }
+        let start = floor_char_boundary(text, hit.start.saturating_sub(half));
+        let end = ceil_char_boundary(text, (hit.end + half).min(text.len()));
+        fragments.push(start..end);
+    }
+    fragments
+        .into_iter()
+        .map(|fragment| {
+            let mut out = String::new();
+            let mut cursor = fragment.start;
+            for hit in matches
+                .iter()
+                .filter(|hit| hit.start >= fragment.start && hit.end <= fragment.end)
+            {
+                escape_html(&text[cursor..hit.start], &mut out);
+                out.push_str(&options.pre_tag);
+                escape_html(&text[hit.clone()], &mut out);
+                out.push_str(&options.post_tag);
+                cursor = hit.end;
+            }
+            escape_html(&text[cursor..fragment.end], &mut out);
+            Snippet {
+                text: out.trim().to_string(),
+                kind: SnippetKind::Term,
+                score: None,
+            }
+        })
+        .collect()
+}
+
+/// Appends `text` with `&`, `<`, `>`, `"` and `'` replaced by entities.
+fn escape_html(text: &str, out: &mut String) {
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(ch),
+        }
+    }
+}
+
+fn floor_char_boundary(text: &str, mut idx: usize) -> usize {
+    while !text.is_char_boundary(idx) {
+        idx -= 1;
+    }
+    idx
+}
+
+fn ceil_char_boundary(text: &str, mut idx: usize) -> usize {
+    while !text.is_char_boundary(idx) {
+        idx += 1;
+    }
+    idx
+}
+
+/// Splits text into windows of `size` consecutive sentences.
+pub fn sentence_windows(text: &str, size: usize) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    for (idx, ch) in text.char_indices() {
+        if matches!(ch, '.' | '!' | '?' | '\n') {
+            let end = idx + ch.len_utf8();
+            let sentence = text[start..end].trim();
+            if !sentence.is_empty() {
+                sentences.push(sentence);
+            }
+            start = end;
+        }
+    }
+    let tail = text[start..].trim();
+    if !tail.is_empty() {
+        sentences.push(tail);
+    }
+    let size = size.max(1);
+    if sentences.len() <= size {
+        return vec![sentences.join(" ")]
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect();
+    }
+    sentences
+        .windows(size)
+        .map(|window| window.join(" "))
+        .collect()
+}
+
+/// Attaches term snippets to results that matched the query text and the
+/// best-matching sentence window to the remaining vector hits.
+#[instrument(skip_all, fields(results = results.len()))]
+pub async fn annotate<C: LlmClient + ?Sized>(
+    client: &C,
+    results: &mut [QueryResult],
+    query: Option<&str>,
+    vector: Option<&[f32]>,
+    model: Option<&str>,
+    options: &HighlightOptions,
+) -> Result<()> {
+    if !options.enabled {
+        return Ok(());
+    }
+    let terms = query.map(query_terms).unwrap_or_default();
+    let mut pending = Vec::new();
+    for (idx, result) in results.iter_mut().enumerate() {
+        result.snippets = term_snippets(&result.document.text, &terms, options);
+        if result.snippets.is_empty() && vector.is_some() && !result.document.text.is_empty() {
+            pending.push(idx);
+        }
+    }
+    let Some(vector) = vector else {
+        return Ok(());
+    };
+    if pending.is_empty() {
+        return Ok(());
+    }
+    let mut owners = Vec::new();
+    let mut inputs = Vec::new();
+    for idx in pending {
+        for window in sentence_windows(&results[idx].document.text, options.sentence_window) {
+            owners.push(idx);
+            inputs.push(DocumentInput {
+                id: None,
+                text: window,
+                metadata: serde_json::Value::Null,
+            });
+        }
+    }
+    let embeddings = embed_documents(client, &inputs, model).await?;
+    let mut best: Vec<Option<(f32, usize)>> = vec![None; results.len()];
+    for (position, (owner, embedding)) in owners.iter().zip(embeddings.iter()).enumerate() {
+        let score = cosine(vector, embedding);
+        if !score.is_finite() {
+            continue;
+        }
+        if best[*owner].is_none_or(|(current, _)| score > current) {
+            best[*owner] = Some((score, position));
+        }
+    }
+    for (idx, choice) in best.into_iter().enumerate() {
+        if let Some((score, position)) = choice {
+            let mut text = String::new();
+            escape_html(&inputs[position].text, &mut text);
+            results[idx].snippets = vec![Snippet {
+                text,
+                kind: SnippetKind::Semantic,
+                score: Some(score),
+            }];
+        }
+    }
+    Ok(())
+}
+
+/// Drops document fields that were not requested.
+pub fn project(results: &mut [QueryResult], fields: &[DocumentField]) {
+    let keep_text = fields.contains(&DocumentField::Text);
+    let keep_metadata = fields.contains(&DocumentField::Metadata);
+    let keep_embedding = fields.contains(&DocumentField::Embedding);
+    for result in results.iter_mut() {
+        let document = &mut result.document;
+        if !keep_text {
+            document.text.clear();
+        }
+        if !keep_metadata {
+            document.metadata = serde_json::Value::Null;
+        }
+        if !keep_embedding {
+            document.embedding = None;
+            document.embedding_model = None;
+            document.vectors.clear();
+        }
+    }
+}
@@ -0,0 +1,455 @@
+use crate::core::schema::{DocumentInput, IngestFile, IngestedFile};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use pulldown_cmark::{Event, HeadingLevel, Parser, Tag, TagEnd};
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use thiserror::Error;
+use utoipa::ToSchema;
+
+#[derive(Debug, Error)]
+pub enum IngestError {
+    #[error("{path}: file content is not valid base64: {reason}")]
+    Decode { path: String, reason: String },
+    #[error("{path}: file of {size} bytes exceeds the limit of {max} bytes")]
+    TooLarge {
+        path: String,
+        size: usize,
+        max: usize,
+    },
+    #[error("{path}: unsupported file format")]
+    UnsupportedFormat { path: String },
+    #[error("{path}: could not extract PDF text: {reason}")]
+    Pdf { path: String, reason: String },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileFormat {
+    Markdown,
+    Html,
+    Pdf,
+    PlainText,
+    Source,
+}
+
+/// Raw file handed to the ingestion pipeline.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub path: String,
+    pub content_type: Option<String>,
+    pub bytes: Vec<u8>,
+}
+
+impl SourceFile {
+    pub fn from_request(file: IngestFile) -> Result<Self, IngestError> {
+        let bytes = STANDARD
+            .decode(file.data.as_bytes())
+            .map_err(|err| IngestError::Decode {
+                path: file.path.clone(),
+                reason: err.to_string(),
+            })?;
+        Ok(Self {
+            path: file.path,
+            content_type: file.content_type,
+            bytes,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExtractedText {
+    pub format: FileFormat,
+    pub title: Option<String>,
+    pub language: Option<String>,
+    pub text: String,
+}
+
+/// Detects the file format from the content type, the extension and the
+/// leading bytes, then extracts plain text and a title.
+pub fn extract(file: &SourceFile, max_bytes: usize) -> Result<ExtractedText, IngestError> {
+    if file.bytes.len() > max_bytes {
+        return Err(IngestError::TooLarge {
+            path: file.path.clone(),
+            size: file.bytes.len(),
+            max: max_bytes,
+        });
+    }
+    let extension = Path::new(&file.path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+    let content_type = file
+        .content_type
+        .as_deref()
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_lowercase())
+        .unwrap_or_default();
+    let file_name = Path::new(&file.path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_string);
+
+    if content_type == "application/pdf" || extension == "pdf" || file.bytes.starts_with(b"%PDF") {
+        let (title, text) = extract_pdf(&file.path, &file.bytes)?;
+        return Ok(ExtractedText {
+            format: FileFormat::Pdf,
+            title: title.or(file_name),
+            language: None,
+            text,
+        });
+    }
+    let Ok(content) = std::str::from_utf8(&file.bytes) else {
+        return Err(IngestError::UnsupportedFormat {
+            path: file.path.clone(),
+        });
+    };
+    let extracted =
+        if content_type == "text/markdown" || matches!(extension.as_str(), "md" | "markdown") {
+            let (title, text) = extract_markdown(content);
+            ExtractedText {
+                format: FileFormat::Markdown,
+                title,
+                language: None,
+                text,
+            }
+        } else if content_type == "text/html" || matches!(extension.as_str(), "html" | "htm") {
+            let (title, text) = extract_html(content);
+            ExtractedText {
+                format: FileFormat::Html,
+                title,
+                language: None,
+                text,
+            }
+        } else if let Some(language) = detect_language(&extension, content) {
+            ExtractedText {
+                format: FileFormat::Source,
+                title: None,
+                language: Some(language.to_string()),
+                text: content.to_string(),
+            }
+        } else {
+            ExtractedText {
+                format: FileFormat::PlainText,
+                title: None,
+                language: None,
+                text: content.to_string(),
+            }
+        };
+    Ok(ExtractedText {
+        title: extracted.title.or(file_name),
+        ..extracted
+    })
+}
+
+fn extract_markdown(content: &str) -> (Option<String>, String) {
+    let mut title = None;
+    let mut in_title = false;
+    let mut heading = String::new();
+    let mut text = String::new();
+    for event in Parser::new(content) {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                in_title = title.is_none() && level == HeadingLevel::H1;
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if in_title {
+                    title = Some(heading.trim().to_string());
+                    in_title = false;
+                }
+                text.push_str("\n\n");
+            }
+            Event::End(TagEnd::Paragraph | TagEnd::CodeBlock | TagEnd::Item) => {
+                text.push_str("\n\n");
+            }
+            Event::Text(value) | Event::Code(value) => {
+                if in_title {
+                    heading.push_str(&value);
+                }
+                text.push_str(&value);
+            }
+            Event::SoftBreak | Event::HardBreak => text.push('\n'),
+            _ => {}
+        }
+    }
+    (title, tidy(&text))
+}
+
+/// Elements whose text is navigation or page chrome rather than content.
+const BOILERPLATE: &[&str] = &[
+    "script", "style", "noscript", "nav", "header", "footer", "aside", "form", "template",
+    "iframe", "svg",
+];
+
+/// Elements that end a paragraph in the extracted text.
+const BLOCKS: &[&str] = &[
+    "p",
+    "div",
+    "section",
+    "article",
+    "main",
+    "li",
+    "ul",
+    "ol",
+    "table",
+    "tr",
+    "pre",
+    "blockquote",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "br",
+    "hr",
+];
+
+fn extract_html(content: &str) -> (Option<String>, String) {
+    let document = Html::parse_document(content);
+    let selector = |css: &str| Selector::parse(css).expect("static selector");
+    let text_of = |element: ElementRef<'_>| {
+        element
+            .text()
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let title = document
+        .select(&selector("title"))
+        .chain(document.select(&selector("h1")))
+        .map(text_of)
+        .find(|title| !title.is_empty());
+    let root = document
+        .select(&selector("main"))
+        .chain(document.select(&selector("article")))
+        .chain(document.select(&selector("body")))
+        .next()
+        .unwrap_or_else(|| document.root_element());
+    let mut text = String::new();
+    collect_html_text(root, &mut text);
+    (title, tidy(&text))
+}
+
+fn collect_html_text(element: ElementRef<'_>, out: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(value) => {
+                let words: Vec<&str> = value.split_whitespace().collect();
+                if words.is_empty() {
+                    continue;
+                }
+                if value.starts_with(char::is_whitespace)
+                    && !out.is_empty()
+                    && !out.ends_with(char::is_whitespace)
+                {
+                    out.push(' ');
+                }
+                out.push_str(&words.join(" "));
+                if value.ends_with(char::is_whitespace) {
+                    out.push(' ');
+                }
+            }
+            Node::Element(node) => {
+                let name = node.name();
+                if BOILERPLATE.contains(&name) {
+                    continue;
+                }
+                let Some(child) = ElementRef::wrap(child) else {
+                    continue;
+                };
+                collect_html_text(child, out);
+                if BLOCKS.contains(&name) {
+                    out.push_str("\n\n");
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn extract_pdf(path: &str, bytes: &[u8]) -> Result<(Option<String>, String), IngestError> {
+    let pdf_error = |reason: String| IngestError::Pdf {
+        path: path.to_string(),
+        reason,
+    };
+    let document = lopdf::Document::load_mem(bytes).map_err(|err| pdf_error(err.to_string()))?;
+    let pages: Vec<u32> = document.get_pages().keys().copied().collect();
+    let mut text = String::new();
+    for page in pages {
+        let page_text = document
+            .extract_text(&[page])
+            .map_err(|err| pdf_error(err.to_string()))?;
+        text.push_str(&page_text);
+        text.push_str("\n\n");
+    }
+    let title = document
+        .trailer
+        .get(b"Info")
+        .and_then(|info| document.dereference(info))
+        .and_then(|(_, info)| info.as_dict())
+        .and_then(|info| info.get(b"Title"))
+        .and_then(|title| title.as_str())
+        .ok()
+        .map(|title| String::from_utf8_lossy(title).trim().to_string())
+        .filter(|title| !title.is_empty());
+    Ok((title, tidy(&text)))
+}
+
+/// Maps file extensions (and shebang lines) to a source language.
+pub fn detect_language(extension: &str, content: &str) -> Option<&'static str> {
+    let language = match extension {
+        "rs" => "rust",
+        "py" | "pyi" => "python",
+        "js" | "mjs" | "cjs" | "jsx" => "javascript",
+        "ts" | "tsx" => "typescript",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" => "cpp",
+        "cs" => "csharp",
+        "rb" => "ruby",
+        "php" => "php",
+        "swift" => "swift",
+        "scala" => "scala",
+        "sh" | "bash" | "zsh" => "shell",
+        "sql" => "sql",
+        "lua" => "lua",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "json" => "json",
+        _ => {
+            let shebang = content.lines().next()?.strip_prefix("#!")?;
+            let interpreter = shebang.split_whitespace().last()?;
+            let interpreter = interpreter.rsplit('/').next()?;
+            return match interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.') {
+                "python" => Some("python"),
+                "sh" | "bash" | "zsh" => Some("shell"),
+                "node" => Some("javascript"),
+                "ruby" => Some("ruby"),
+                "perl" => Some("perl"),
+                _ => None,
+            };
+        }
+    };
+    Some(language)
+}
+
+/// Collapses runs of blank lines and trailing whitespace.
+fn tidy(text: &str) -> String {
+    let mut out = String::new();
+    let mut blank = 0;
+    for line in text.lines().map(str::trim_end) {
+        if line.trim().is_empty() {
+            blank += 1;
+            continue;
+        }
+        if !out.is_empty() {
+            out.push_str(if blank > 0 { "\n\n" } else { "\n" });
+        }
+        blank = 0;
+        out.push_str(line);
+    }
+    out
+}
+
+/// Splits text into chunks of at most `size` characters, packing whole
+/// paragraphs where possible. Paragraphs longer than `size` are cut into
+/// windows that repeat `overlap` characters of the previous window.
+pub fn chunk_text(text: &str, size: usize, overlap: usize) -> Vec<String> {
+    let size = size.max(1);
+    let step = size.saturating_sub(overlap).max(1);
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+    for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        for piece in windows(paragraph, size, step) {
+            let piece_len = piece.chars().count();
+            if current_len > 0 && current_len + 2 + piece_len > size {
+                chunks.push(std::mem::take(&mut current));
+                current_len = 0;
+            }
+            if current_len > 0 {
+                current.push_str("\n\n");
+                current_len += 2;
+            }
+            current.push_str(&piece);
+            current_len += piece_len;
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+fn windows(paragraph: &str, size: usize, step: usize) -> Vec<String> {
+    let chars: Vec<char> = paragraph.chars().collect();
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    loop {
+        let end = (start + size).min(chars.len());
+        pieces.push(chars[start..end].iter().collect());
+        if end == chars.len() {
+            return pieces;
+        }
+        start += step;
+    }
+}
+
+/// Builds one document per chunk with ids derived from the file path so
+/// that re-ingesting a file replaces its chunks. Caller metadata is merged
+/// over the extracted `title`, `path`, `format` and `language`.
+pub fn to_documents(
+    file: &SourceFile,
+    extracted: &ExtractedText,
+    chunks: &[String],
+    metadata: &serde_json::Value,
+) -> Vec<DocumentInput> {
+    chunks
+        .iter()
+        .enumerate()
+        .map(|(idx, chunk)| {
+            let mut fields = serde_json::Map::new();
+            fields.insert("path".into(), file.path.clone().into());
+            fields.insert(
+                "format".into(),
+                serde_json::to_value(extracted.format).expect("format serializes"),
+            );
+            if let Some(title) = &extracted.title {
+                fields.insert("title".into(), title.clone().into());
+            }
+            if let Some(language) = &extracted.language {
+                fields.insert("language".into(), language.clone().into());
+            }
+            fields.insert("chunk".into(), idx.into());
+            fields.insert("chunks".into(), chunks.len().into());
+            if let Some(extra) = metadata.as_object() {
+                fields.extend(extra.clone());
+            }
+            DocumentInput {
+                id: Some(chunk_id(&file.path, idx)),
+                text: chunk.clone(),
+                metadata: serde_json::Value::Object(fields),
+            }
+        })
+        .collect()
+}
+
+pub fn chunk_id(path: &str, index: usize) -> String {
+    format!("{path}#{index}")
+}
+
+pub fn summary(file: &SourceFile, extracted: &ExtractedText, chunks: usize) -> IngestedFile {
+    IngestedFile {
+        path: file.path.clone(),
+        format: extracted.format,
+        title: extracted.title.clone(),
+        language: extracted.language.clone(),
+        chunks,
+    }
+}
@@ -0,0 +1,284 @@
+use crate::core::config::LimitsConfig;
+use crate::core::errors::{ErrorCode, ErrorDetail};
+use crate::core::schema::LimitStats;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::sync::Notify;
+
+#[derive(Debug, Error)]
+pub enum LimitError {
+    #[error("rate limit exceeded for tenant {tenant} on {capability}")]
+    RateLimited {
+        tenant: String,
+        capability: String,
+        retry_after: Duration,
+    },
+    #[error("too many concurrent requests for {capability}")]
+    Overloaded { capability: String },
+}
+
+impl LimitError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            LimitError::RateLimited { .. } => ErrorCode::RateLimited,
+            LimitError::Overloaded { .. } => ErrorCode::Overloaded,
+        }
+    }
+
+    /// Suggested wait before retrying.
+    pub fn retry_after(&self) -> Duration {
+        match self {
+            LimitError::RateLimited { retry_after, .. } => *retry_after,
+            LimitError::Overloaded { .. } => Duration::from_secs(1),
+        }
+    }
+}
+
+impl From<LimitError> for ErrorDetail {
+    fn from(error: LimitError) -> Self {
+        ErrorDetail::new(error.code(), error.to_string())
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Default)]
+struct LimiterState {
+    in_flight: BTreeMap<String, usize>,
+    total: usize,
+    waiting: usize,
+    waiting_priority: usize,
+    /// Keyed by tenant and capability.
+    buckets: HashMap<(String, String), Bucket>,
+    rate_limited: u64,
+    overloaded: u64,
+}
+
+/// Admission control shared by the HTTP router and the bus handlers.
+/// Limits are read from the config on every call so reloads apply to the
+/// next request.
+#[derive(Clone, Default)]
+pub struct Limiter {
+    state: Arc<Mutex<LimiterState>>,
+    released: Arc<Notify>,
+}
+
+/// Holds a processing slot until dropped.
+pub struct LimitPermit {
+    limiter: Limiter,
+    capability: String,
+}
+
+impl Drop for LimitPermit {
+    fn drop(&mut self) {
+        let mut state = self.limiter.state.lock();
+        state.total -= 1;
+        if let Some(count) = state.in_flight.get_mut(&self.capability) {
+            *count -= 1;
+            if *count == 0 {
+                state.in_flight.remove(&self.capability);
+            }
+        }
+        drop(state);
+        self.limiter.released.notify_waiters();
+    }
+}
+
+/// Outcome of [`Limiter::try_acquire`].
+pub enum Admission {
+    Admitted(LimitPermit),
+    /// No slot was free, so the request holds a place in the wait queue.
+    Queued(QueuedRequest),
+}
+
+impl Admission {
+    /// Returns the permit, waiting for a slot if the request was queued.
+    pub async fn permit(self) -> Result<LimitPermit, LimitError> {
+        match self {
+            Admission::Admitted(permit) => Ok(permit),
+            Admission::Queued(queued) => queued.wait().await,
+        }
+    }
+}
+
+/// A place in the wait queue. It is left on every exit path, including
+/// cancellation.
+pub struct QueuedRequest {
+    limiter: Limiter,
+    config: LimitsConfig,
+    capability: String,
+    priority: bool,
+    deadline: tokio::time::Instant,
+}
+
+impl QueuedRequest {
+    async fn wait(self) -> Result<LimitPermit, LimitError> {
+        let limiter = &self.limiter;
+        loop {
+            let released = limiter.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+            if let Some(permit) = limiter.try_admit(&self.config, &self.capability, self.priority) {
+                return Ok(permit);
+            }
+            if tokio::time::timeout_at(self.deadline, released)
+                .await
+                .is_err()
+            {
+                limiter.state.lock().overloaded += 1;
+                return Err(LimitError::Overloaded {
+                    capability: self.capability.clone(),
+                });
+            }
+        }
+    }
+}
+
+impl Drop for QueuedRequest {
+    fn drop(&mut self) {
+        let mut state = self.limiter.state.lock();
+        state.waiting -= 1;
+        if self.priority {
+            state.waiting_priority -= 1;
+            drop(state);
+            self.limiter.released.notify_waiters();
+        }
+    }
+}
+
+impl Limiter {
+    /// Takes a token from the tenant's bucket for `capability` (with or
+    /// without the `brainml.` prefix), then waits
+    /// up to `queue_timeout_ms` for a processing slot. Priority capabilities
+    /// may use the reserved slots, and while one of them is queued no other
+    /// request is admitted.
+    pub async fn acquire(
+        &self,
+        config: &LimitsConfig,
+        tenant: &str,
+        capability: &str,
+    ) -> Result<LimitPermit, LimitError> {
+        self.try_acquire(config, tenant, capability)?.permit().await
+    }
+
+    /// The part of [`Limiter::acquire`] that does not wait: takes the token
+    /// and either admits the request or queues it. A request that would
+    /// queue behind `max_queued` others is rejected instead; priority
+    /// capabilities are not counted against that cap.
+    pub fn try_acquire(
+        &self,
+        config: &LimitsConfig,
+        tenant: &str,
+        capability: &str,
+    ) -> Result<Admission, LimitError> {
+        let capability = capability.strip_prefix("brainml.").unwrap_or(capability);
+        self.take_token(config, tenant, capability)?;
+        let priority = config.priority.iter().any(|name| name == capability);
+        if let Some(permit) = self.try_admit(config, capability, priority) {
+            return Ok(Admission::Admitted(permit));
+        }
+        let mut state = self.state.lock();
+        if !priority && state.waiting - state.waiting_priority >= config.max_queued {
+            state.overloaded += 1;
+            return Err(LimitError::Overloaded {
+                capability: capability.to_string(),
+            });
+        }
+        state.waiting += 1;
+        if priority {
+            state.waiting_priority += 1;
+        }
+        Ok(Admission::Queued(QueuedRequest {
+            limiter: self.clone(),
+            config: config.clone(),
+            capability: capability.to_string(),
+            priority,
+            deadline: tokio::time::Instant::now() + Duration::from_millis(config.queue_timeout_ms),
+        }))
+    }
+
+    pub fn stats(&self) -> LimitStats {
+        let state = self.state.lock();
+        LimitStats {
+            in_flight: state.in_flight.clone(),
+            waiting: state.waiting,
+            rate_limited: state.rate_limited,
+            overloaded: state.overloaded,
+        }
+    }
+
+    fn take_token(
+        &self,
+        config: &LimitsConfig,
+        tenant: &str,
+        capability: &str,
+    ) -> Result<(), LimitError> {
+        let Some(rate) = config.rate.get(capability) else {
+            return Ok(());
+        };
+        let burst = f64::from(rate.burst);
+        let now = Instant::now();
+        let mut state = self.state.lock();
+        let bucket = state
+            .buckets
+            .entry((tenant.to_string(), capability.to_string()))
+            .or_insert(Bucket {
+                tokens: burst,
+                updated: now,
+            });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate.per_second).min(burst);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        let retry_after = Duration::from_secs_f64((1.0 - bucket.tokens) / rate.per_second);
+        state.rate_limited += 1;
+        Err(LimitError::RateLimited {
+            tenant: tenant.to_string(),
+            capability: capability.to_string(),
+            retry_after,
+        })
+    }
+
+    fn try_admit(
+        &self,
+        config: &LimitsConfig,
+        capability: &str,
+        priority: bool,
+    ) -> Option<LimitPermit> {
+        let mut state = self.state.lock();
+        let slots = if priority {
+            config.max_in_flight
+        } else {
+            config
+                .max_in_flight
+                .saturating_sub(config.reserved_slots)
+                .max(1)
+        };
+        if state.total >= slots || (!priority && state.waiting_priority > 0) {
+            return None;
+        }
+        let current = state.in_flight.get(capability).copied().unwrap_or(0);
+        if config
+            .concurrency
+            .get(capability)
+            .is_some_and(|max| current >= *max)
+        {
+            return None;
+        }
+        state.total += 1;
+        *state.in_flight.entry(capability.to_string()).or_insert(0) += 1;
+        Some(LimitPermit {
+            limiter: self.clone(),
+            capability: capability.to_string(),
+        })
+    }
+}
@@ -0,0 +1,298 @@
+use crate::adapters::braindb::{BraindbClient, ScanDocumentsRequest, UpsertDocumentsRequest};
+use crate::adapters::llm::LlmClient;
+use crate::core::cache::QueryCache;
+use crate::core::config::BrainmlConfig;
+use crate::core::embeddings::embed_documents;
+use crate::core::schema::{DocumentInput, DocumentRecord, MigrationProgress, MigrationState};
+use crate::core::tenancy::TenantContext;
+use anyhow::Result;
+use chrono::Utc;
+use parking_lot::RwLock;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::{error, info, instrument};
+
+/// Re-embeds records whose stored vectors were produced by a model other
+/// than the one currently configured for their field.
+#[derive(Clone, Default)]
+pub struct EmbeddingMigrator {
+    progress: Arc<RwLock<BTreeMap<String, MigrationProgress>>>,
+    running: Arc<AtomicBool>,
+}
+
+impl EmbeddingMigrator {
+    /// Spawns a background migration across the tenant's collections, or
+    /// every collection for the default tenant. Returns `None` when a
+    /// migration is already running.
+    pub fn start(
+        &self,
+        braindb: Arc<dyn BraindbClient>,
+        llm: Arc<dyn LlmClient>,
+        cache: QueryCache,
+        config: BrainmlConfig,
+        tenant: TenantContext,
+        batch_size: usize,
+    ) -> Option<JoinHandle<()>> {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return None;
+        }
+        let migrator = self.clone();
+        Some(tokio::spawn(async move {
+            if let Err(err) = migrator
+                .migrate_all(
+                    braindb.as_ref(),
+                    llm.as_ref(),
+                    &cache,
+                    &config,
+                    &tenant,
+                    batch_size,
+                )
+                .await
+            {
+                error!(error = %err, "embedding migration failed");
+            }
+            migrator.running.store(false, Ordering::SeqCst);
+        }))
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    pub fn progress(&self) -> Vec<MigrationProgress> {
+        self.progress.read().values().cloned().collect()
+    }
+
+    /// Migrates the collections visible to `tenant`; the default tenant is
+    /// the operator and migrates every namespace.
+    #[instrument(skip_all, fields(tenant = %tenant.id, batch_size))]
+    pub async fn migrate_all(
+        &self,
+        braindb: &dyn BraindbClient,
+        llm: &dyn LlmClient,
+        cache: &QueryCache,
+        config: &BrainmlConfig,
+        tenant: &TenantContext,
+        batch_size: usize,
+    ) -> Result<Vec<MigrationProgress>> {
+        let stats = braindb.stats().await?;
+        let mut finished = Vec::new();
+        for collection in stats.collections {
+            if !tenant.is_default() && tenant.unscope(&collection.name).is_none() {
+                continue;
+            }
+            let progress = self
+                .migrate_collection(braindb, llm, cache, config, &collection.name, batch_size)
+                .await;
+            finished.push(progress);
+        }
+        Ok(finished)
+    }
+
+    #[instrument(skip_all, fields(collection = %collection))]
+    async fn migrate_collection(
+        &self,
+        braindb: &dyn BraindbClient,
+        llm: &dyn LlmClient,
+        cache: &QueryCache,
+        config: &BrainmlConfig,
+        collection: &str,
+        batch_size: usize,
+    ) -> MigrationProgress {
+        let now = Utc::now();
+        self.update(MigrationProgress {
+            collection: collection.to_string(),
+            state: MigrationState::Running,
+            target_model: config.embedding_model.clone(),
+            scanned: 0,
+            migrated: 0,
+            error: None,
+            started_at: now,
+            updated_at: now,
+        });
+        let result = self
+            .migrate_batches(braindb, llm, cache, config, collection, batch_size.max(1))
+            .await;
+        let mut progress = self.get(collection);
+        progress.updated_at = Utc::now();
+        match result {
+            Ok(()) => progress.state = MigrationState::Completed,
+            Err(err) => {
+                progress.state = MigrationState::Failed;
+                progress.error = Some(err.to_string());
+            }
+        }
+        self.update(progress.clone());
+        info!(
+            scanned = progress.scanned,
+            migrated = progress.migrated,
+            state = ?progress.state,
+            "embedding migration finished"
+        );
+        progress
+    }
+
+    /// Re-embeds one window of the collection at a time. Embedding is slow,
+    /// so each window is read again before the upsert and a record that no
+    /// longer matches the copy that was embedded is left alone rather than
+    /// overwritten with its old text; the next migration picks it up if it
+    /// is still stale.
+    async fn migrate_batches(
+        &self,
+        braindb: &dyn BraindbClient,
+        llm: &dyn LlmClient,
+        cache: &QueryCache,
+        config: &BrainmlConfig,
+        collection: &str,
+        batch_size: usize,
+    ) -> Result<()> {
+        let mut offset = 0;
+        loop {
+            let batch = braindb
+                .scan_documents(ScanDocumentsRequest {
+                    collection: collection.to_string(),
+                    offset,
+                    limit: batch_size,
+                })
+                .await?;
+            if batch.is_empty() {
+                return Ok(());
+            }
+            let scanned = batch.len();
+            let mut stale: Vec<DocumentRecord> = batch
+                .into_iter()
+                .filter(|record| is_stale(record, config))
+                .collect();
+            let mut migrated = 0;
+            if !stale.is_empty() {
+                let read: HashMap<String, DocumentRecord> = stale
+                    .iter()
+                    .map(|record| (record.id.clone(), record.clone()))
+                    .collect();
+                reembed(llm, config, &mut stale).await?;
+                let current: HashMap<String, DocumentRecord> = braindb
+                    .scan_documents(ScanDocumentsRequest {
+                        collection: collection.to_string(),
+                        offset,
+                        limit: batch_size,
+                    })
+                    .await?
+                    .into_iter()
+                    .map(|record| (record.id.clone(), record))
+                    .collect();
+                stale.retain(|record| current.get(&record.id) == read.get(&record.id));
+                migrated = stale.len();
+                if !stale.is_empty() {
+                    braindb
+                        .upsert_documents(UpsertDocumentsRequest {
+                            collection: collection.to_string(),
+                            documents: stale,
+                        })
+                        .await?;
+                    cache.invalidate(collection);
+                }
+            }
+            offset += scanned;
+            let mut progress = self.get(collection);
+            progress.scanned += scanned;
+            progress.migrated += migrated;
+            progress.updated_at = Utc::now();
+            info!(
+                collection,
+                scanned = progress.scanned,
+                migrated = progress.migrated,
+                "embedding migration batch applied"
+            );
+            self.update(progress);
+            if scanned < batch_size {
+                return Ok(());
+            }
+        }
+    }
+
+    fn get(&self, collection: &str) -> MigrationProgress {
+        self.progress
+            .read()
+            .get(collection)
+            .cloned()
+            .expect("migration progress registered before use")
+    }
+
+    fn update(&self, progress: MigrationProgress) {
+        self.progress
+            .write()
+            .insert(progress.collection.clone(), progress);
+    }
+}
+
+/// A record is stale when its default embedding or any configured named
+/// vector was produced by a different model than the configured one.
+pub fn is_stale(record: &DocumentRecord, config: &BrainmlConfig) -> bool {
+    let default_stale = record
+        .embedding
+        .as_ref()
+        .is_some_and(|values| !values.is_empty())
+        && record.embedding_model != config.embedding_model;
+    default_stale
+        || record.vectors.iter().any(|(field, named)| {
+            config
+                .vector_fields
+                .get(field)
+                .is_some_and(|spec| spec.model != named.model)
+        })
+}
+
+async fn reembed(
+    llm: &dyn LlmClient,
+    config: &BrainmlConfig,
+    records: &mut [DocumentRecord],
+) -> Result<()> {
+    let inputs: Vec<DocumentInput> = records
+        .iter()
+        .map(|record| DocumentInput {
+            id: Some(record.id.clone()),
+            text: record.text.clone(),
+            metadata: serde_json::Value::Null,
+        })
+        .collect();
+    let default_model = config.embedding_model.as_deref();
+    if records.iter().any(|record| {
+        record
+            .embedding
+            .as_ref()
+            .is_some_and(|values| !values.is_empty())
+            && record.embedding_model.as_deref() != default_model
+    }) {
+        let vectors = embed_documents(llm, &inputs, default_model).await?;
+        for (record, vector) in records.iter_mut().zip(vectors) {
+            if record
+                .embedding
+                .as_ref()
+                .is_some_and(|values| !values.is_empty())
+            {
+                record.embedding = Some(vector);
+                record.embedding_model = default_model.map(str::to_string);
+            }
+        }
+    }
+    for (field, spec) in &config.vector_fields {
+        if !records.iter().any(|record| {
+            record
+                .vectors
+                .get(field)
+                .is_some_and(|named| named.model != spec.model)
+        }) {
+            continue;
+        }
+        let vectors = embed_documents(llm, &inputs, Some(&spec.model)).await?;
+        for (record, vector) in records.iter_mut().zip(vectors) {
+            if let Some(named) = record.vectors.get_mut(field) {
+                named.model = spec.model.clone();
+                named.values = vector;
+            }
+        }
+    }
+    Ok(())
+}
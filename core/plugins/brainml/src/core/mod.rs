@@ -1,8 +1,28 @@
+pub mod aggregations;
+pub mod aliases;
+pub mod analytics;
+pub mod audit;
+pub mod bulk;
 pub mod bus;
+pub mod cache;
 pub mod config;
+pub mod distance;
 pub mod embeddings;
+pub mod errors;
+pub mod evaluation;
+pub mod expiry;
+pub mod highlight;
+pub mod ingest;
+pub mod limits;
+pub mod migrator;
+pub mod pagination;
 pub mod pipeline;
+pub mod quantization;
 pub mod ranker;
 pub mod retriever;
+pub mod rewrite;
 pub mod schema;
 pub mod scoring;
+pub mod sync;
+pub mod tenancy;
+pub mod wal;
@@ -0,0 +1,205 @@
+use crate::core::schema::{QueryRequest, QueryResult, TotalHits, TotalHitsRelation};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PaginationError {
+    #[error("invalid cursor: {0}")]
+    InvalidCursor(String),
+    #[error("offset and cursor cannot be combined")]
+    OffsetWithCursor,
+    #[error("result window of {requested} exceeds the maximum of {max}")]
+    WindowTooLarge { requested: usize, max: usize },
+}
+
+/// Search-after position: the retrieval score and id of the last result
+/// returned, plus how many results precede the next page.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Cursor {
+    pub score: f32,
+    pub id: String,
+    pub position: usize,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let bytes = serde_json::to_vec(self).expect("cursor serializes");
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    pub fn decode(value: &str) -> Result<Self, PaginationError> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(value)
+            .map_err(|err| PaginationError::InvalidCursor(err.to_string()))?;
+        serde_json::from_slice(&bytes)
+            .map_err(|err| PaginationError::InvalidCursor(err.to_string()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Page {
+    pub offset: usize,
+    pub cursor: Option<Cursor>,
+    pub size: usize,
+    /// Candidate pool of a diversified query. Diversified results are not
+    /// in retrieval order, so cursors resume after the last returned id
+    /// instead of seeking by score.
+    pub diversify_candidates: Option<usize>,
+    /// Aggregations are computed over the whole result window.
+    pub aggregate: bool,
+    max_window: usize,
+}
+
+impl Page {
+    pub fn from_request(
+        request: &QueryRequest,
+        max_window: usize,
+    ) -> Result<Self, PaginationError> {
+        let cursor = request.cursor.as_deref().map(Cursor::decode).transpose()?;
+        if cursor.is_some() && request.offset > 0 {
+            return Err(PaginationError::OffsetWithCursor);
+        }
+        let start = cursor
+            .as_ref()
+            .map(|cursor| cursor.position)
+            .unwrap_or(request.offset);
+        let requested = start + request.top_k;
+        if requested > max_window {
+            return Err(PaginationError::WindowTooLarge {
+                requested,
+                max: max_window,
+            });
+        }
+        Ok(Self {
+            offset: request.offset,
+            cursor,
+            size: request.top_k,
+            diversify_candidates: request.diversify.as_ref().map(|options| options.candidates),
+            aggregate: !request.aggregations.is_empty(),
+            max_window,
+        })
+    }
+
+    /// Ranked results the page needs. One extra result tells whether a
+    /// further page exists; cursor pages also take a page of slack so that
+    /// documents inserted ahead of the cursor do not push the page out of
+    /// the window. Diversification stops once it has picked this many.
+    pub fn window(&self) -> usize {
+        match &self.cursor {
+            Some(cursor) => cursor.position + self.size * 2 + 1,
+            None => self.offset + self.size + 1,
+        }
+    }
+
+    /// Number of candidates to retrieve: the [`Self::window`], at least the
+    /// candidate pool for diversified queries and the whole result window
+    /// for aggregating ones.
+    pub fn fetch_size(&self) -> usize {
+        if self.aggregate {
+            return self.max_window + 1;
+        }
+        let wanted = self.window();
+        let wanted = match self.diversify_candidates {
+            Some(candidates) => wanted.max(candidates),
+            None => wanted,
+        };
+        wanted.min(self.max_window + 1)
+    }
+}
+
+/// Orders candidates by retrieval score, breaking ties by id, so that the
+/// same documents always come back in the same order.
+pub fn stable_sort(results: &mut [QueryResult]) {
+    results.sort_by(|a, b| compare(a.score, &a.id, b.score, &b.id));
+    for (idx, result) in results.iter_mut().enumerate() {
+        result.rank = idx + 1;
+    }
+}
+
+fn compare(score_a: f32, id_a: &str, score_b: f32, id_b: &str) -> Ordering {
+    score_b
+        .partial_cmp(&score_a)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| id_a.cmp(id_b))
+}
+
+pub struct PageSlice {
+    pub results: Vec<QueryResult>,
+    pub total_hits: TotalHits,
+    pub next_cursor: Option<String>,
+}
+
+/// Cuts the requested page out of the ranked candidates. `retrieval_scores`
+/// holds the pre-fusion score of each candidate; cursors are keyed on it
+/// because fused scores shift as documents are upserted.
+pub fn slice(
+    page: &Page,
+    results: Vec<QueryResult>,
+    retrieval_scores: &HashMap<String, f32>,
+    fetched: usize,
+) -> PageSlice {
+    let score_of = |result: &QueryResult| {
+        retrieval_scores
+            .get(&result.id)
+            .copied()
+            .unwrap_or(result.score)
+    };
+    let total = results.len();
+    let relation = if fetched < page.fetch_size() {
+        TotalHitsRelation::Eq
+    } else {
+        TotalHitsRelation::Gte
+    };
+    let (start, position) = match &page.cursor {
+        Some(cursor) if page.diversify_candidates.is_some() => {
+            let start = results
+                .iter()
+                .position(|result| result.id == cursor.id)
+                .map(|idx| idx + 1)
+                .unwrap_or(cursor.position.min(total));
+            (start, cursor.position)
+        }
+        Some(cursor) => {
+            let start = results
+                .iter()
+                .position(|result| {
+                    compare(cursor.score, &cursor.id, score_of(result), &result.id)
+                        == Ordering::Less
+                })
+                .unwrap_or(total);
+            (start, cursor.position)
+        }
+        None => (page.offset.min(total), page.offset),
+    };
+    let end = (start + page.size).min(total);
+    let next_cursor = (end < total && end > start).then(|| {
+        Cursor {
+            score: score_of(&results[end - 1]),
+            id: results[end - 1].id.clone(),
+            position: position + (end - start),
+        }
+        .encode()
+    });
+    let mut page_results: Vec<QueryResult> =
+        results.into_iter().skip(start).take(end - start).collect();
+    for (idx, result) in page_results.iter_mut().enumerate() {
+        result.rank = position + idx + 1;
+    }
+    PageSlice {
+        results: page_results,
+        total_hits: TotalHits {
+            // A full window ends with the one extra candidate that only
+            // shows more exist; it is not counted.
+            value: match relation {
+                TotalHitsRelation::Eq => total,
+                TotalHitsRelation::Gte => total.saturating_sub(1),
+            },
+            relation,
+        },
+        next_cursor,
+    }
+}
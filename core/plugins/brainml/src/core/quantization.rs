@@ -0,0 +1,163 @@
+use crate::core::distance;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// How a collection stores its default embedding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Quantization {
+    /// Full precision `f32` values.
+    #[default]
+    None,
+    /// One byte per dimension, scaled between the vector's min and max.
+    Int8,
+    /// One bit per dimension: the sign, with the mean magnitude kept once.
+    Binary,
+}
+
+/// A compressed embedding. Scores are computed asymmetrically: the query
+/// stays full precision and only the stored side is approximated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuantizedVector {
+    Int8 {
+        codes: Vec<u8>,
+        min: f32,
+        scale: f32,
+    },
+    Binary {
+        bits: Vec<u64>,
+        dims: usize,
+        magnitude: f32,
+    },
+}
+
+impl QuantizedVector {
+    /// Encodes `values`, or returns `None` for `Quantization::None`.
+    pub fn encode(kind: Quantization, values: &[f32]) -> Option<Self> {
+        let finite = values.iter().copied().filter(|value| value.is_finite());
+        match kind {
+            Quantization::None => None,
+            Quantization::Int8 => {
+                let (min, max) = finite.fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), v| {
+                    (lo.min(v), hi.max(v))
+                });
+                if min > max {
+                    return Some(QuantizedVector::Int8 {
+                        codes: vec![0; values.len()],
+                        min: 0.0,
+                        scale: 0.0,
+                    });
+                }
+                let scale = (max - min) / u8::MAX as f32;
+                let codes = values
+                    .iter()
+                    .map(|value| {
+                        if scale > 0.0 {
+                            ((value.clamp(min, max) - min) / scale).round() as u8
+                        } else {
+                            0
+                        }
+                    })
+                    .collect();
+                Some(QuantizedVector::Int8 { codes, min, scale })
+            }
+            Quantization::Binary => {
+                let count = finite.clone().count();
+                let magnitude = if count == 0 {
+                    0.0
+                } else {
+                    finite.map(f32::abs).sum::<f32>() / count as f32
+                };
+                let mut bits = vec![0u64; values.len().div_ceil(64)];
+                for (idx, value) in values.iter().enumerate() {
+                    if *value > 0.0 {
+                        bits[idx / 64] |= 1 << (idx % 64);
+                    }
+                }
+                Some(QuantizedVector::Binary {
+                    bits,
+                    dims: values.len(),
+                    magnitude,
+                })
+            }
+        }
+    }
+
+    pub fn kind(&self) -> Quantization {
+        match self {
+            QuantizedVector::Int8 { .. } => Quantization::Int8,
+            QuantizedVector::Binary { .. } => Quantization::Binary,
+        }
+    }
+
+    pub fn dimensions(&self) -> usize {
+        match self {
+            QuantizedVector::Int8 { codes, .. } => codes.len(),
+            QuantizedVector::Binary { dims, .. } => *dims,
+        }
+    }
+
+    /// Approximate dot product with a full precision `query`, without
+    /// decoding: int8 codes go through the SIMD affine kernel, binary
+    /// codes give `magnitude * (Σq where set − Σq where clear)`.
+    pub fn dot(&self, query: &[f32]) -> f32 {
+        match self {
+            QuantizedVector::Int8 { codes, min, scale } => {
+                distance::dot_u8_affine(query, codes, *min, *scale)
+            }
+            QuantizedVector::Binary {
+                bits, magnitude, ..
+            } => {
+                let (set, total) = query.iter().take(self.dimensions()).enumerate().fold(
+                    (0.0, 0.0),
+                    |(set, total), (idx, q)| {
+                        if bits[idx / 64] & (1 << (idx % 64)) != 0 {
+                            (set + q, total + q)
+                        } else {
+                            (set, total + q)
+                        }
+                    },
+                );
+                magnitude * (2.0 * set - total)
+            }
+        }
+    }
+
+    /// Reconstructs an approximation of the encoded values.
+    pub fn decode(&self) -> Vec<f32> {
+        match self {
+            QuantizedVector::Int8 { codes, min, scale } => codes
+                .iter()
+                .map(|code| min + scale * *code as f32)
+                .collect(),
+            QuantizedVector::Binary {
+                bits,
+                dims,
+                magnitude,
+            } => (0..*dims)
+                .map(|idx| {
+                    if bits[idx / 64] & (1 << (idx % 64)) != 0 {
+                        *magnitude
+                    } else {
+                        -magnitude
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Bytes held by the codes and their parameters.
+    pub fn size_bytes(&self) -> usize {
+        match self {
+            QuantizedVector::Int8 { codes, .. } => codes.len() + 2 * std::mem::size_of::<f32>(),
+            QuantizedVector::Binary { bits, .. } => {
+                bits.len() * std::mem::size_of::<u64>() + std::mem::size_of::<f32>()
+            }
+        }
+    }
+}
+
+/// Bytes held by a full precision vector of `dims` dimensions.
+pub fn full_precision_bytes(dims: usize) -> usize {
+    dims * std::mem::size_of::<f32>()
+}
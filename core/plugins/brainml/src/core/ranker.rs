@@ -1,5 +1,8 @@
-use crate::core::schema::QueryResult;
-use std::collections::HashMap;
+use crate::core::distance::cosine;
+use crate::core::highlight::query_terms;
+use crate::core::schema::{DiversifyOptions, QueryResult};
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
 use tracing::instrument;
 
 #[instrument(skip_all, fields(candidates = results.len(), rrf_k = rrf_k))]
@@ -23,3 +26,129 @@ pub fn reciprocal_rank_fusion(mut results: Vec<QueryResult>, rrf_k: usize) -> Ve
     }
     results
 }
+
+/// Largest candidate pool a diversified query may ask for. Each pick
+/// compares the pick against every remaining candidate.
+pub const MAX_DIVERSIFY_CANDIDATES: usize = 1000;
+
+#[derive(Debug, Error)]
+pub enum RankerError {
+    #[error("lambda must be between 0 and 1, got {0}")]
+    InvalidLambda(f32),
+    #[error("diversify candidates must be between 1 and {max}, got {requested}")]
+    InvalidCandidates { requested: usize, max: usize },
+}
+
+/// Checks diversify options before any candidates are retrieved.
+pub fn validate(options: &DiversifyOptions) -> Result<(), RankerError> {
+    if !(0.0..=1.0).contains(&options.lambda) {
+        return Err(RankerError::InvalidLambda(options.lambda));
+    }
+    if !(1..=MAX_DIVERSIFY_CANDIDATES).contains(&options.candidates) {
+        return Err(RankerError::InvalidCandidates {
+            requested: options.candidates,
+            max: MAX_DIVERSIFY_CANDIDATES,
+        });
+    }
+    Ok(())
+}
+
+/// Drops near-duplicates and reorders the remaining results by maximal
+/// marginal relevance: each pick maximises
+/// `lambda * relevance - (1 - lambda) * max similarity to earlier picks`.
+/// Similarity uses the searched embedding when both documents carry one and
+/// falls back to term overlap otherwise. Stops after `limit` picks, so the
+/// cost grows with the candidates times the requested window.
+#[instrument(skip_all, fields(candidates = results.len(), lambda = options.lambda, limit))]
+pub fn diversify(
+    results: Vec<QueryResult>,
+    options: &DiversifyOptions,
+    vector_field: Option<&str>,
+    limit: usize,
+) -> Result<Vec<QueryResult>, RankerError> {
+    validate(options)?;
+    let features: Vec<Features> = results
+        .iter()
+        .map(|result| Features::new(result, vector_field))
+        .collect();
+    let mut candidates: Vec<usize> = (0..results.len()).collect();
+    // Highest similarity of each candidate to any pick so far.
+    let mut redundancy = vec![0.0_f32; results.len()];
+    let mut selected: Vec<usize> = Vec::with_capacity(limit.min(results.len()));
+    let mut seen_keys = HashSet::new();
+    while !candidates.is_empty() && selected.len() < limit {
+        let mut best: Option<(usize, f32)> = None;
+        for (slot, &idx) in candidates.iter().enumerate() {
+            let mmr =
+                options.lambda * results[idx].score - (1.0 - options.lambda) * redundancy[idx];
+            if best.is_none_or(|(_, current)| mmr > current) {
+                best = Some((slot, mmr));
+            }
+        }
+        let (slot, _) = best.expect("candidates is not empty");
+        let idx = candidates.remove(slot);
+        if let Some(key) = &options.collapse_key {
+            if let Some(value) = results[idx].document.metadata.get(key) {
+                if !seen_keys.insert(value.to_string()) {
+                    continue;
+                }
+            }
+        }
+        if !selected.is_empty()
+            && options
+                .collapse_threshold
+                .is_some_and(|threshold| redundancy[idx] >= threshold)
+        {
+            continue;
+        }
+        selected.push(idx);
+        for &other in &candidates {
+            let similarity = features[other].similarity(&features[idx]);
+            redundancy[other] = redundancy[other].max(similarity);
+        }
+    }
+    let mut slots: Vec<Option<QueryResult>> = results.into_iter().map(Some).collect();
+    Ok(selected
+        .into_iter()
+        .enumerate()
+        .map(|(position, idx)| {
+            let mut result = slots[idx].take().expect("each result is selected once");
+            result.rank = position + 1;
+            result
+        })
+        .collect())
+}
+
+struct Features<'a> {
+    embedding: Option<&'a [f32]>,
+    terms: HashSet<String>,
+}
+
+impl<'a> Features<'a> {
+    fn new(result: &'a QueryResult, vector_field: Option<&str>) -> Self {
+        let document = &result.document;
+        let embedding = match vector_field {
+            Some(field) => document
+                .vectors
+                .get(field)
+                .map(|named| named.values.as_slice()),
+            None => document.embedding.as_deref(),
+        }
+        .filter(|values| !values.is_empty() && values.iter().all(|value| value.is_finite()));
+        Self {
+            embedding,
+            terms: query_terms(&document.text).into_iter().collect(),
+        }
+    }
+
+    fn similarity(&self, other: &Features<'_>) -> f32 {
+        if let (Some(a), Some(b)) = (self.embedding, other.embedding) {
+            return cosine(a, b);
+        }
+        if self.terms.is_empty() && other.terms.is_empty() {
+            return 0.0;
+        }
+        let shared = self.terms.intersection(&other.terms).count();
+        shared as f32 / self.terms.union(&other.terms).count() as f32
+    }
+}
@@ -1,6 +1,7 @@
 use crate::adapters::braindb::{
     BraindbClient, CreateCollectionRequest, HybridQueryRequest, UpsertDocumentsRequest,
 };
+use crate::core::config::QuantizationSettings;
 use crate::core::schema::{
     DocumentInput, DocumentRecord, QueryRequest, QueryResult, QueryStrategy,
 };
@@ -8,10 +9,15 @@ use anyhow::Result;
 use tracing::instrument;
 
 #[instrument(skip_all)]
-pub async fn ensure_collection<C: BraindbClient + ?Sized>(client: &C, name: &str) -> Result<()> {
+pub async fn ensure_collection<C: BraindbClient + ?Sized>(
+    client: &C,
+    name: &str,
+    quantization: &QuantizationSettings,
+) -> Result<()> {
     let schema = serde_json::json!({
         "name": name,
         "fields": ["id", "text", "metadata", "embedding"],
+        "quantization": quantization,
     });
     client
         .create_collection(CreateCollectionRequest {
@@ -36,6 +42,7 @@ pub async fn hybrid_query<C: BraindbClient + ?Sized>(
     client: &C,
     query: QueryRequest,
     vector: Option<Vec<f32>>,
+    model: Option<&str>,
 ) -> Result<Vec<QueryResult>> {
     let strategy = if query.hybrid {
         QueryStrategy::Hybrid
@@ -51,18 +58,33 @@ pub async fn hybrid_query<C: BraindbClient + ?Sized>(
         top_k: query.top_k,
         strategy,
         filters: query.filters,
+        vector_field: query.vector_field,
+        embedding_model: model.map(str::to_string),
+        date_range: query.date_range,
     };
     let results = client.hybrid_query(request).await?;
     Ok(results)
 }
 
-pub fn build_records(documents: &[DocumentInput], embeddings: &[Vec<f32>]) -> Vec<DocumentRecord> {
+pub fn build_records(
+    documents: &[DocumentInput],
+    embeddings: &[Vec<f32>],
+    model: Option<&str>,
+) -> Vec<DocumentRecord> {
     documents
         .iter()
         .enumerate()
         .map(|(idx, doc)| {
             let embedding = embeddings.get(idx).cloned();
-            DocumentRecord::new(doc.clone(), embedding)
+            let mut record = DocumentRecord::new(doc.clone(), embedding);
+            if record
+                .embedding
+                .as_ref()
+                .is_some_and(|values| !values.is_empty())
+            {
+                record.embedding_model = model.map(str::to_string);
+            }
+            record
         })
         .collect()
 }
@@ -0,0 +1,242 @@
+use crate::core::config::TypoToleranceConfig;
+use crate::core::highlight::words;
+use crate::core::schema::TermCorrection;
+use parking_lot::RwLock;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Upper bound on query strings searched for one request.
+pub const MAX_VARIANTS: usize = 8;
+
+/// Indexed terms of a collection with their document frequency.
+#[derive(Debug, Clone, Default)]
+pub struct TermDictionary {
+    terms: BTreeMap<String, usize>,
+}
+
+impl TermDictionary {
+    pub fn from_texts<'a>(texts: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut dictionary = Self::default();
+        for text in texts {
+            dictionary.add(text);
+        }
+        dictionary
+    }
+
+    /// Counts every distinct term of `text` once.
+    pub fn add(&mut self, text: &str) {
+        let mut seen = normalized_terms(text);
+        seen.sort();
+        seen.dedup();
+        for term in seen {
+            *self.terms.entry(term).or_insert(0) += 1;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn contains(&self, term: &str) -> bool {
+        self.terms.contains_key(term)
+    }
+
+    /// Closest indexed term within `max_distance` edits. Ties go to the more
+    /// frequent term, then to the alphabetically first.
+    pub fn correct(&self, term: &str, max_distance: usize) -> Option<&str> {
+        let length = term.chars().count();
+        self.terms
+            .iter()
+            .filter(|(candidate, _)| candidate.chars().count().abs_diff(length) <= max_distance)
+            .filter_map(|(candidate, frequency)| {
+                let distance = edit_distance(term, candidate);
+                (distance <= max_distance).then_some((distance, *frequency, candidate))
+            })
+            .min_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)).then(a.2.cmp(b.2)))
+            .map(|(_, _, candidate)| candidate.as_str())
+    }
+}
+
+/// Damerau-Levenshtein distance restricted to adjacent transpositions.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// Term dictionaries per scoped collection, built on first use and dropped
+/// whenever the collection is written to.
+#[derive(Clone, Default)]
+pub struct TermDictionaries {
+    dictionaries: Arc<RwLock<HashMap<String, Arc<TermDictionary>>>>,
+    generation: Arc<AtomicU64>,
+}
+
+impl TermDictionaries {
+    pub fn get(&self, collection: &str) -> Option<Arc<TermDictionary>> {
+        self.dictionaries.read().get(collection).cloned()
+    }
+
+    /// Generation to pass to [`Self::insert`]; a write in between makes the
+    /// insert a no-op so a dictionary built from stale data is not cached.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    pub fn insert(&self, collection: &str, generation: u64, dictionary: Arc<TermDictionary>) {
+        let mut dictionaries = self.dictionaries.write();
+        if self.generation.load(Ordering::SeqCst) == generation {
+            dictionaries.insert(collection.to_string(), dictionary);
+        }
+    }
+
+    pub fn invalidate(&self, collection: &str) {
+        let mut dictionaries = self.dictionaries.write();
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        dictionaries.remove(collection);
+    }
+}
+
+/// Lower-cased words of `text` in order.
+pub fn normalized_terms(text: &str) -> Vec<String> {
+    words(text)
+        .into_iter()
+        .map(|range| text[range].to_lowercase())
+        .collect()
+}
+
+/// Replaces query words that are missing from the dictionary with their
+/// closest indexed term. Numbers and short words are left alone.
+pub fn correct_query(
+    query: &str,
+    dictionary: &TermDictionary,
+    config: &TypoToleranceConfig,
+) -> (String, Vec<TermCorrection>) {
+    let mut corrected = String::with_capacity(query.len());
+    let mut corrections = Vec::new();
+    let mut last = 0;
+    for range in words(query) {
+        let term = query[range.clone()].to_lowercase();
+        let length = term.chars().count();
+        if length < config.min_term_length
+            || term.chars().all(|ch| ch.is_numeric())
+            || dictionary.contains(&term)
+        {
+            continue;
+        }
+        let max_distance = if length < 8 { 1 } else { config.max_distance };
+        let Some(correction) = dictionary.correct(&term, max_distance) else {
+            continue;
+        };
+        corrected.push_str(&query[last..range.start]);
+        corrected.push_str(correction);
+        last = range.end;
+        corrections.push(TermCorrection {
+            term,
+            correction: correction.to_string(),
+        });
+    }
+    corrected.push_str(&query[last..]);
+    (corrected, corrections)
+}
+
+/// Query strings produced by swapping a synonym group entry found in
+/// `query` for each other entry of the group.
+pub fn synonym_variants(query: &str, groups: &[Vec<String>]) -> Vec<String> {
+    let normalized = format!(" {} ", normalized_terms(query).join(" "));
+    let mut variants = Vec::new();
+    for group in groups {
+        let entries: Vec<String> = group
+            .iter()
+            .map(|entry| normalized_terms(entry).join(" "))
+            .filter(|entry| !entry.is_empty())
+            .collect();
+        for entry in &entries {
+            let needle = format!(" {entry} ");
+            if !normalized.contains(&needle) {
+                continue;
+            }
+            for other in entries.iter().filter(|other| *other != entry) {
+                let variant = normalized.replacen(&needle, &format!(" {other} "), 1);
+                variants.push(variant.trim().to_string());
+            }
+        }
+    }
+    variants
+}
+
+/// System prompt for LLM query expansion; `{max}` is replaced with the
+/// number of phrasings wanted.
+pub const EXPANSION_PROMPT: &str = "You rewrite search queries. Suggest up to {max} alternative \
+search queries with the same intent as the user's query, using different wording. Reply with one \
+query per line and nothing else.";
+
+/// Parses a chat reply into phrasings: one per line, with list markers and
+/// quotes stripped.
+pub fn parse_expansions(reply: &str, max: usize) -> Vec<String> {
+    let mut expansions: Vec<String> = Vec::new();
+    for line in reply.lines() {
+        let mut line = line.trim().trim_start_matches(['-', '*', '•']).trim_start();
+        if let Some(idx) = line.find(['.', ')']) {
+            if idx > 0 && line[..idx].chars().all(|ch| ch.is_ascii_digit()) {
+                line = &line[idx + 1..];
+            }
+        }
+        let line = line.trim_matches(|ch: char| ch == '"' || ch == '\'' || ch.is_whitespace());
+        if line.is_empty()
+            || expansions
+                .iter()
+                .any(|seen| seen.eq_ignore_ascii_case(line))
+        {
+            continue;
+        }
+        expansions.push(line.to_string());
+        if expansions.len() == max {
+            break;
+        }
+    }
+    expansions
+}
+
+/// Primary query first, then the remaining variants without duplicates,
+/// capped at [`MAX_VARIANTS`].
+pub fn merge_variants(primary: &str, variants: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut merged = vec![primary.to_string()];
+    let mut seen = vec![normalized_terms(primary).join(" ")];
+    for variant in variants {
+        let key = normalized_terms(&variant).join(" ");
+        if key.is_empty() || seen.contains(&key) {
+            continue;
+        }
+        seen.push(key);
+        merged.push(variant);
+        if merged.len() == MAX_VARIANTS {
+            break;
+        }
+    }
+    merged
+}
@@ -1,9 +1,12 @@
+use crate::core::ingest::FileFormat;
+use crate::core::quantization::Quantization;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use std::collections::BTreeMap;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
 pub struct DocumentInput {
     pub id: Option<String>,
     pub text: String,
@@ -11,18 +14,31 @@ pub struct DocumentInput {
     pub metadata: serde_json::Value,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
 pub struct DocumentRecord {
     pub id: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub text: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
     pub metadata: serde_json::Value,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub embedding: Option<Vec<f32>>,
+    /// Model that produced `embedding`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding_model: Option<String>,
+    /// Additional embeddings keyed by vector field name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub vectors: BTreeMap<String, NamedVector>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct NamedVector {
+    pub model: String,
+    pub values: Vec<f32>,
+}
+
 impl DocumentRecord {
     pub fn new(input: DocumentInput, embedding: Option<Vec<f32>>) -> Self {
         let now = Utc::now();
@@ -31,13 +47,15 @@ impl DocumentRecord {
             text: input.text,
             metadata: input.metadata,
             embedding,
+            embedding_model: None,
+            vectors: BTreeMap::new(),
             created_at: now,
             updated_at: now,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct IndexRequest {
     pub collection: String,
@@ -47,6 +65,9 @@ pub struct IndexRequest {
     pub embed: bool,
     #[serde(default)]
     pub fts: bool,
+    /// Named vector fields to embed; empty embeds every configured field.
+    #[serde(default)]
+    pub vector_fields: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -63,6 +84,310 @@ pub struct QueryRequest {
     pub hybrid: bool,
     #[serde(default)]
     pub filters: Vec<QueryFilter>,
+    /// Named vector field to search; `None` searches the default embedding.
+    #[serde(default)]
+    pub vector_field: Option<String>,
+    /// Document fields returned with each result. `id` and timestamps are
+    /// always included.
+    #[serde(default = "default_fields")]
+    pub fields: Vec<DocumentField>,
+    #[serde(default)]
+    pub highlight: HighlightOptions,
+    /// Number of results to skip. Cannot be combined with `cursor`.
+    #[serde(default)]
+    pub offset: usize,
+    /// Opaque `nextCursor` from a previous response.
+    #[serde(default)]
+    pub cursor: Option<String>,
+    /// Reorders results by maximal marginal relevance and collapses
+    /// near-duplicates.
+    #[serde(default)]
+    pub diversify: Option<DiversifyOptions>,
+    /// Adds a `debug` section describing how the query was rewritten.
+    #[serde(default)]
+    pub debug: bool,
+    /// Keeps only results whose timestamp falls inside the range.
+    #[serde(default)]
+    pub date_range: Option<DateRange>,
+    /// Boosts recent documents by decaying scores with age.
+    #[serde(default)]
+    pub recency: Option<RecencyBoost>,
+    /// Facets computed over the filtered candidates, keyed by a name chosen
+    /// by the client.
+    #[serde(default)]
+    pub aggregations: BTreeMap<String, Aggregation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiversifyOptions {
+    /// Trade-off between relevance (`1.0`) and diversity (`0.0`).
+    #[serde(default = "default_mmr_lambda")]
+    pub lambda: f32,
+    /// Drops results at least this similar to a higher-ranked result.
+    #[serde(default)]
+    pub collapse_threshold: Option<f32>,
+    /// Keeps only the best result per value of this metadata key.
+    #[serde(default)]
+    pub collapse_key: Option<String>,
+    /// Candidates retrieved before diversifying. Kept fixed across pages so
+    /// every page is cut from the same ordering.
+    #[serde(default = "default_mmr_candidates")]
+    pub candidates: usize,
+}
+
+impl Default for DiversifyOptions {
+    fn default() -> Self {
+        Self {
+            lambda: default_mmr_lambda(),
+            collapse_threshold: None,
+            collapse_key: None,
+            candidates: default_mmr_candidates(),
+        }
+    }
+}
+
+/// Document timestamp used by date filters, recency boosts and TTLs.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DateField {
+    CreatedAt,
+    #[default]
+    UpdatedAt,
+}
+
+impl DateField {
+    pub fn of(self, document: &DocumentRecord) -> DateTime<Utc> {
+        match self {
+            DateField::CreatedAt => document.created_at,
+            DateField::UpdatedAt => document.updated_at,
+        }
+    }
+}
+
+/// Range over a document timestamp; `from` is inclusive, `to` exclusive.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DateRange {
+    #[serde(default)]
+    pub field: DateField,
+    #[serde(default)]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl DateRange {
+    pub fn contains(&self, document: &DocumentRecord) -> bool {
+        let at = self.field.of(document);
+        self.from.is_none_or(|from| at >= from) && self.to.is_none_or(|to| at < to)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DecayFunction {
+    #[default]
+    Exp,
+    Linear,
+    Gauss,
+}
+
+/// Multiplies scores by a decay factor that is `1.0` for documents younger
+/// than `offsetSecs` and `decay` at `offsetSecs + scaleSecs`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RecencyBoost {
+    #[serde(default)]
+    pub function: DecayFunction,
+    #[serde(default)]
+    pub field: DateField,
+    pub scale_secs: u64,
+    #[serde(default)]
+    pub offset_secs: u64,
+    #[serde(default = "default_recency_decay")]
+    pub decay: f32,
+    /// Share of the score subject to decay; `0.0` turns the boost off.
+    #[serde(default = "default_recency_weight")]
+    pub weight: f32,
+}
+
+fn default_recency_decay() -> f32 {
+    0.5
+}
+
+fn default_recency_weight() -> f32 {
+    1.0
+}
+
+/// Facet over a metadata field. `field` is a metadata key; dots reach into
+/// nested objects. Array values count once per element.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Aggregation {
+    /// Most frequent values, by descending document count.
+    Terms {
+        field: String,
+        #[serde(default = "default_terms_size")]
+        size: usize,
+    },
+    /// Numeric values grouped into buckets `interval` wide.
+    Histogram {
+        field: String,
+        interval: f64,
+    },
+    /// RFC 3339 timestamps grouped by calendar interval. `created_at` and
+    /// `updated_at` use the document timestamps.
+    DateHistogram {
+        field: String,
+        interval: CalendarInterval,
+    },
+    Min {
+        field: String,
+    },
+    Max {
+        field: String,
+    },
+    Avg {
+        field: String,
+    },
+}
+
+fn default_terms_size() -> usize {
+    10
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CalendarInterval {
+    Minute,
+    Hour,
+    Day,
+    /// ISO weeks, starting on Monday.
+    Week,
+    Month,
+    Year,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregationResult {
+    /// Candidates that have a value for the field.
+    pub doc_count: usize,
+    /// Buckets of `terms`, `histogram` and `date_histogram` aggregations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buckets: Option<Vec<AggregationBucket>>,
+    /// Result of `min`, `max` and `avg`; absent when no candidate has a
+    /// numeric value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregationBucket {
+    /// Term value, lower bound of a histogram bucket, or RFC 3339 start of a
+    /// date bucket.
+    pub key: serde_json::Value,
+    pub doc_count: usize,
+}
+
+fn default_mmr_lambda() -> f32 {
+    0.5
+}
+
+fn default_mmr_candidates() -> usize {
+    100
+}
+
+fn default_fields() -> Vec<DocumentField> {
+    vec![DocumentField::Metadata]
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentField {
+    Text,
+    Metadata,
+    /// The default embedding and every named vector.
+    Embedding,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HighlightOptions {
+    /// Off by default: snippets of vector hits cost an extra embedding call
+    /// per query.
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_pre_tag")]
+    pub pre_tag: String,
+    #[serde(default = "default_post_tag")]
+    pub post_tag: String,
+    /// Approximate snippet length in characters.
+    #[serde(default = "default_fragment_size")]
+    pub fragment_size: usize,
+    #[serde(default = "default_max_fragments")]
+    pub max_fragments: usize,
+    /// Sentences per window when picking the best semantic match.
+    #[serde(default = "default_sentence_window")]
+    pub sentence_window: usize,
+}
+
+impl Default for HighlightOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            pre_tag: default_pre_tag(),
+            post_tag: default_post_tag(),
+            fragment_size: default_fragment_size(),
+            max_fragments: default_max_fragments(),
+            sentence_window: default_sentence_window(),
+        }
+    }
+}
+
+fn default_pre_tag() -> String {
+    "<em>".to_string()
+}
+
+fn default_post_tag() -> String {
+    "</em>".to_string()
+}
+
+fn default_fragment_size() -> usize {
+    160
+}
+
+fn default_max_fragments() -> usize {
+    3
+}
+
+fn default_sentence_window() -> usize {
+    1
+}
+
+impl Default for QueryRequest {
+    fn default() -> Self {
+        Self {
+            collection: String::new(),
+            query: None,
+            vector: None,
+            top_k: default_top_k(),
+            hybrid: false,
+            filters: Vec::new(),
+            vector_field: None,
+            fields: default_fields(),
+            highlight: HighlightOptions::default(),
+            offset: 0,
+            cursor: None,
+            diversify: None,
+            debug: false,
+            date_range: None,
+            recency: None,
+            aggregations: BTreeMap::new(),
+        }
+    }
 }
 
 fn default_top_k() -> usize {
@@ -103,13 +428,224 @@ pub struct QueryResult {
     pub score: f32,
     pub rank: usize,
     pub document: DocumentRecord,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub snippets: Vec<Snippet>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Snippet {
+    pub text: String,
+    pub kind: SnippetKind,
+    /// Similarity to the query vector for semantic snippets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SnippetKind {
+    /// Fragment around matched query terms.
+    Term,
+    /// Sentence window closest to the query vector.
+    Semantic,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct QueryResponse {
     pub results: Vec<QueryResult>,
     pub latency_ms: u64,
+    #[serde(default)]
+    pub total_hits: TotalHits,
+    /// Cursor for the next page; absent on the last page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    /// Identifies the logged query when analytics are enabled; pass it back
+    /// with feedback on the results.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_id: Option<String>,
+    /// Present when the request set `debug`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debug: Option<QueryDebug>,
+    /// Results of the requested aggregations, by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aggregations: BTreeMap<String, AggregationResult>,
+    /// Set when more candidates matched than the result window holds, so
+    /// the aggregations cover only the best-scoring ones.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub aggregations_truncated: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryDebug {
+    /// Query as sent by the client.
+    pub query: Option<String>,
+    /// Query after spelling correction; used for embedding and highlights.
+    pub rewritten_query: Option<String>,
+    pub corrections: Vec<TermCorrection>,
+    /// Every query string searched, the rewritten query first.
+    pub variants: Vec<String>,
+    /// Phrasings suggested by the chat model.
+    pub expansions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TermCorrection {
+    pub term: String,
+    pub correction: String,
+}
+
+/// Number of matching documents. `gte` means the count is a lower bound
+/// because matches beyond the retrieval window were not counted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct TotalHits {
+    pub value: usize,
+    pub relation: TotalHitsRelation,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TotalHitsRelation {
+    #[default]
+    Eq,
+    Gte,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestRequest {
+    pub collection: String,
+    #[serde(default)]
+    pub files: Vec<IngestFile>,
+    /// Merged into the metadata extracted from each file.
+    #[serde(default)]
+    pub metadata: serde_json::Value,
+    #[serde(default)]
+    pub embed: bool,
+    #[serde(default)]
+    pub fts: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestFile {
+    pub path: String,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// Base64-encoded file contents.
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestResponse {
+    pub collection: String,
+    pub files: Vec<IngestedFile>,
+    pub documents: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestedFile {
+    pub path: String,
+    pub format: FileFormat,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub language: Option<String>,
+    pub chunks: usize,
+}
+
+/// Target of a bulk ingest: the query parameters of
+/// `POST /api/v1/brainml/bulk` and the `open` step on `brainml.bulk`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct BulkOptions {
+    pub collection: String,
+    #[serde(default)]
+    pub embed: bool,
+    #[serde(default)]
+    pub fts: bool,
+}
+
+/// One step of a chunked bulk ingest over `brainml.bulk`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BulkRequest {
+    /// Starts a stream; the response carries its `streamId`.
+    Open(BulkOptions),
+    /// Indexes the next chunk. Chunks are numbered from 0 and must arrive
+    /// in order; a failed chunk may be sent again with the same `seq`.
+    Chunk(BulkChunk),
+    /// Ends the stream and returns its summary.
+    Close(BulkStreamRef),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkChunk {
+    pub stream_id: Uuid,
+    pub seq: u64,
+    pub documents: Vec<DocumentInput>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkStreamRef {
+    pub stream_id: Uuid,
+}
+
+/// Progress report of a bulk ingest: an NDJSON line of the HTTP response,
+/// or the response to a bus step.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BulkEvent {
+    Progress(BulkProgress),
+    Summary(BulkSummary),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkProgress {
+    /// Present on bus streams.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_id: Option<Uuid>,
+    /// Documents read so far, including rejected ones.
+    pub received: u64,
+    pub indexed: u64,
+    pub failed: u64,
+    pub batches: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkSummary {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_id: Option<Uuid>,
+    pub collection: String,
+    pub received: u64,
+    pub indexed: u64,
+    pub failed: u64,
+    pub batches: u64,
+    /// Rejected lines; only the first 100 are listed.
+    pub errors: Vec<BulkLineError>,
+    /// Why the stream stopped early. Input after the failed batch was not
+    /// read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkLineError {
+    /// 1-based NDJSON line number.
+    pub line: u64,
+    pub message: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -129,6 +665,8 @@ pub struct TrainResponse {
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct StatsResponse {
     pub collections: Vec<CollectionStats>,
+    #[serde(default)]
+    pub tenants: Vec<TenantStats>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -136,6 +674,23 @@ pub struct CollectionStats {
     pub name: String,
     pub document_count: usize,
     pub embedding_dimensions: Option<usize>,
+    #[serde(default)]
+    pub quantization: Quantization,
+    /// Bytes held by default embeddings: codes plus any kept originals.
+    #[serde(default)]
+    pub vector_bytes: u64,
+    /// Estimated bytes held by the collection's documents and vectors.
+    #[serde(default)]
+    pub memory_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TenantStats {
+    pub tenant: String,
+    pub collection_count: usize,
+    pub document_count: usize,
+    pub storage_bytes: u64,
+    pub queries_per_second: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -144,12 +699,313 @@ pub struct HealthResponse {
     pub details: Option<serde_json::Value>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminRequest {
+    #[serde(default)]
+    pub action: AdminAction,
+    /// Batch size for `migrateEmbeddings`; falls back to the configured size.
+    #[serde(default)]
+    pub batch_size: Option<usize>,
+    /// Changes applied together by `updateAliases`.
+    #[serde(default)]
+    pub aliases: Vec<AliasUpdate>,
+}
+
+/// Points `alias` at `collection`, or removes it when `collection` is
+/// absent.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AliasUpdate {
+    pub alias: String,
+    #[serde(default)]
+    pub collection: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionAlias {
+    pub alias: String,
+    pub collection: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum AdminAction {
+    #[default]
+    Status,
+    MigrateEmbeddings,
+    ReloadConfig,
+    /// Runs a directory sync pass now instead of waiting for the interval.
+    SyncDirectories,
+    /// Applies `aliases` atomically, e.g. to swap an alias to a rebuilt
+    /// collection.
+    UpdateAliases,
+    /// Runs an expiry sweep now instead of waiting for the interval.
+    ExpireDocuments,
+}
+
+impl AdminAction {
+    /// Actions that reach every tenant's collections or the shared
+    /// configuration.
+    pub fn is_operator_only(self) -> bool {
+        matches!(
+            self,
+            AdminAction::MigrateEmbeddings
+                | AdminAction::ReloadConfig
+                | AdminAction::SyncDirectories
+                | AdminAction::ExpireDocuments
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationState {
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationProgress {
+    pub collection: String,
+    pub state: MigrationState,
+    pub target_model: Option<String>,
+    pub scanned: usize,
+    pub migrated: usize,
+    #[serde(default)]
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AdminStatus {
     pub version: String,
     pub uptime_seconds: u64,
     pub capabilities: Vec<String>,
+    #[serde(default)]
+    pub migration_running: bool,
+    #[serde(default)]
+    pub migrations: Vec<MigrationProgress>,
+    #[serde(default)]
+    pub config_generation: u64,
+    /// Active configuration with secrets redacted; `null` for tenants
+    /// other than the default one.
+    #[serde(default)]
+    pub active_config: serde_json::Value,
+    #[serde(default)]
+    pub sync_running: bool,
+    /// Outcome of the last sync pass per watched directory.
+    #[serde(default)]
+    pub sync: Vec<SyncReport>,
+    #[serde(default)]
+    pub query_cache: CacheStats,
+    /// Limiter state; reported to the default tenant only.
+    #[serde(default)]
+    pub limits: Option<LimitStats>,
+    /// Aliases visible to the caller.
+    #[serde(default)]
+    pub aliases: Vec<CollectionAlias>,
+    #[serde(default)]
+    pub expiry_running: bool,
+    /// Outcome of the last expiry sweep; reported to the default tenant
+    /// only.
+    #[serde(default)]
+    pub expiry: Option<ExpiryReport>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    pub entries: usize,
+    pub hits: u64,
+    /// Hits served through embedding similarity rather than an exact match.
+    pub semantic_hits: u64,
+    pub misses: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LimitStats {
+    /// Requests currently being processed per capability.
+    pub in_flight: BTreeMap<String, usize>,
+    /// Requests queued for a free slot.
+    pub waiting: usize,
+    /// Requests rejected by a rate limit since start-up.
+    pub rate_limited: u64,
+    /// Requests rejected after waiting `queue_timeout_ms` for a slot.
+    pub overloaded: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    pub collection: String,
+    pub root: String,
+    #[serde(default)]
+    pub tenant: Option<String>,
+    pub scanned: usize,
+    pub unchanged: usize,
+    pub upserted: usize,
+    pub deleted: usize,
+    /// Files that could not be extracted; they are retried once they change.
+    pub failed: usize,
+    #[serde(default)]
+    pub error: Option<String>,
+    pub finished_at: DateTime<Utc>,
+}
+
+/// Outcome of an expiry sweep over every collection with a TTL.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpiryReport {
+    /// Physical collections that had a TTL.
+    pub collections: usize,
+    pub scanned: usize,
+    pub expired: usize,
+    #[serde(default)]
+    pub error: Option<String>,
+    pub finished_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedbackRequest {
+    /// `queryId` from the query response the feedback is about.
+    pub query_id: String,
+    pub collection: String,
+    pub document_id: String,
+    pub relevant: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedbackResponse {
+    pub recorded: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct AnalyticsReportRequest {
+    /// Restricts the report to one collection.
+    #[serde(default)]
+    pub collection: Option<String>,
+    /// Ignores queries logged before this instant.
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalyticsReport {
+    pub collections: Vec<CollectionAnalytics>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionAnalytics {
+    pub collection: String,
+    pub queries: usize,
+    pub zero_result_queries: usize,
+    pub zero_result_rate: f64,
+    /// Queries with at least one result marked relevant.
+    pub clicked_queries: usize,
+    pub click_through_rate: f64,
+    pub relevant_feedback: usize,
+    pub irrelevant_feedback: usize,
+    pub mean_latency_ms: f64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Success,
+    Failure,
+}
+
+/// The hashed part of an audit log entry.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditRecord {
+    /// Position in the log, starting at 1.
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    pub tenant: String,
+    /// Fingerprint of the caller's token; the token itself is never logged.
+    pub caller: Option<String>,
+    pub capability: String,
+    /// Admin action or trained pipeline.
+    pub action: Option<String>,
+    /// Collection as named by the caller.
+    pub collection: Option<String>,
+    pub documents: Option<u64>,
+    pub outcome: AuditOutcome,
+    pub error: Option<String>,
+    /// Hash of the previous entry, all zeros for the first.
+    pub prev_hash: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    #[serde(flatten)]
+    pub record: AuditRecord,
+    /// Hex SHA-256 of the record as serialized without this field.
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct AuditQueryRequest {
+    /// Only honoured for the default tenant; other tenants always see
+    /// their own entries.
+    #[serde(default)]
+    pub tenant: Option<String>,
+    #[serde(default)]
+    pub capability: Option<String>,
+    #[serde(default)]
+    pub collection: Option<String>,
+    #[serde(default)]
+    pub outcome: Option<AuditOutcome>,
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+    /// Newest entries returned; defaults to 100.
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditChainStatus {
+    pub valid: bool,
+    /// Entries read from the log.
+    pub entries: u64,
+    /// Hash of the last entry. The log's head file catches removal of the
+    /// newest entries; a copy kept elsewhere also catches a rewritten head
+    /// file.
+    pub head_hash: Option<String>,
+    /// Line of the first entry that does not verify.
+    pub broken_at: Option<u64>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditQueryResponse {
+    /// Matching entries, newest first.
+    pub entries: Vec<AuditEntry>,
+    /// Matching entries before `limit` was applied.
+    pub total: usize,
+    /// Verification of the whole log; reported to the default tenant only.
+    pub chain: Option<AuditChainStatus>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
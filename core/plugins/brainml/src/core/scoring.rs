@@ -1,4 +1,12 @@
-use crate::core::schema::QueryResult;
+use crate::core::schema::{DecayFunction, QueryResult, RecencyBoost};
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ScoringError {
+    #[error("invalid recency boost: {0}")]
+    InvalidRecency(String),
+}
 
 pub fn normalize_scores(results: &mut [QueryResult]) {
     if results.is_empty() {
@@ -15,3 +23,55 @@ pub fn normalize_scores(results: &mut [QueryResult]) {
         result.score /= max_score;
     }
 }
+
+/// Decay factor for a document `age_secs` old: `1.0` within the offset,
+/// `decay` at `offset + scale`, falling off as the chosen function.
+pub fn decay_factor(boost: &RecencyBoost, age_secs: f64) -> f64 {
+    let distance = (age_secs.abs() - boost.offset_secs as f64).max(0.0);
+    let scale = boost.scale_secs as f64;
+    let decay = boost.decay as f64;
+    match boost.function {
+        DecayFunction::Exp => (decay.ln() / scale * distance).exp(),
+        DecayFunction::Linear => {
+            let zero_at = scale / (1.0 - decay);
+            ((zero_at - distance) / zero_at).max(0.0)
+        }
+        DecayFunction::Gauss => {
+            let variance = -scale * scale / (2.0 * decay.ln());
+            (-distance * distance / (2.0 * variance)).exp()
+        }
+    }
+}
+
+/// Scales each score by the age of its document at `now`. Only `weight` of
+/// the score decays, so older documents are demoted rather than dropped.
+pub fn apply_recency(
+    results: &mut [QueryResult],
+    boost: &RecencyBoost,
+    now: DateTime<Utc>,
+) -> Result<(), ScoringError> {
+    if boost.scale_secs == 0 {
+        return Err(ScoringError::InvalidRecency(
+            "scaleSecs must be positive".into(),
+        ));
+    }
+    if !(boost.decay > 0.0 && boost.decay < 1.0) {
+        return Err(ScoringError::InvalidRecency(format!(
+            "decay must be between 0 and 1 exclusive, got {}",
+            boost.decay
+        )));
+    }
+    if !(0.0..=1.0).contains(&boost.weight) {
+        return Err(ScoringError::InvalidRecency(format!(
+            "weight must be between 0 and 1, got {}",
+            boost.weight
+        )));
+    }
+    let weight = boost.weight as f64;
+    for result in results.iter_mut() {
+        let age = now - boost.field.of(&result.document);
+        let factor = decay_factor(boost, age.num_milliseconds() as f64 / 1000.0);
+        result.score *= (1.0 - weight + weight * factor) as f32;
+    }
+    Ok(())
+}
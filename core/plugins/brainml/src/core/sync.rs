@@ -0,0 +1,215 @@
+use crate::core::config::SyncSource;
+use crate::core::schema::SyncReport;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Content hash and chunk count of every file indexed from a source, keyed
+/// by the path relative to the source root.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SyncManifest {
+    pub root: PathBuf,
+    pub collection: String,
+    #[serde(default)]
+    pub tenant: Option<String>,
+    #[serde(default)]
+    pub files: BTreeMap<String, ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ManifestEntry {
+    pub hash: String,
+    pub chunks: usize,
+}
+
+impl SyncManifest {
+    pub fn empty(source: &SyncSource) -> Self {
+        Self {
+            root: source.path.clone(),
+            collection: source.collection.clone(),
+            tenant: source.tenant.clone(),
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Loads the manifest for `source`. A missing manifest, or one written
+    /// for a different root, collection or tenant, starts from scratch.
+    pub fn load(path: &Path, source: &SyncSource) -> io::Result<Self> {
+        let contents = match std::fs::read(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::empty(source)),
+            Err(err) => return Err(err),
+        };
+        let manifest: Self = serde_json::from_slice(&contents)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        if manifest.root != source.path
+            || manifest.collection != source.collection
+            || manifest.tenant != source.tenant
+        {
+            return Ok(Self::empty(source));
+        }
+        Ok(manifest)
+    }
+
+    /// Writes the manifest through a temporary file so a crash never leaves
+    /// a truncated manifest behind.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        let contents = serde_json::to_vec_pretty(self)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        std::fs::write(&tmp, contents)?;
+        std::fs::rename(&tmp, path)
+    }
+}
+
+/// Manifest file for a source: the collection name plus a digest of the
+/// root and tenant, so two sources never share a manifest.
+pub fn manifest_path(dir: &Path, source: &SyncSource) -> PathBuf {
+    let mut hasher = Sha256::new();
+    hasher.update(source.path.to_string_lossy().as_bytes());
+    hasher.update(source.tenant.as_deref().unwrap_or_default().as_bytes());
+    let digest = format!("{:x}", hasher.finalize());
+    let name: String = source
+        .collection
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || ch == '-' {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    dir.join(format!("{name}-{}.json", &digest[..12]))
+}
+
+#[derive(Debug, Clone)]
+pub struct ScannedFile {
+    /// Path relative to the source root with `/` separators.
+    pub path: String,
+    pub absolute: PathBuf,
+    pub hash: String,
+}
+
+/// Walks the source directory and hashes every included file. Hidden
+/// entries and names listed in `ignore` are skipped.
+pub fn scan(source: &SyncSource) -> io::Result<Vec<ScannedFile>> {
+    let mut files = Vec::new();
+    let mut pending = vec![source.path.clone()];
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') || source.ignore.contains(&name) {
+                continue;
+            }
+            let file_type = entry.file_type()?;
+            let absolute = entry.path();
+            if file_type.is_dir() {
+                pending.push(absolute);
+                continue;
+            }
+            if !file_type.is_file() || !included(source, &absolute) {
+                continue;
+            }
+            let bytes = std::fs::read(&absolute)?;
+            let relative = absolute
+                .strip_prefix(&source.path)
+                .unwrap_or(&absolute)
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push(ScannedFile {
+                path: relative,
+                absolute,
+                hash: format!("{:x}", Sha256::digest(&bytes)),
+            });
+        }
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+fn included(source: &SyncSource, path: &Path) -> bool {
+    if source.extensions.is_empty() {
+        return true;
+    }
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default();
+    source.extensions.iter().any(|wanted| {
+        wanted
+            .trim_start_matches('.')
+            .eq_ignore_ascii_case(extension)
+    })
+}
+
+#[derive(Debug, Default)]
+pub struct SyncPlan {
+    /// New files and files whose content hash changed.
+    pub changed: Vec<ScannedFile>,
+    /// Files in the manifest that no longer exist, with their chunk count.
+    pub removed: Vec<(String, usize)>,
+    pub unchanged: usize,
+}
+
+pub fn plan(manifest: &SyncManifest, scanned: Vec<ScannedFile>) -> SyncPlan {
+    let mut plan = SyncPlan::default();
+    let present: std::collections::HashSet<&str> =
+        scanned.iter().map(|file| file.path.as_str()).collect();
+    plan.removed = manifest
+        .files
+        .iter()
+        .filter(|(path, _)| !present.contains(path.as_str()))
+        .map(|(path, entry)| (path.clone(), entry.chunks))
+        .collect();
+    for file in scanned {
+        match manifest.files.get(&file.path) {
+            Some(entry) if entry.hash == file.hash => plan.unchanged += 1,
+            _ => plan.changed.push(file),
+        }
+    }
+    plan
+}
+
+/// Tracks the directory sync loop and the last report per source.
+#[derive(Clone, Default)]
+pub struct DirectorySync {
+    reports: Arc<RwLock<BTreeMap<String, SyncReport>>>,
+    running: Arc<AtomicBool>,
+}
+
+impl DirectorySync {
+    /// Marks a sync pass as running. Returns `false` if one already is.
+    pub fn begin(&self) -> bool {
+        !self.running.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn finish(&self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    pub fn record(&self, report: SyncReport) {
+        self.reports
+            .write()
+            .insert(format!("{}:{}", report.collection, report.root), report);
+    }
+
+    pub fn reports(&self) -> Vec<SyncReport> {
+        self.reports.read().values().cloned().collect()
+    }
+}
@@ -0,0 +1,246 @@
+use crate::core::config::{TenancyConfig, TenantQuota};
+use crate::core::schema::{CollectionStats, TenantStats};
+use parking_lot::RwLock;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Separator between the tenant namespace and the collection name in the
+/// physical collection name handed to braindb.
+pub const NAMESPACE_SEPARATOR: &str = "::";
+
+#[derive(Debug, Error)]
+pub enum TenantError {
+    #[error("invalid collection name: {0}")]
+    InvalidCollection(String),
+    #[error("quota exceeded for tenant {tenant}: {reason}")]
+    QuotaExceeded { tenant: String, reason: String },
+    #[error("unauthorized: {0}")]
+    Unauthorized(String),
+    #[error("forbidden: {0}")]
+    Forbidden(String),
+    #[error("invalid tenant id: {0:?}")]
+    InvalidTenant(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct TenantContext {
+    pub id: String,
+    namespaced: bool,
+    /// Fingerprint of the token the caller presented, see
+    /// [`token_fingerprint`].
+    caller: Option<String>,
+}
+
+/// Two contexts are the same tenant whichever token they came with.
+impl PartialEq for TenantContext {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id && self.namespaced == other.namespaced
+    }
+}
+
+impl Eq for TenantContext {}
+
+impl TenantContext {
+    /// Resolves the tenant of a request from its bus/bearer token. With
+    /// tenancy enabled the token must be listed in `tenancy.tokens`; an
+    /// explicit tenant id (e.g. an HTTP header) is only accepted when it
+    /// names the token's tenant, so it can never switch tenants.
+    pub fn resolve(
+        config: &TenancyConfig,
+        explicit: Option<&str>,
+        token: Option<&str>,
+    ) -> Result<Self, TenantError> {
+        let caller = token.map(token_fingerprint);
+        if !config.enabled {
+            return Ok(Self {
+                caller,
+                ..Self::default_tenant(config)
+            });
+        }
+        let token = token
+            .ok_or_else(|| TenantError::Unauthorized("a tenant token is required".to_string()))?;
+        let id = config
+            .tokens
+            .get(token)
+            .ok_or_else(|| TenantError::Unauthorized("unknown tenant token".to_string()))?;
+        let explicit = explicit.map(str::trim).filter(|value| !value.is_empty());
+        if explicit.is_some_and(|explicit| explicit != id) {
+            return Err(TenantError::Unauthorized(
+                "tenant does not match the token".to_string(),
+            ));
+        }
+        Ok(Self {
+            caller,
+            ..Self::named(config, id)?
+        })
+    }
+
+    /// A tenant named by trusted configuration, such as a sync source.
+    pub fn named(config: &TenancyConfig, id: &str) -> Result<Self, TenantError> {
+        if !config.enabled {
+            return Ok(Self::default_tenant(config));
+        }
+        if id.trim().is_empty() || id.contains(NAMESPACE_SEPARATOR) {
+            return Err(TenantError::InvalidTenant(id.to_string()));
+        }
+        Ok(Self {
+            id: id.to_string(),
+            namespaced: id != config.default_tenant,
+            caller: None,
+        })
+    }
+
+    pub fn is_default(&self) -> bool {
+        !self.namespaced
+    }
+
+    pub fn default_tenant(config: &TenancyConfig) -> Self {
+        Self {
+            id: config.default_tenant.clone(),
+            namespaced: false,
+            caller: None,
+        }
+    }
+
+    /// Fingerprint of the caller's token, if one was presented.
+    pub fn caller(&self) -> Option<&str> {
+        self.caller.as_deref()
+    }
+
+    /// Maps a tenant-visible collection name onto the physical collection.
+    /// The default tenant keeps un-prefixed names so single-tenant
+    /// deployments see no change.
+    pub fn scope(&self, collection: &str) -> Result<String, TenantError> {
+        if collection.contains(NAMESPACE_SEPARATOR) {
+            return Err(TenantError::InvalidCollection(collection.to_string()));
+        }
+        if self.namespaced {
+            Ok(format!("{}{NAMESPACE_SEPARATOR}{collection}", self.id))
+        } else {
+            Ok(collection.to_string())
+        }
+    }
+
+    /// Returns the tenant-visible name if the physical collection belongs to
+    /// this tenant.
+    pub fn unscope<'a>(&self, physical: &'a str) -> Option<&'a str> {
+        match split_namespace(physical) {
+            Some((tenant, name)) if self.namespaced && tenant == self.id => Some(name),
+            None if !self.namespaced => Some(physical),
+            _ => None,
+        }
+    }
+}
+
+/// Identifies a token in logs without revealing it: the first 16 hex
+/// digits of its SHA-256.
+pub fn token_fingerprint(token: &str) -> String {
+    let digest = Sha256::digest(token.as_bytes());
+    digest[..8]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Splits a physical collection name into its tenant namespace and the
+/// tenant-visible collection name. Un-prefixed names belong to the default
+/// tenant and yield `None`.
+pub fn split_namespace(physical: &str) -> Option<(&str, &str)> {
+    physical.split_once(NAMESPACE_SEPARATOR)
+}
+
+#[derive(Debug, Default)]
+struct TenantUsage {
+    window_start: Option<Instant>,
+    window_queries: u32,
+    last_qps: u32,
+}
+
+#[derive(Clone, Default)]
+pub struct TenantRegistry {
+    usage: Arc<RwLock<HashMap<String, TenantUsage>>>,
+}
+
+impl TenantRegistry {
+    /// Counts a query against the tenant's QPS quota using a one second
+    /// fixed window.
+    pub fn check_query(
+        &self,
+        tenant: &TenantContext,
+        quota: &TenantQuota,
+    ) -> Result<(), TenantError> {
+        let mut usage = self.usage.write();
+        let entry = usage.entry(tenant.id.clone()).or_default();
+        let now = Instant::now();
+        match entry.window_start {
+            Some(start) if now.duration_since(start) < Duration::from_secs(1) => {}
+            _ => {
+                entry.last_qps = entry.window_queries;
+                entry.window_start = Some(now);
+                entry.window_queries = 0;
+            }
+        }
+        if let Some(max_qps) = quota.max_qps {
+            if entry.window_queries >= max_qps {
+                return Err(TenantError::QuotaExceeded {
+                    tenant: tenant.id.clone(),
+                    reason: format!("more than {max_qps} queries per second"),
+                });
+            }
+        }
+        entry.window_queries += 1;
+        Ok(())
+    }
+
+    /// Checks document and storage quotas before an index request is applied.
+    /// Usage is taken from the backend's stats of the tenant's collections,
+    /// so it follows deletes and expiry and survives restarts.
+    pub fn check_index(
+        &self,
+        tenant: &TenantContext,
+        quota: &TenantQuota,
+        collections: &[CollectionStats],
+        incoming_documents: usize,
+        incoming_bytes: u64,
+    ) -> Result<(), TenantError> {
+        if let Some(max_documents) = quota.max_documents {
+            let current: usize = collections.iter().map(|stats| stats.document_count).sum();
+            if current + incoming_documents > max_documents {
+                return Err(TenantError::QuotaExceeded {
+                    tenant: tenant.id.clone(),
+                    reason: format!("document limit of {max_documents} reached"),
+                });
+            }
+        }
+        if let Some(max_storage) = quota.max_storage_bytes {
+            if storage_bytes(collections) + incoming_bytes > max_storage {
+                return Err(TenantError::QuotaExceeded {
+                    tenant: tenant.id.clone(),
+                    reason: format!("storage limit of {max_storage} bytes reached"),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn stats(&self, tenant: &str, collections: &[CollectionStats]) -> TenantStats {
+        let usage = self.usage.read();
+        let entry = usage.get(tenant);
+        TenantStats {
+            tenant: tenant.to_string(),
+            collection_count: collections.len(),
+            document_count: collections.iter().map(|stats| stats.document_count).sum(),
+            storage_bytes: storage_bytes(collections),
+            queries_per_second: entry
+                .map(|usage| usage.last_qps.max(usage.window_queries))
+                .unwrap_or(0),
+        }
+    }
+}
+
+fn storage_bytes(collections: &[CollectionStats]) -> u64 {
+    collections.iter().map(|stats| stats.memory_bytes).sum()
+}
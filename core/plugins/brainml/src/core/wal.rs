@@ -0,0 +1,241 @@
+use crate::adapters::braindb::{
+    CreateCollectionRequest, DeleteDocumentsRequest, UpsertDocumentsRequest,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use thiserror::Error;
+use tracing::warn;
+
+/// Bytes before each entry: payload length and checksum, both u32 LE.
+const HEADER_BYTES: usize = 8;
+
+/// Entries larger than this are treated as corrupt on replay, so larger
+/// writes are refused before they are logged.
+pub const MAX_ENTRY_BYTES: usize = 1 << 30;
+
+/// When appended entries reach the disk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FsyncPolicy {
+    /// Before every write is acknowledged. Nothing acknowledged is lost.
+    #[default]
+    Always,
+    /// Periodically. Survives a process crash but may lose the last
+    /// interval on power loss.
+    Interval,
+    /// Left to the operating system.
+    Never,
+}
+
+#[derive(Debug, Error)]
+pub enum WalError {
+    #[error("write-ahead log I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("write-ahead log entry could not be encoded: {0}")]
+    Encode(#[from] serde_json::Error),
+    #[error("write-ahead log is unusable after a failed append")]
+    Poisoned,
+    #[error("write-ahead log entry of {bytes} bytes exceeds the {max} byte limit")]
+    TooLarge { bytes: usize, max: usize },
+}
+
+/// A logged mutation, replayed on startup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum WalOp {
+    CreateCollection(CreateCollectionRequest),
+    Upsert(UpsertDocumentsRequest),
+    Delete(DeleteDocumentsRequest),
+}
+
+impl WalOp {
+    /// Frames the entry as written to the log. Payloads over `max_bytes`,
+    /// or over [`MAX_ENTRY_BYTES`] whatever `max_bytes` says, are rejected
+    /// since replay could not read them back.
+    pub fn encode(&self, max_bytes: usize) -> Result<Vec<u8>, WalError> {
+        let payload = serde_json::to_vec(self)?;
+        let max = max_bytes.min(MAX_ENTRY_BYTES);
+        if payload.len() > max {
+            return Err(WalError::TooLarge {
+                bytes: payload.len(),
+                max,
+            });
+        }
+        let mut frame = Vec::with_capacity(HEADER_BYTES + payload.len());
+        frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&checksum(&payload).to_le_bytes());
+        frame.extend_from_slice(&payload);
+        Ok(frame)
+    }
+}
+
+fn checksum(payload: &[u8]) -> u32 {
+    let digest = Sha256::digest(payload);
+    u32::from_le_bytes([digest[0], digest[1], digest[2], digest[3]])
+}
+
+/// Entries recovered when opening a log.
+#[derive(Debug, Default)]
+pub struct Recovery {
+    pub entries: Vec<WalOp>,
+    /// Bytes dropped from the end: a torn or corrupt entry and all that
+    /// follows it.
+    pub truncated_bytes: u64,
+}
+
+/// Append-only log of framed entries. Every mutation is appended before it
+/// is applied, so the log can be replayed on top of the last checkpoint.
+/// Replaying an entry twice leaves the same state, which makes a crash
+/// between writing a checkpoint and resetting the log harmless.
+pub struct Wal {
+    file: File,
+    len: u64,
+    policy: FsyncPolicy,
+    dirty: bool,
+    poisoned: bool,
+    #[cfg(any(test, feature = "fault-injection"))]
+    fail_after_bytes: Option<u64>,
+}
+
+impl Wal {
+    /// Opens or creates the log at `path` and reads back its entries. A
+    /// torn write at the end, left by a crash mid-append, is truncated.
+    pub fn open(path: &Path, policy: FsyncPolicy) -> Result<(Self, Recovery), WalError> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        let mut recovery = Recovery::default();
+        let mut offset = 0;
+        while let Some((op, next)) = read_entry(&bytes, offset) {
+            recovery.entries.push(op);
+            offset = next;
+        }
+        if offset < bytes.len() {
+            recovery.truncated_bytes = (bytes.len() - offset) as u64;
+            warn!(
+                path = %path.display(),
+                offset,
+                dropped = recovery.truncated_bytes,
+                "truncating torn write-ahead log tail"
+            );
+            file.set_len(offset as u64)?;
+            file.sync_all()?;
+        }
+        file.seek(SeekFrom::End(0))?;
+        Ok((
+            Self {
+                file,
+                len: offset as u64,
+                policy,
+                dirty: false,
+                poisoned: false,
+                #[cfg(any(test, feature = "fault-injection"))]
+                fail_after_bytes: None,
+            },
+            recovery,
+        ))
+    }
+
+    /// Appends one framed entry (see [`WalOp::encode`]) and returns the log
+    /// length. With [`FsyncPolicy::Always`] the entry is on disk when this
+    /// returns.
+    pub fn append(&mut self, frame: &[u8]) -> Result<u64, WalError> {
+        if self.poisoned {
+            return Err(WalError::Poisoned);
+        }
+        let result = self.write_frame(frame);
+        if result.is_err() {
+            // A partial frame may be on disk; replay drops it on next open.
+            self.poisoned = true;
+        }
+        result?;
+        self.len += frame.len() as u64;
+        self.dirty = true;
+        if self.policy == FsyncPolicy::Always {
+            self.sync()?;
+        }
+        Ok(self.len)
+    }
+
+    fn write_frame(&mut self, frame: &[u8]) -> Result<(), WalError> {
+        #[cfg(any(test, feature = "fault-injection"))]
+        if let Some(budget) = self.fail_after_bytes {
+            if (frame.len() as u64) > budget {
+                self.file.write_all(&frame[..budget as usize])?;
+                self.file.sync_data()?;
+                return Err(std::io::Error::other("injected fault: write interrupted").into());
+            }
+            self.fail_after_bytes = Some(budget - frame.len() as u64);
+        }
+        self.file.write_all(frame)?;
+        Ok(())
+    }
+
+    /// Flushes appended entries to disk if any are pending.
+    pub fn sync(&mut self) -> Result<(), WalError> {
+        if self.dirty {
+            self.file.sync_data()?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Empties the log once its entries are covered by a checkpoint.
+    pub fn reset(&mut self) -> Result<(), WalError> {
+        if self.poisoned {
+            return Err(WalError::Poisoned);
+        }
+        self.file.set_len(0)?;
+        self.file.sync_all()?;
+        self.len = 0;
+        self.dirty = false;
+        Ok(())
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Fault injection for crash tests: the append that would take the log
+    /// past `bytes` more bytes writes only part of its entry and fails, as
+    /// if the process died mid-write.
+    #[cfg(any(test, feature = "fault-injection"))]
+    pub fn fail_after_bytes(&mut self, bytes: u64) {
+        self.fail_after_bytes = Some(bytes);
+    }
+}
+
+impl Drop for Wal {
+    fn drop(&mut self) {
+        if let Err(err) = self.sync() {
+            warn!(error = %err, "failed to flush write-ahead log");
+        }
+    }
+}
+
+fn read_entry(bytes: &[u8], offset: usize) -> Option<(WalOp, usize)> {
+    let header = bytes.get(offset..offset + HEADER_BYTES)?;
+    let len = u32::from_le_bytes(header[..4].try_into().ok()?) as usize;
+    let expected = u32::from_le_bytes(header[4..].try_into().ok()?);
+    if len > MAX_ENTRY_BYTES {
+        return None;
+    }
+    let start = offset + HEADER_BYTES;
+    let payload = bytes.get(start..start + len)?;
+    if checksum(payload) != expected {
+        return None;
+    }
+    let op = serde_json::from_slice(payload).ok()?;
+    Some((op, start + len))
+}
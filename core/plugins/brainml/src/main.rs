@@ -3,6 +3,7 @@ use std::{collections::HashMap, net::SocketAddr, path::PathBuf, sync::Arc};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use axum::Router;
+use clap::Parser;
 use tokio::net::TcpListener;
 use tokio::signal;
 use tokio::sync::oneshot;
@@ -10,11 +11,34 @@ use tracing::{error, info};
 
 use brainml::adapters::braindb::{BraindbClient, PluginBusBraindbClient};
 use brainml::adapters::llm::{LlmClient, PluginBusLlmClient};
-use brainml::core::bus::{channel, start_bus, Handler, OutboundCommand};
-use brainml::core::config::{BrainmlConfig, BrainmlConfigLoader};
-use brainml::core::pipeline::PipelineManager;
+use brainml::adapters::local::LocalBraindbClient;
+use brainml::api::errors::ApiError;
+use brainml::core::bus::{channel, start_bus, Admit, Handler, OutboundCommand};
+use brainml::core::config::{
+    BrainmlConfig, ConfigOverrides, ConfigSources, SharedConfig, StorageBackend,
+};
+use brainml::core::errors::{ErrorCode, ErrorDetail};
+use brainml::core::tenancy::TenantContext;
 use brainml::util::tracing::init_tracing;
 
+/// brainml plug-in for the bkg.rs platform.
+///
+/// Configuration layers, lowest to highest precedence: built-in defaults,
+/// the config file (JSON or YAML), `BRAINML_*` environment variables and
+/// these flags.
+#[derive(Debug, Parser)]
+#[command(name = "brainml", version)]
+struct Cli {
+    /// Config file; defaults to `$BRAINML_CONFIG` or `./config.json`.
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Print the effective configuration and exit.
+    #[arg(long)]
+    print_config: bool,
+    #[command(flatten)]
+    overrides: ConfigOverrides,
+}
+
 mod plugin_interface {
     use super::*;
 
@@ -67,12 +91,7 @@ impl BrainmlPlugin {
 impl BkgPlugin for BrainmlPlugin {
     async fn init(&mut self, config: BrainmlConfig) -> Result<()> {
         let router = self.routes();
-        let port_env = std::env::var("PLUGIN_PORT").ok();
-        let port = if let Some(port_str) = port_env {
-            port_str.parse::<u16>().context("invalid PLUGIN_PORT")?
-        } else {
-            config.port
-        };
+        let port = config.port;
         let addr: SocketAddr = format!("0.0.0.0:{port}").parse()?;
         let listener = TcpListener::bind(addr).await?;
         let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
@@ -88,7 +107,7 @@ impl BkgPlugin for BrainmlPlugin {
         info!(port, "brainml http server listening");
 
         let handlers = build_handlers(self.state.clone());
-        let bus_url = bus_endpoint(&config);
+        let bus_url = config.bus.clone();
         let receiver = self
             .command_receiver
             .take()
@@ -97,11 +116,12 @@ impl BkgPlugin for BrainmlPlugin {
             self.name.clone(),
             &bus_url,
             port,
-            capabilities(),
+            brainml::api::admin::capabilities(),
             serde_json::json!({"version": env!("CARGO_PKG_VERSION")}),
             receiver,
             self.command_sender.clone(),
             handlers,
+            bus_admission(self.state.clone()),
         )
         .await
         .map_err(|err| anyhow::anyhow!("failed to start bus: {err}"))?;
@@ -141,87 +161,171 @@ impl BkgPlugin for BrainmlPlugin {
     }
 }
 
-fn capabilities() -> Vec<String> {
-    vec![
-        "brainml.index".into(),
-        "brainml.query".into(),
-        "brainml.train".into(),
-        "brainml.stats".into(),
-        "brainml.admin".into(),
-    ]
+fn bus_tenant(
+    state: &brainml::api::AppState,
+    token: Option<&str>,
+) -> Result<TenantContext, ErrorDetail> {
+    TenantContext::resolve(&state.config.load().tenancy, None, token)
+        .map_err(|err| failed(err.into()))
 }
 
-fn bus_endpoint(config: &BrainmlConfig) -> String {
-    if let Ok(port) = std::env::var("BKG_PLUGIN_BUS_PORT") {
-        format!("ws://127.0.0.1:{port}")
-    } else {
-        config.bus.clone()
-    }
+fn invalid_payload(err: serde_json::Error) -> ErrorDetail {
+    ErrorDetail::new(ErrorCode::InvalidRequest, err.to_string())
+}
+
+/// Classifies a handler error the same way the HTTP API does.
+fn failed(err: anyhow::Error) -> ErrorDetail {
+    ApiError::from(err).into()
 }
 
 fn build_handlers(state: brainml::api::AppState) -> Arc<HashMap<String, Arc<Handler>>> {
     let mut map: HashMap<String, Arc<Handler>> = HashMap::new();
-    for capability in capabilities() {
+    for capability in brainml::api::admin::capabilities() {
         let state_clone = state.clone();
         let handler: Arc<Handler> = match capability.as_str() {
-            "brainml.index" => Arc::new(move |_id, _capability, payload, _token| {
+            "brainml.index" => Arc::new(move |_id, _capability, payload, token| {
                 let state = state_clone.clone();
                 Box::pin(async move {
+                    let tenant = bus_tenant(&state, token.as_deref())?;
                     let request: brainml::core::schema::IndexRequest =
-                        serde_json::from_value(payload).map_err(|err| err.to_string())?;
+                        serde_json::from_value(payload).map_err(invalid_payload)?;
                     let response = state
-                        .process_index(request)
+                        .process_index_for(&tenant, request)
                         .await
-                        .map_err(|err| err.to_string())?;
-                    serde_json::to_value(response).map_err(|err| err.to_string())
+                        .map_err(failed)?;
+                    serde_json::to_value(response).map_err(|err| failed(err.into()))
                 })
             }),
-            "brainml.query" => Arc::new(move |_id, _capability, payload, _token| {
+            "brainml.ingest" => Arc::new(move |_id, _capability, payload, token| {
                 let state = state_clone.clone();
                 Box::pin(async move {
+                    let tenant = bus_tenant(&state, token.as_deref())?;
+                    let request: brainml::core::schema::IngestRequest =
+                        serde_json::from_value(payload).map_err(invalid_payload)?;
+                    let response = state
+                        .process_ingest_for(&tenant, request)
+                        .await
+                        .map_err(failed)?;
+                    serde_json::to_value(response).map_err(|err| failed(err.into()))
+                })
+            }),
+            "brainml.bulk" => Arc::new(move |_id, _capability, payload, token| {
+                let state = state_clone.clone();
+                Box::pin(async move {
+                    let tenant = bus_tenant(&state, token.as_deref())?;
+                    let request: brainml::core::schema::BulkRequest =
+                        serde_json::from_value(payload).map_err(invalid_payload)?;
+                    let event = state
+                        .process_bulk_for(&tenant, request)
+                        .await
+                        .map_err(failed)?;
+                    serde_json::to_value(event).map_err(|err| failed(err.into()))
+                })
+            }),
+            "brainml.query" => Arc::new(move |_id, _capability, payload, token| {
+                let state = state_clone.clone();
+                Box::pin(async move {
+                    let tenant = bus_tenant(&state, token.as_deref())?;
                     let request: brainml::core::schema::QueryRequest =
-                        serde_json::from_value(payload).map_err(|err| err.to_string())?;
+                        serde_json::from_value(payload).map_err(invalid_payload)?;
                     let response = state
-                        .process_query(request)
+                        .process_query_for(&tenant, request)
                         .await
-                        .map_err(|err| err.to_string())?;
-                    serde_json::to_value(response).map_err(|err| err.to_string())
+                        .map_err(failed)?;
+                    serde_json::to_value(response).map_err(|err| failed(err.into()))
                 })
             }),
-            "brainml.train" => Arc::new(move |_id, _capability, payload, _token| {
+            "brainml.train" => Arc::new(move |_id, _capability, payload, token| {
                 let state = state_clone.clone();
                 Box::pin(async move {
+                    let tenant = bus_tenant(&state, token.as_deref())?;
                     let request: brainml::core::schema::TrainRequest =
-                        serde_json::from_value(payload).map_err(|err| err.to_string())?;
+                        serde_json::from_value(payload).map_err(invalid_payload)?;
                     let response = state
-                        .process_train(request)
+                        .process_train_for(&tenant, request)
                         .await
-                        .map_err(|err| err.to_string())?;
-                    serde_json::to_value(response).map_err(|err| err.to_string())
+                        .map_err(failed)?;
+                    serde_json::to_value(response).map_err(|err| failed(err.into()))
                 })
             }),
-            "brainml.stats" => Arc::new(move |_id, _capability, _payload, _token| {
+            "brainml.stats" => Arc::new(move |_id, _capability, _payload, token| {
                 let state = state_clone.clone();
                 Box::pin(async move {
-                    let stats = state.braindb.stats().await.map_err(|err| err.to_string())?;
-                    serde_json::to_value(stats).map_err(|err| err.to_string())
+                    let tenant = bus_tenant(&state, token.as_deref())?;
+                    let stats = state.process_stats_for(&tenant).await.map_err(failed)?;
+                    serde_json::to_value(stats).map_err(|err| failed(err.into()))
                 })
             }),
-            "brainml.admin" => Arc::new(move |_id, _capability, _payload, _token| {
+            "brainml.admin" => Arc::new(move |_id, _capability, payload, token| {
                 let state = state_clone.clone();
                 Box::pin(async move {
-                    let status = brainml::core::schema::AdminStatus {
-                        version: env!("CARGO_PKG_VERSION").to_string(),
-                        uptime_seconds: state.start_time.elapsed().as_secs(),
-                        capabilities: capabilities(),
+                    let tenant = bus_tenant(&state, token.as_deref())?;
+                    let request: brainml::core::schema::AdminRequest = if payload.is_null() {
+                        Default::default()
+                    } else {
+                        serde_json::from_value(payload).map_err(invalid_payload)?
                     };
-                    serde_json::to_value(status).map_err(|err| err.to_string())
+                    let status = state
+                        .process_admin_for(&tenant, request)
+                        .await
+                        .map_err(failed)?;
+                    serde_json::to_value(status).map_err(|err| failed(err.into()))
+                })
+            }),
+            "brainml.feedback" => Arc::new(move |_id, _capability, payload, token| {
+                let state = state_clone.clone();
+                Box::pin(async move {
+                    let tenant = bus_tenant(&state, token.as_deref())?;
+                    let request: brainml::core::schema::FeedbackRequest =
+                        serde_json::from_value(payload).map_err(invalid_payload)?;
+                    let response = state
+                        .process_feedback_for(&tenant, request)
+                        .await
+                        .map_err(failed)?;
+                    serde_json::to_value(response).map_err(|err| failed(err.into()))
+                })
+            }),
+            "brainml.analytics" => Arc::new(move |_id, _capability, payload, token| {
+                let state = state_clone.clone();
+                Box::pin(async move {
+                    let tenant = bus_tenant(&state, token.as_deref())?;
+                    let request: brainml::core::schema::AnalyticsReportRequest =
+                        if payload.is_null() {
+                            Default::default()
+                        } else {
+                            serde_json::from_value(payload).map_err(invalid_payload)?
+                        };
+                    let report = state
+                        .process_analytics_report_for(&tenant, request)
+                        .await
+                        .map_err(failed)?;
+                    serde_json::to_value(report).map_err(|err| failed(err.into()))
+                })
+            }),
+            "brainml.audit" => Arc::new(move |_id, _capability, payload, token| {
+                let state = state_clone.clone();
+                Box::pin(async move {
+                    let tenant = bus_tenant(&state, token.as_deref())?;
+                    let request: brainml::core::schema::AuditQueryRequest = if payload.is_null() {
+                        Default::default()
+                    } else {
+                        serde_json::from_value(payload).map_err(invalid_payload)?
+                    };
+                    let response = state
+                        .process_audit_for(&tenant, request)
+                        .await
+                        .map_err(failed)?;
+                    serde_json::to_value(response).map_err(|err| failed(err.into()))
                 })
             }),
             other => {
-                let error = format!("unsupported capability {other}");
+                let error = ErrorDetail::new(
+                    ErrorCode::Unsupported,
+                    format!("unsupported capability {other}"),
+                );
                 Arc::new(move |_id, _capability, _payload, _token| {
-                    Box::pin(async move { Err(error.clone()) })
+                    let error = error.clone();
+                    Box::pin(async move { Err(error) })
                 })
             }
         };
@@ -230,13 +334,104 @@ fn build_handlers(state: brainml::api::AppState) -> Arc<HashMap<String, Arc<Hand
     Arc::new(map)
 }
 
+/// Applies the configured rate and concurrency limits to bus requests.
+/// Rejected requests fail with the `rate_limited` or `overloaded` code.
+fn bus_admission(state: brainml::api::AppState) -> Arc<Admit> {
+    Arc::new(move |capability, token| {
+        let tenant = bus_tenant(&state, token)?;
+        state
+            .try_admit(&tenant, capability)
+            .map_err(ErrorDetail::from)
+    })
+}
+
+/// Reloads the config on SIGHUP and, when `reload.watch` is set, whenever
+/// the config file's modification time changes.
+fn spawn_reload_triggers(state: brainml::api::AppState, path: PathBuf, config: &BrainmlConfig) {
+    #[cfg(unix)]
+    {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut hangup = match signal::unix::signal(signal::unix::SignalKind::hangup()) {
+                Ok(hangup) => hangup,
+                Err(err) => {
+                    error!(error = %err, "failed to install SIGHUP handler");
+                    return;
+                }
+            };
+            while hangup.recv().await.is_some() {
+                info!("SIGHUP received, reloading config");
+                if let Err(err) = state.reload_config() {
+                    error!(error = %err, "config reload failed, keeping active config");
+                }
+            }
+        });
+    }
+    if !config.reload.watch {
+        return;
+    }
+    let interval = std::time::Duration::from_secs(config.reload.poll_interval_secs);
+    tokio::spawn(async move {
+        let modified = |path: &PathBuf| {
+            std::fs::metadata(path)
+                .and_then(|meta| meta.modified())
+                .ok()
+        };
+        let mut last_seen = modified(&path);
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let current = modified(&path);
+            if current.is_none() || current == last_seen {
+                continue;
+            }
+            last_seen = current;
+            info!(path = %path.display(), "config file changed, reloading");
+            if let Err(err) = state.reload_config() {
+                error!(error = %err, "config reload failed, keeping active config");
+            }
+        }
+    });
+}
+
+/// Re-runs the directory sync on the configured interval. The interval and
+/// sources are re-read every pass so config reloads apply.
+fn spawn_directory_sync(state: brainml::api::AppState) {
+    tokio::spawn(async move {
+        loop {
+            let config = state.config.load();
+            if !config.sync.sources.is_empty() {
+                state.sync_all().await;
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(config.sync.interval_secs)).await;
+        }
+    });
+}
+
+/// Deletes documents past their collection's TTL on the configured
+/// interval. Like the directory sync, settings are re-read every pass.
+fn spawn_expiry_sweep(state: brainml::api::AppState) {
+    tokio::spawn(async move {
+        loop {
+            let config = state.config.load();
+            if !config.expiry.ttl_secs.is_empty() {
+                state.expire_documents().await;
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(config.expiry.interval_secs)).await;
+        }
+    });
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    init_tracing();
-    let plugin_name = std::env::var("BKG_PLUGIN_NAME").unwrap_or_else(|_| "brainml".to_string());
-    let config_path = match std::env::var("BRAINML_CONFIG") {
-        Ok(path) => PathBuf::from(path),
-        Err(_) => match std::env::current_dir() {
+    let cli = Cli::parse();
+    let explicit_path = cli
+        .config
+        .clone()
+        .or_else(|| std::env::var("BRAINML_CONFIG").ok().map(PathBuf::from));
+    let config_path = match &explicit_path {
+        Some(path) => path.clone(),
+        None => match std::env::current_dir() {
             Ok(mut cwd) => {
                 cwd.push("config.json");
                 cwd
@@ -244,23 +439,61 @@ async fn main() -> Result<()> {
             Err(_) => PathBuf::from("config.json"),
         },
     };
-    let config = BrainmlConfigLoader::load(&config_path)?;
+    let shared_config = SharedConfig::from_sources(ConfigSources {
+        file: Some(config_path.clone()),
+        file_required: explicit_path.is_some(),
+        environment: true,
+        overrides: cli.overrides,
+    })?;
+    let config = shared_config.load().as_ref().clone();
+    if cli.print_config {
+        println!("{}", serde_json::to_string_pretty(&config.redacted())?);
+        return Ok(());
+    }
+
+    let exporter = init_tracing(&config.telemetry)?;
+    let plugin_name = std::env::var("BKG_PLUGIN_NAME").unwrap_or_else(|_| "brainml".to_string());
     let (command_sender, command_receiver) = channel();
-    let braindb: Arc<dyn BraindbClient> =
-        Arc::new(PluginBusBraindbClient::new(command_sender.clone()));
+    let local_storage = match config.storage.backend {
+        StorageBackend::Braindb => None,
+        StorageBackend::Local => Some(
+            LocalBraindbClient::open(&config.storage)
+                .await
+                .with_context(|| {
+                    format!(
+                        "failed to open local storage at {}",
+                        config.storage.path.display()
+                    )
+                })?,
+        ),
+    };
+    let braindb: Arc<dyn BraindbClient> = match &local_storage {
+        Some(local) => Arc::new(local.clone()),
+        None => Arc::new(PluginBusBraindbClient::new(command_sender.clone())),
+    };
     let llm: Arc<dyn LlmClient> = Arc::new(PluginBusLlmClient::new(command_sender.clone()));
 
-    let state = brainml::api::AppState {
-        braindb,
-        llm,
-        pipeline: PipelineManager::default(),
-        config: config.clone(),
-        start_time: std::time::Instant::now(),
-    };
+    let state = brainml::api::AppState::new(braindb, llm, shared_config);
+    state.aliases.load(&config.aliases.path).with_context(|| {
+        format!(
+            "failed to load aliases from {}",
+            config.aliases.path.display()
+        )
+    })?;
 
-    let mut plugin =
-        BrainmlPlugin::new(plugin_name, state, command_sender.clone(), command_receiver);
-    plugin.init(config).await?;
+    let mut plugin = BrainmlPlugin::new(
+        plugin_name,
+        state.clone(),
+        command_sender.clone(),
+        command_receiver,
+    );
+    plugin.init(config.clone()).await?;
+    if config.migration.auto_start {
+        state.start_migration(&state.default_tenant(), None);
+    }
+    spawn_reload_triggers(state.clone(), config_path, &config);
+    spawn_directory_sync(state.clone());
+    spawn_expiry_sweep(state.clone());
 
     let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
     tokio::spawn(async move {
@@ -270,5 +503,14 @@ async fn main() -> Result<()> {
     });
     let _ = shutdown_rx.await;
     plugin.shutdown().await?;
+    if let Some(local) = local_storage {
+        local
+            .checkpoint()
+            .await
+            .context("failed to checkpoint local storage")?;
+    }
+    if let Some(exporter) = exporter {
+        exporter.flush().await;
+    }
     Ok(())
 }
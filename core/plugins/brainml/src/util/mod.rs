@@ -1,3 +1,5 @@
 pub mod id;
+pub mod otlp;
 pub mod time;
+pub mod trace_context;
 pub mod tracing;
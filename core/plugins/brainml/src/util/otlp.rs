@@ -0,0 +1,239 @@
+use crate::core::config::TelemetryConfig;
+use crate::util::trace_context::TraceContext;
+use anyhow::{bail, Context};
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::header::{CONTENT_TYPE, HOST};
+use hyper::Uri;
+use hyper_util::rt::TokioIo;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot};
+
+/// Spans buffered before new ones are dropped.
+const QUEUE_CAPACITY: usize = 4096;
+/// Spans per export request.
+const MAX_BATCH: usize = 512;
+
+/// OTLP span kinds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpanKind {
+    #[default]
+    Internal,
+    Server,
+    Client,
+    Producer,
+    Consumer,
+}
+
+impl SpanKind {
+    /// Reads an `otel.kind` span field; unknown values are internal.
+    pub fn parse(value: &str) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "server" => SpanKind::Server,
+            "client" => SpanKind::Client,
+            "producer" => SpanKind::Producer,
+            "consumer" => SpanKind::Consumer,
+            _ => SpanKind::Internal,
+        }
+    }
+
+    fn otlp_value(self) -> u8 {
+        match self {
+            SpanKind::Internal => 1,
+            SpanKind::Server => 2,
+            SpanKind::Client => 3,
+            SpanKind::Producer => 4,
+            SpanKind::Consumer => 5,
+        }
+    }
+}
+
+/// A finished span ready for export.
+#[derive(Debug, Clone)]
+pub struct SpanRecord {
+    pub name: String,
+    pub kind: SpanKind,
+    pub context: TraceContext,
+    pub parent_span_id: Option<u64>,
+    pub start: SystemTime,
+    pub end: SystemTime,
+    pub attributes: Vec<(String, String)>,
+}
+
+enum ExportMessage {
+    Span(SpanRecord),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Batches spans and posts them to an OTLP/HTTP collector as JSON. Export
+/// failures, including a collector that does not answer within the export
+/// timeout, are logged and the batch is dropped.
+#[derive(Clone)]
+pub struct OtlpExporter {
+    sender: mpsc::Sender<ExportMessage>,
+}
+
+impl OtlpExporter {
+    /// Starts the export task; must be called inside a tokio runtime.
+    pub fn spawn(endpoint: &str, config: &TelemetryConfig) -> anyhow::Result<Self> {
+        let uri = traces_uri(endpoint)?;
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(run(
+            Collector {
+                uri,
+                service_name: config.service_name.clone(),
+                timeout: Duration::from_millis(config.export_timeout_ms),
+            },
+            Duration::from_millis(config.export_interval_ms),
+            receiver,
+        ));
+        Ok(Self { sender })
+    }
+
+    /// Queues a span; drops it when the queue is full.
+    pub fn export(&self, span: SpanRecord) {
+        let _ = self.sender.try_send(ExportMessage::Span(span));
+    }
+
+    /// Sends every queued span and waits for the request to finish.
+    pub async fn flush(&self) {
+        let (done, finished) = oneshot::channel();
+        if self.sender.send(ExportMessage::Flush(done)).await.is_ok() {
+            let _ = finished.await;
+        }
+    }
+}
+
+/// `{endpoint}/v1/traces`. Only plain `http` collectors are supported.
+fn traces_uri(endpoint: &str) -> anyhow::Result<Uri> {
+    let endpoint = endpoint.trim_end_matches('/');
+    let uri: Uri = format!("{endpoint}/v1/traces")
+        .parse()
+        .with_context(|| format!("invalid OTLP endpoint {endpoint}"))?;
+    if uri.scheme_str() != Some("http") || uri.host().is_none() {
+        bail!("OTLP endpoint must be an http:// URL, got {endpoint}");
+    }
+    Ok(uri)
+}
+
+/// Where and how the export task posts batches.
+struct Collector {
+    uri: Uri,
+    service_name: String,
+    timeout: Duration,
+}
+
+async fn run(
+    collector: Collector,
+    interval: Duration,
+    mut receiver: mpsc::Receiver<ExportMessage>,
+) {
+    let mut batch = Vec::new();
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        tokio::select! {
+            message = receiver.recv() => match message {
+                Some(ExportMessage::Span(span)) => {
+                    batch.push(span);
+                    if batch.len() >= MAX_BATCH {
+                        send_batch(&collector, &mut batch).await;
+                    }
+                }
+                Some(ExportMessage::Flush(done)) => {
+                    send_batch(&collector, &mut batch).await;
+                    let _ = done.send(());
+                }
+                None => {
+                    send_batch(&collector, &mut batch).await;
+                    break;
+                }
+            },
+            _ = ticker.tick() => send_batch(&collector, &mut batch).await,
+        }
+    }
+}
+
+async fn send_batch(collector: &Collector, batch: &mut Vec<SpanRecord>) {
+    if batch.is_empty() {
+        return;
+    }
+    let body = encode(&collector.service_name, batch);
+    let spans = batch.len();
+    batch.clear();
+    match tokio::time::timeout(collector.timeout, post(&collector.uri, body)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => tracing::warn!(error = %err, spans, "failed to export spans"),
+        Err(_) => tracing::warn!(
+            timeout_ms = collector.timeout.as_millis() as u64,
+            spans,
+            "span export timed out"
+        ),
+    }
+}
+
+/// OTLP/JSON `ExportTraceServiceRequest`.
+pub fn encode(service_name: &str, spans: &[SpanRecord]) -> Vec<u8> {
+    let unix_nanos = |time: SystemTime| {
+        time.duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos()
+            .to_string()
+    };
+    let string_attribute = |key: &str, value: &str| serde_json::json!({ "key": key, "value": { "stringValue": value } });
+    let spans: Vec<_> = spans
+        .iter()
+        .map(|span| {
+            let mut value = serde_json::json!({
+                "traceId": format!("{:032x}", span.context.trace_id),
+                "spanId": format!("{:016x}", span.context.span_id),
+                "name": span.name,
+                "kind": span.kind.otlp_value(),
+                "startTimeUnixNano": unix_nanos(span.start),
+                "endTimeUnixNano": unix_nanos(span.end),
+                "attributes": span
+                    .attributes
+                    .iter()
+                    .map(|(key, value)| string_attribute(key, value))
+                    .collect::<Vec<_>>(),
+            });
+            if let Some(parent) = span.parent_span_id {
+                value["parentSpanId"] = format!("{parent:016x}").into();
+            }
+            value
+        })
+        .collect();
+    let request = serde_json::json!({
+        "resourceSpans": [{
+            "resource": { "attributes": [string_attribute("service.name", service_name)] },
+            "scopeSpans": [{
+                "scope": { "name": "brainml", "version": env!("CARGO_PKG_VERSION") },
+                "spans": spans,
+            }],
+        }],
+    });
+    serde_json::to_vec(&request).unwrap_or_default()
+}
+
+async fn post(uri: &Uri, body: Vec<u8>) -> anyhow::Result<()> {
+    let host = uri.host().unwrap_or_default();
+    let port = uri.port_u16().unwrap_or(80);
+    let stream = TcpStream::connect((host, port)).await?;
+    let (mut sender, connection) =
+        hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+    tokio::spawn(async move {
+        let _ = connection.await;
+    });
+    let authority = uri.authority().map(|a| a.as_str()).unwrap_or(host);
+    let request = hyper::Request::post(uri.path())
+        .header(HOST, authority)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(body)))?;
+    let response = sender.send_request(request).await?;
+    let status = response.status();
+    response.into_body().collect().await?;
+    if !status.is_success() {
+        bail!("collector answered {status}");
+    }
+    Ok(())
+}
@@ -0,0 +1,234 @@
+use crate::util::otlp::{OtlpExporter, SpanKind, SpanRecord};
+use rand::Rng;
+use std::fmt;
+use std::time::SystemTime;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Header and bus message field carrying the W3C trace context.
+pub const TRACEPARENT: &str = "traceparent";
+
+/// Span field holding an incoming `traceparent`; a span created with it
+/// continues the remote trace.
+const TRACEPARENT_FIELD: &str = "traceparent";
+/// Span fields read by the layer instead of being exported as attributes.
+const NAME_FIELD: &str = "otel.name";
+const KIND_FIELD: &str = "otel.kind";
+
+/// A W3C `traceparent` value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    pub trace_id: u128,
+    pub span_id: u64,
+    pub sampled: bool,
+}
+
+impl TraceContext {
+    /// Parses `00-<trace-id>-<span-id>-<flags>`. Unknown future versions are
+    /// accepted as long as the first four fields are well formed.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split('-');
+        let version = parts.next()?;
+        let trace_id = parts.next()?;
+        let span_id = parts.next()?;
+        let flags = parts.next()?;
+        let is_hex = |part: &str| {
+            part.bytes()
+                .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte))
+        };
+        if version.len() != 2
+            || version == "ff"
+            || (version == "00" && parts.next().is_some())
+            || trace_id.len() != 32
+            || span_id.len() != 16
+            || flags.len() != 2
+            || ![version, trace_id, span_id, flags].into_iter().all(is_hex)
+        {
+            return None;
+        }
+        let trace_id = u128::from_str_radix(trace_id, 16).ok()?;
+        let span_id = u64::from_str_radix(span_id, 16).ok()?;
+        let flags = u8::from_str_radix(flags, 16).ok()?;
+        if trace_id == 0 || span_id == 0 {
+            return None;
+        }
+        Some(Self {
+            trace_id,
+            span_id,
+            sampled: flags & 1 == 1,
+        })
+    }
+
+    /// Starts a new sampled trace.
+    pub fn root() -> Self {
+        let mut rng = rand::thread_rng();
+        Self {
+            trace_id: rng.gen_range(1..=u128::MAX),
+            span_id: rng.gen_range(1..=u64::MAX),
+            sampled: true,
+        }
+    }
+
+    /// A new span in the same trace.
+    pub fn child(&self) -> Self {
+        Self {
+            span_id: rand::thread_rng().gen_range(1..=u64::MAX),
+            ..*self
+        }
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id,
+            self.span_id,
+            u8::from(self.sampled)
+        )
+    }
+}
+
+/// Trace context of the current span, if [`TraceLayer`] is installed.
+pub fn current() -> Option<TraceContext> {
+    tracing::Span::current()
+        .with_subscriber(|(id, dispatch)| {
+            let registry = dispatch.downcast_ref::<tracing_subscriber::Registry>()?;
+            let span = registry.span(id)?;
+            let extensions = span.extensions();
+            extensions.get::<SpanTrace>().map(|trace| trace.context)
+        })
+        .flatten()
+}
+
+/// `traceparent` to send with an outgoing request from the current span.
+pub fn current_traceparent() -> Option<String> {
+    current().map(|context| context.to_string())
+}
+
+/// Per-span state kept in the registry's span extensions.
+struct SpanTrace {
+    context: TraceContext,
+    parent_span_id: Option<u64>,
+    name: String,
+    kind: SpanKind,
+    start: SystemTime,
+    attributes: Vec<(String, String)>,
+}
+
+#[derive(Default)]
+struct FieldVisitor {
+    traceparent: Option<String>,
+    name: Option<String>,
+    kind: Option<String>,
+    attributes: Vec<(String, String)>,
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            TRACEPARENT_FIELD => self.traceparent = Some(value.to_string()),
+            NAME_FIELD => self.name = Some(value.to_string()),
+            KIND_FIELD => self.kind = Some(value.to_string()),
+            name => self.attributes.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record_str(field, &format!("{value:?}"));
+    }
+}
+
+/// Assigns every span a place in a W3C trace and, with an exporter, sends
+/// finished sampled spans to it. A span continues its parent's trace, or the
+/// remote trace in its `traceparent` field, or starts a new one.
+#[derive(Clone, Default)]
+pub struct TraceLayer {
+    exporter: Option<OtlpExporter>,
+}
+
+impl TraceLayer {
+    pub fn new(exporter: Option<OtlpExporter>) -> Self {
+        Self { exporter }
+    }
+}
+
+impl<S> Layer<S> for TraceLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = FieldVisitor::default();
+        attrs.record(&mut fields);
+        let parent = fields
+            .traceparent
+            .as_deref()
+            .and_then(TraceContext::parse)
+            .or_else(|| {
+                let parent = span.parent()?;
+                let extensions = parent.extensions();
+                extensions.get::<SpanTrace>().map(|trace| trace.context)
+            });
+        let (context, parent_span_id) = match parent {
+            Some(parent) => (parent.child(), Some(parent.span_id)),
+            None => (TraceContext::root(), None),
+        };
+        span.extensions_mut().insert(SpanTrace {
+            context,
+            parent_span_id,
+            name: fields
+                .name
+                .unwrap_or_else(|| span.metadata().name().to_string()),
+            kind: fields
+                .kind
+                .as_deref()
+                .map(SpanKind::parse)
+                .unwrap_or_default(),
+            start: SystemTime::now(),
+            attributes: fields.attributes,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = FieldVisitor::default();
+        values.record(&mut fields);
+        let mut extensions = span.extensions_mut();
+        if let Some(trace) = extensions.get_mut::<SpanTrace>() {
+            trace.attributes.extend(fields.attributes);
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(exporter) = &self.exporter else {
+            return;
+        };
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(trace) = span.extensions_mut().remove::<SpanTrace>() else {
+            return;
+        };
+        if trace.context.sampled {
+            exporter.export(SpanRecord {
+                name: trace.name,
+                kind: trace.kind,
+                context: trace.context,
+                parent_span_id: trace.parent_span_id,
+                start: trace.start,
+                end: SystemTime::now(),
+                attributes: trace.attributes,
+            });
+        }
+    }
+}
@@ -1,11 +1,32 @@
+use crate::core::config::TelemetryConfig;
+use crate::util::otlp::OtlpExporter;
+use crate::util::trace_context::TraceLayer;
 use tracing_subscriber::fmt::time::UtcTime;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
 
-pub fn init_tracing() {
+/// Installs the JSON log subscriber and the trace context layer. When an
+/// OTLP endpoint is configured (or `OTEL_EXPORTER_OTLP_ENDPOINT` is set) the
+/// returned exporter should be flushed before exit.
+pub fn init_tracing(config: &TelemetryConfig) -> anyhow::Result<Option<OtlpExporter>> {
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-    tracing_subscriber::fmt()
-        .with_env_filter(filter)
-        .with_timer(UtcTime::rfc_3339())
-        .json()
+    let endpoint = config
+        .otlp_endpoint
+        .clone()
+        .or_else(|| std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok())
+        .filter(|endpoint| !endpoint.trim().is_empty());
+    let exporter = endpoint
+        .map(|endpoint| OtlpExporter::spawn(&endpoint, config))
+        .transpose()?;
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_timer(UtcTime::rfc_3339())
+                .json(),
+        )
+        .with(TraceLayer::new(exporter.clone()))
         .init();
+    Ok(exporter)
 }
@@ -0,0 +1,350 @@
+use anyhow::Result;
+use axum::body::{to_bytes, Body};
+use axum::http::{header, Request, StatusCode};
+use brainml::adapters::braindb::NullBraindbClient;
+use brainml::adapters::llm::NullLlmClient;
+use brainml::api::AppState;
+use brainml::core::aggregations::truncate;
+use brainml::core::config::{BrainmlConfig, CollectionDefaults};
+use brainml::core::schema::{
+    Aggregation, AggregationBucket, AggregationResult, CalendarInterval, DateField, DateRange,
+    DocumentInput, IndexRequest, QueryRequest,
+};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+async fn seeded_state() -> Result<AppState> {
+    let state = AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        Arc::new(NullLlmClient),
+        BrainmlConfig::default(),
+    );
+    let documents = [
+        json!({ "category": "guide", "tags": ["rust", "async"], "price": 12.5, "meta": { "pages": 40 }, "published": "2024-01-15T10:00:00Z" }),
+        json!({ "category": "guide", "tags": ["rust"], "price": 30, "meta": { "pages": 120 }, "published": "2024-01-31T23:59:59Z" }),
+        json!({ "category": "reference", "tags": ["go", "rust", "rust"], "price": 7, "published": "2024-02-01T00:00:00Z" }),
+        json!({ "category": "news", "price": "free", "published": "not a date" }),
+    ];
+    state
+        .process_index(IndexRequest {
+            collection: "books".into(),
+            documents: documents
+                .into_iter()
+                .enumerate()
+                .map(|(idx, metadata)| DocumentInput {
+                    id: Some(format!("book-{idx}")),
+                    text: "programming book".into(),
+                    metadata,
+                })
+                .collect(),
+            embed: false,
+            fts: true,
+            ..Default::default()
+        })
+        .await?;
+    Ok(state)
+}
+
+fn bucket(key: serde_json::Value, doc_count: usize) -> AggregationBucket {
+    AggregationBucket { key, doc_count }
+}
+
+fn query(aggregations: Vec<(&str, Aggregation)>) -> QueryRequest {
+    QueryRequest {
+        collection: "books".into(),
+        query: Some("book".into()),
+        top_k: 1,
+        aggregations: aggregations
+            .into_iter()
+            .map(|(name, aggregation)| (name.to_string(), aggregation))
+            .collect(),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn facets_cover_every_candidate_not_just_the_page() -> Result<()> {
+    let state = seeded_state().await?;
+    let response = state
+        .process_query(query(vec![
+            (
+                "categories",
+                Aggregation::Terms {
+                    field: "category".into(),
+                    size: 2,
+                },
+            ),
+            (
+                "tags",
+                Aggregation::Terms {
+                    field: "tags".into(),
+                    size: 10,
+                },
+            ),
+            (
+                "prices",
+                Aggregation::Histogram {
+                    field: "price".into(),
+                    interval: 10.0,
+                },
+            ),
+            (
+                "min_price",
+                Aggregation::Min {
+                    field: "price".into(),
+                },
+            ),
+            (
+                "max_price",
+                Aggregation::Max {
+                    field: "price".into(),
+                },
+            ),
+            (
+                "avg_pages",
+                Aggregation::Avg {
+                    field: "meta.pages".into(),
+                },
+            ),
+        ]))
+        .await?;
+    assert_eq!(response.results.len(), 1);
+    let aggregations = response.aggregations;
+
+    assert_eq!(
+        aggregations["categories"],
+        AggregationResult {
+            doc_count: 4,
+            buckets: Some(vec![bucket(json!("guide"), 2), bucket(json!("news"), 1)]),
+            value: None,
+        }
+    );
+    // Repeated array values count once per document.
+    assert_eq!(
+        aggregations["tags"].buckets,
+        Some(vec![
+            bucket(json!("rust"), 3),
+            bucket(json!("async"), 1),
+            bucket(json!("go"), 1),
+        ])
+    );
+    assert_eq!(
+        aggregations["prices"],
+        AggregationResult {
+            doc_count: 3,
+            buckets: Some(vec![
+                bucket(json!(0.0), 1),
+                bucket(json!(10.0), 1),
+                bucket(json!(30.0), 1),
+            ]),
+            value: None,
+        }
+    );
+    assert_eq!(aggregations["min_price"].value, Some(7.0));
+    assert_eq!(aggregations["max_price"].value, Some(30.0));
+    assert_eq!(
+        aggregations["avg_pages"],
+        AggregationResult {
+            doc_count: 2,
+            buckets: None,
+            value: Some(80.0),
+        }
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn aggregations_stop_at_the_result_window() -> Result<()> {
+    let state = AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        Arc::new(NullLlmClient),
+        BrainmlConfig {
+            collection_defaults: CollectionDefaults {
+                max_result_window: 3,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    );
+    state
+        .process_index(IndexRequest {
+            collection: "books".into(),
+            documents: (0..5)
+                .map(|idx| DocumentInput {
+                    id: Some(format!("book-{idx}")),
+                    text: format!("programming book {idx}"),
+                    metadata: json!({ "category": "guide" }),
+                })
+                .collect(),
+            embed: false,
+            fts: true,
+            ..Default::default()
+        })
+        .await?;
+    let categories = || {
+        query(vec![(
+            "categories",
+            Aggregation::Terms {
+                field: "category".into(),
+                size: 10,
+            },
+        )])
+    };
+
+    let response = state.process_query(categories()).await?;
+    assert_eq!(
+        response.aggregations["categories"],
+        AggregationResult {
+            doc_count: 3,
+            buckets: Some(vec![bucket(json!("guide"), 3)]),
+            value: None,
+        }
+    );
+    assert!(response.aggregations_truncated);
+
+    let narrow = QueryRequest {
+        query: Some("book 1".into()),
+        ..categories()
+    };
+    let response = state.process_query(narrow).await?;
+    assert_eq!(response.aggregations["categories"].doc_count, 1);
+    assert!(!response.aggregations_truncated);
+    Ok(())
+}
+
+#[tokio::test]
+async fn date_histograms_bucket_by_calendar_interval() -> Result<()> {
+    let state = seeded_state().await?;
+    let response = state
+        .process_query(query(vec![
+            (
+                "by_month",
+                Aggregation::DateHistogram {
+                    field: "published".into(),
+                    interval: CalendarInterval::Month,
+                },
+            ),
+            (
+                "indexed",
+                Aggregation::DateHistogram {
+                    field: "created_at".into(),
+                    interval: CalendarInterval::Year,
+                },
+            ),
+        ]))
+        .await?;
+    assert_eq!(
+        response.aggregations["by_month"],
+        AggregationResult {
+            doc_count: 3,
+            buckets: Some(vec![
+                bucket(json!("2024-01-01T00:00:00+00:00"), 2),
+                bucket(json!("2024-02-01T00:00:00+00:00"), 1),
+            ]),
+            value: None,
+        }
+    );
+    let indexed = &response.aggregations["indexed"];
+    assert_eq!(indexed.doc_count, 4);
+    assert_eq!(indexed.buckets.as_ref().map(Vec::len), Some(1));
+
+    // Filters apply before aggregating.
+    let filtered = state
+        .process_query(QueryRequest {
+            date_range: Some(DateRange {
+                field: DateField::CreatedAt,
+                to: Some(Utc::now() - chrono::Duration::days(1)),
+                ..Default::default()
+            }),
+            ..query(vec![(
+                "categories",
+                Aggregation::Terms {
+                    field: "category".into(),
+                    size: 10,
+                },
+            )])
+        })
+        .await?;
+    assert_eq!(
+        filtered.aggregations["categories"],
+        AggregationResult {
+            doc_count: 0,
+            buckets: Some(Vec::new()),
+            value: None,
+        }
+    );
+
+    let at: DateTime<Utc> = "2024-03-07T15:42:10Z".parse()?;
+    let starts: Vec<String> = [
+        CalendarInterval::Minute,
+        CalendarInterval::Hour,
+        CalendarInterval::Day,
+        CalendarInterval::Week,
+        CalendarInterval::Month,
+        CalendarInterval::Year,
+    ]
+    .into_iter()
+    .map(|interval| truncate(at, interval).to_rfc3339())
+    .collect();
+    assert_eq!(
+        starts,
+        vec![
+            "2024-03-07T15:42:00+00:00",
+            "2024-03-07T15:00:00+00:00",
+            "2024-03-07T00:00:00+00:00",
+            "2024-03-04T00:00:00+00:00",
+            "2024-03-01T00:00:00+00:00",
+            "2024-01-01T00:00:00+00:00",
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn aggregations_round_trip_over_http() -> Result<()> {
+    let state = seeded_state().await?;
+    let post = |body: serde_json::Value| {
+        Request::post("/api/v1/brainml/query")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+    };
+
+    let response = brainml::api::router(state.clone())
+        .oneshot(post(json!({
+            "collection": "books",
+            "query": "book",
+            "aggregations": {
+                "categories": { "type": "terms", "field": "category", "size": 1 },
+                "avg_price": { "type": "avg", "field": "price" }
+            }
+        }))?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: serde_json::Value =
+        serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await?)?;
+    assert_eq!(
+        body["aggregations"],
+        json!({
+            "avg_price": { "docCount": 3, "value": 49.5 / 3.0 },
+            "categories": { "docCount": 4, "buckets": [{ "key": "guide", "docCount": 2 }] }
+        })
+    );
+
+    let mut aggregations = BTreeMap::new();
+    aggregations.insert(
+        "prices".to_string(),
+        json!({ "type": "histogram", "field": "price", "interval": 0 }),
+    );
+    let response = brainml::api::router(state)
+        .oneshot(post(json!({
+            "collection": "books",
+            "query": "book",
+            "aggregations": aggregations
+        }))?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    Ok(())
+}
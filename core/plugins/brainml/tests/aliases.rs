@@ -0,0 +1,247 @@
+use anyhow::Result;
+use axum::body::{to_bytes, Body};
+use axum::http::{header, Request, StatusCode};
+use brainml::adapters::braindb::NullBraindbClient;
+use brainml::adapters::llm::NullLlmClient;
+use brainml::api::AppState;
+use brainml::core::aliases::{AliasError, AliasRegistry};
+use brainml::core::config::{AliasConfig, BrainmlConfig, QueryCacheConfig, TenancyConfig};
+use brainml::core::schema::{
+    AdminAction, AdminRequest, AliasUpdate, CollectionAlias, DocumentInput, IndexRequest,
+    QueryRequest,
+};
+use brainml::core::tenancy::TenantContext;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+struct Workspace {
+    root: PathBuf,
+}
+
+impl Workspace {
+    fn new(name: &str) -> Self {
+        let root =
+            std::env::temp_dir().join(format!("brainml-aliases-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        Self { root }
+    }
+
+    fn aliases_path(&self) -> PathBuf {
+        self.root.join("aliases.json")
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+fn state(workspace: &Workspace) -> AppState {
+    AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        Arc::new(NullLlmClient),
+        BrainmlConfig {
+            tenancy: TenancyConfig {
+                enabled: true,
+                tokens: HashMap::from([("blue-token".to_string(), "blue".to_string())]),
+                ..Default::default()
+            },
+            query_cache: QueryCacheConfig {
+                enabled: true,
+                ..Default::default()
+            },
+            aliases: AliasConfig {
+                path: workspace.aliases_path(),
+            },
+            ..Default::default()
+        },
+    )
+}
+
+async fn index(state: &AppState, tenant: &TenantContext, collection: &str, id: &str) -> Result<()> {
+    state
+        .process_index_for(
+            tenant,
+            IndexRequest {
+                collection: collection.into(),
+                documents: vec![DocumentInput {
+                    id: Some(id.into()),
+                    text: "quarterly report".into(),
+                    metadata: serde_json::json!({}),
+                }],
+                embed: false,
+                fts: true,
+                ..Default::default()
+            },
+        )
+        .await?;
+    Ok(())
+}
+
+async fn ids(state: &AppState, tenant: &TenantContext, collection: &str) -> Result<Vec<String>> {
+    let response = state
+        .process_query_for(
+            tenant,
+            QueryRequest {
+                collection: collection.into(),
+                query: Some("report".into()),
+                top_k: 5,
+                ..Default::default()
+            },
+        )
+        .await?;
+    Ok(response
+        .results
+        .into_iter()
+        .map(|result| result.id)
+        .collect())
+}
+
+fn point(alias: &str, collection: &str) -> AliasUpdate {
+    AliasUpdate {
+        alias: alias.into(),
+        collection: Some(collection.into()),
+    }
+}
+
+fn update(aliases: Vec<AliasUpdate>) -> AdminRequest {
+    AdminRequest {
+        action: AdminAction::UpdateAliases,
+        aliases,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn swapping_an_alias_redirects_queries() -> Result<()> {
+    let workspace = Workspace::new("swap");
+    let state = state(&workspace);
+    let tenant = state.default_tenant();
+    index(&state, &tenant, "docs_blue", "blue-1").await?;
+    index(&state, &tenant, "docs_green", "green-1").await?;
+
+    let status = state
+        .process_admin(update(vec![point("docs", "docs_blue")]))
+        .await?;
+    assert_eq!(
+        status.aliases,
+        vec![CollectionAlias {
+            alias: "docs".into(),
+            collection: "docs_blue".into(),
+        }]
+    );
+    assert_eq!(ids(&state, &tenant, "docs").await?, vec!["blue-1"]);
+
+    state
+        .process_admin(update(vec![point("docs", "docs_green")]))
+        .await?;
+    assert_eq!(ids(&state, &tenant, "docs").await?, vec!["green-1"]);
+
+    // Writes name the collection itself, so none can take the alias's name.
+    let err = index(&state, &tenant, "docs", "green-2").await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<AliasError>(),
+        Some(AliasError::Invalid { .. })
+    ));
+    assert_eq!(ids(&state, &tenant, "docs_green").await?, vec!["green-1"]);
+
+    state
+        .process_admin(update(vec![AliasUpdate {
+            alias: "docs".into(),
+            collection: None,
+        }]))
+        .await?;
+    assert!(state.admin_status().aliases.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_updates_change_nothing() -> Result<()> {
+    let workspace = Workspace::new("invalid");
+    let state = state(&workspace);
+    let tenant = state.default_tenant();
+    index(&state, &tenant, "docs_blue", "blue-1").await?;
+    state
+        .process_admin(update(vec![point("docs", "docs_blue")]))
+        .await?;
+
+    let cases = [
+        // The second change fails, so the first is not applied either.
+        vec![point("latest", "docs_blue"), point("docs", "missing")],
+        vec![point("docs_blue", "docs_blue")],
+        vec![point("latest", "docs")],
+        vec![AliasUpdate {
+            alias: "unknown".into(),
+            collection: None,
+        }],
+    ];
+    for changes in cases {
+        let err = state.process_admin(update(changes)).await.unwrap_err();
+        assert!(err.is::<AliasError>(), "{err}");
+    }
+    assert_eq!(
+        state.admin_status().aliases,
+        vec![CollectionAlias {
+            alias: "docs".into(),
+            collection: "docs_blue".into(),
+        }]
+    );
+
+    // The persisted file survives a restart.
+    let restarted = AliasRegistry::default();
+    restarted.load(&workspace.aliases_path())?;
+    assert_eq!(restarted.resolve("docs"), "docs_blue");
+    assert_eq!(restarted.resolve("latest"), "latest");
+    Ok(())
+}
+
+#[tokio::test]
+async fn aliases_are_scoped_to_the_tenant() -> Result<()> {
+    let workspace = Workspace::new("tenants");
+    let state = state(&workspace);
+    let config = state.config.load().tenancy.clone();
+    let blue = TenantContext::named(&config, "blue")?;
+    index(&state, &blue, "docs_v2", "blue-1").await?;
+    index(&state, &state.default_tenant(), "docs_v2", "default-1").await?;
+
+    let response = brainml::api::router(state.clone())
+        .oneshot(
+            Request::post("/api/v1/brainml/admin")
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::AUTHORIZATION, "Bearer blue-token")
+                .body(Body::from(
+                    r#"{"action":"updateAliases","aliases":[{"alias":"docs","collection":"docs_v2"}]}"#,
+                ))?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: serde_json::Value =
+        serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await?)?;
+    assert_eq!(
+        body["aliases"],
+        serde_json::json!([{ "alias": "docs", "collection": "docs_v2" }])
+    );
+
+    assert_eq!(ids(&state, &blue, "docs").await?, vec!["blue-1"]);
+    assert!(ids(&state, &state.default_tenant(), "docs")
+        .await?
+        .is_empty());
+    assert!(state.admin_status().aliases.is_empty());
+
+    // Pointing at a collection the tenant does not have is a 404.
+    let response = brainml::api::router(state)
+        .oneshot(
+            Request::post("/api/v1/brainml/admin")
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::AUTHORIZATION, "Bearer blue-token")
+                .body(Body::from(
+                    r#"{"action":"updateAliases","aliases":[{"alias":"docs","collection":"missing"}]}"#,
+                ))?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use anyhow::Result;
+use brainml::adapters::braindb::NullBraindbClient;
+use brainml::adapters::llm::NullLlmClient;
+use brainml::api::errors::ApiError;
+use brainml::api::AppState;
+use brainml::core::analytics::AnalyticsError;
+use brainml::core::config::{AnalyticsConfig, BrainmlConfig};
+use brainml::core::errors::ErrorCode;
+use brainml::core::schema::{
+    AnalyticsReportRequest, DocumentInput, FeedbackRequest, IndexRequest, QueryRequest,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+struct LogDir(PathBuf);
+
+impl LogDir {
+    fn new(name: &str) -> Self {
+        let dir =
+            std::env::temp_dir().join(format!("brainml-analytics-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        Self(dir)
+    }
+}
+
+impl Drop for LogDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn state(analytics: AnalyticsConfig) -> AppState {
+    AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        Arc::new(NullLlmClient),
+        BrainmlConfig {
+            analytics,
+            ..Default::default()
+        },
+    )
+}
+
+fn enabled(dir: &LogDir) -> AnalyticsConfig {
+    AnalyticsConfig {
+        enabled: true,
+        path: dir.0.join("queries.ndjson"),
+        ..Default::default()
+    }
+}
+
+async fn index(state: &AppState, collection: &str, texts: &[&str]) -> Result<()> {
+    state
+        .process_index(IndexRequest {
+            collection: collection.into(),
+            documents: texts
+                .iter()
+                .enumerate()
+                .map(|(idx, text)| DocumentInput {
+                    id: Some(format!("doc-{idx}")),
+                    text: text.to_string(),
+                    metadata: serde_json::json!({}),
+                })
+                .collect(),
+            fts: true,
+            ..Default::default()
+        })
+        .await?;
+    Ok(())
+}
+
+fn query(collection: &str, text: &str) -> QueryRequest {
+    QueryRequest {
+        collection: collection.into(),
+        query: Some(text.into()),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn report_computes_click_through_and_zero_result_rates() -> Result<()> {
+    let dir = LogDir::new("report");
+    let state = state(enabled(&dir));
+    index(&state, "docs", &["rust search engine", "vector ranking"]).await?;
+    index(&state, "faq", &["billing questions"]).await?;
+
+    let clicked = state.process_query(query("docs", "search")).await?;
+    let query_id = clicked.query_id.clone().expect("query id");
+    state.process_query(query("docs", "ranking")).await?;
+    state.process_query(query("docs", "kubernetes")).await?;
+    let unclicked = state.process_query(query("faq", "billing")).await?;
+
+    for (query_id, collection, relevant) in [
+        (query_id.clone(), "docs", true),
+        (query_id, "docs", true),
+        (unclicked.query_id.expect("query id"), "faq", false),
+    ] {
+        let response = state
+            .process_feedback_for(
+                &state.default_tenant(),
+                FeedbackRequest {
+                    query_id,
+                    collection: collection.into(),
+                    document_id: "doc-0".into(),
+                    relevant,
+                },
+            )
+            .await?;
+        assert!(response.recorded);
+    }
+
+    let report = state
+        .process_analytics_report_for(&state.default_tenant(), AnalyticsReportRequest::default())
+        .await?;
+    assert_eq!(report.collections.len(), 2);
+    let docs = &report.collections[0];
+    assert_eq!(docs.collection, "docs");
+    assert_eq!(docs.queries, 3);
+    assert_eq!(docs.zero_result_queries, 1);
+    assert!((docs.zero_result_rate - 1.0 / 3.0).abs() < 1e-9);
+    assert_eq!(docs.clicked_queries, 1);
+    assert!((docs.click_through_rate - 1.0 / 3.0).abs() < 1e-9);
+    assert_eq!(docs.relevant_feedback, 2);
+    let faq = &report.collections[1];
+    assert_eq!(faq.click_through_rate, 0.0);
+    assert_eq!(faq.irrelevant_feedback, 1);
+
+    let filtered = state
+        .process_analytics_report_for(
+            &state.default_tenant(),
+            AnalyticsReportRequest {
+                collection: Some("faq".into()),
+                since: None,
+            },
+        )
+        .await?;
+    assert_eq!(filtered.collections.len(), 1);
+
+    let log = std::fs::read_to_string(dir.0.join("queries.ndjson"))?;
+    let first: serde_json::Value = serde_json::from_str(log.lines().next().unwrap())?;
+    assert_eq!(first["event"], "query");
+    assert_eq!(first["query"], "search");
+    assert_eq!(first["result_ids"], serde_json::json!(["doc-0"]));
+    Ok(())
+}
+
+#[tokio::test]
+async fn log_rotates_and_keeps_the_configured_number_of_files() -> Result<()> {
+    let dir = LogDir::new("rotate");
+    let state = state(AnalyticsConfig {
+        max_file_bytes: 1024,
+        max_files: 2,
+        ..enabled(&dir)
+    });
+    index(&state, "docs", &["rotating log entries"]).await?;
+    for _ in 0..20 {
+        state.process_query(query("docs", "rotating")).await?;
+    }
+
+    let mut files: Vec<_> = std::fs::read_dir(&dir.0)?
+        .map(|entry| entry.map(|entry| entry.file_name().to_string_lossy().to_string()))
+        .collect::<Result<_, _>>()?;
+    files.sort();
+    assert_eq!(files, vec!["queries.ndjson", "queries.ndjson.1"]);
+    for file in &files {
+        assert!(std::fs::metadata(dir.0.join(file))?.len() <= 1024);
+    }
+
+    let report = state
+        .process_analytics_report_for(&state.default_tenant(), AnalyticsReportRequest::default())
+        .await?;
+    let queries = report.collections[0].queries;
+    assert!(queries > 0 && queries < 20);
+    Ok(())
+}
+
+#[tokio::test]
+async fn disabled_log_records_nothing() -> Result<()> {
+    let state = state(AnalyticsConfig::default());
+    index(&state, "docs", &["quiet"]).await?;
+    let response = state.process_query(query("docs", "quiet")).await?;
+    assert!(response.query_id.is_none());
+
+    let err = state
+        .process_feedback_for(
+            &state.default_tenant(),
+            FeedbackRequest {
+                query_id: "missing".into(),
+                collection: "docs".into(),
+                document_id: "doc-0".into(),
+                relevant: true,
+            },
+        )
+        .await
+        .unwrap_err();
+    assert!(err.is::<AnalyticsError>());
+    assert_eq!(ApiError::from(err).code(), ErrorCode::Unsupported);
+    Ok(())
+}
@@ -0,0 +1,475 @@
+use anyhow::Result;
+use axum::body::{to_bytes, Body};
+use axum::http::{header, Request, StatusCode};
+use brainml::adapters::braindb::{NullBraindbClient, UpsertDocumentsRequest};
+use brainml::adapters::llm::NullLlmClient;
+use brainml::api::errors::ApiError;
+use brainml::api::AppState;
+use brainml::core::audit::{verify_chain, AuditError, AuditEvent, AuditLog, GENESIS_HASH};
+use brainml::core::config::{
+    AliasConfig, AuditConfig, BrainmlConfig, ExpiryConfig, SyncConfig, SyncSource, TenancyConfig,
+};
+use brainml::core::errors::ErrorCode;
+use brainml::core::schema::{
+    AdminAction, AdminRequest, AuditOutcome, AuditQueryRequest, AuditQueryResponse, DocumentInput,
+    DocumentRecord, IndexRequest, TrainRequest,
+};
+use brainml::core::tenancy::{token_fingerprint, TenantContext};
+use chrono::{Duration, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+struct LogDir(PathBuf);
+
+impl LogDir {
+    fn new(name: &str) -> Self {
+        let dir =
+            std::env::temp_dir().join(format!("brainml-audit-{name}-{}", uuid::Uuid::new_v4()));
+        Self(dir)
+    }
+
+    fn config(&self) -> AuditConfig {
+        AuditConfig {
+            enabled: true,
+            path: self.0.join("audit.ndjson"),
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        std::fs::read_to_string(self.0.join("audit.ndjson"))
+            .unwrap_or_default()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+impl Drop for LogDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn config(audit: AuditConfig) -> BrainmlConfig {
+    BrainmlConfig {
+        aliases: AliasConfig {
+            path: audit.path.with_file_name("aliases.json"),
+        },
+        tenancy: TenancyConfig {
+            enabled: true,
+            tokens: HashMap::from([
+                ("red-token".to_string(), "red".to_string()),
+                ("blue-token".to_string(), "blue".to_string()),
+            ]),
+            ..Default::default()
+        },
+        audit,
+        ..Default::default()
+    }
+}
+
+fn state(audit: AuditConfig) -> AppState {
+    state_with(config(audit))
+}
+
+fn state_with(config: BrainmlConfig) -> AppState {
+    AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        Arc::new(NullLlmClient),
+        config,
+    )
+}
+
+fn tenant(state: &AppState, token: &str) -> TenantContext {
+    TenantContext::resolve(&state.config.load().tenancy, None, Some(token)).unwrap()
+}
+
+fn index_request(collection: &str, count: usize) -> IndexRequest {
+    IndexRequest {
+        collection: collection.into(),
+        documents: (0..count)
+            .map(|idx| DocumentInput {
+                id: Some(format!("doc-{idx}")),
+                text: format!("policy document {idx}"),
+                metadata: serde_json::json!({}),
+            })
+            .collect(),
+        fts: true,
+        ..Default::default()
+    }
+}
+
+async fn audit(state: &AppState, request: AuditQueryRequest) -> Result<AuditQueryResponse> {
+    state
+        .process_audit_for(&state.default_tenant(), request)
+        .await
+}
+
+#[tokio::test]
+async fn writes_are_recorded_with_caller_and_outcome() -> Result<()> {
+    let dir = LogDir::new("writes");
+    let state = state(dir.config());
+    let red = tenant(&state, "red-token");
+
+    state
+        .process_index_for(&red, index_request("handbook", 3))
+        .await?;
+    assert!(state
+        .process_index_for(&red, index_request("bad::name", 1))
+        .await
+        .is_err());
+    state
+        .process_train_for(
+            &red,
+            TrainRequest {
+                pipeline: "reranker".into(),
+                params: serde_json::json!({}),
+            },
+        )
+        .await?;
+    // Reading the status changes nothing and is not recorded.
+    state
+        .process_admin_for(&red, AdminRequest::default())
+        .await?;
+    state
+        .process_admin_for(
+            &red,
+            AdminRequest {
+                action: AdminAction::UpdateAliases,
+                ..Default::default()
+            },
+        )
+        .await?;
+
+    let response = audit(&state, AuditQueryRequest::default()).await?;
+    assert_eq!(response.total, 4);
+    let records: Vec<_> = response.entries.iter().map(|entry| &entry.record).collect();
+    let capabilities: Vec<_> = records
+        .iter()
+        .map(|record| record.capability.as_str())
+        .collect();
+    assert_eq!(
+        capabilities,
+        [
+            "brainml.admin",
+            "brainml.train",
+            "brainml.index",
+            "brainml.index"
+        ]
+    );
+    let fingerprint = token_fingerprint("red-token");
+    for record in &records {
+        assert_eq!(record.tenant, "red");
+        assert_eq!(record.caller.as_deref(), Some(fingerprint.as_str()));
+    }
+    assert_eq!(records[0].action.as_deref(), Some("updateAliases"));
+    assert_eq!(records[1].action.as_deref(), Some("reranker"));
+    assert_eq!(records[2].outcome, AuditOutcome::Failure);
+    assert!(records[2].error.is_some());
+    assert_eq!(records[3].outcome, AuditOutcome::Success);
+    assert_eq!(records[3].collection.as_deref(), Some("handbook"));
+    assert_eq!(records[3].documents, Some(3));
+    assert_eq!(records[3].prev_hash, GENESIS_HASH);
+
+    let chain = response.chain.expect("default tenant sees the chain");
+    assert!(chain.valid);
+    assert_eq!(chain.entries, 4);
+    assert_eq!(chain.head_hash.as_ref(), Some(&response.entries[0].hash));
+    assert!(!dir.lines().join("\n").contains("red-token"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn edited_or_removed_entries_break_the_chain() -> Result<()> {
+    let dir = LogDir::new("tamper");
+    let config = dir.config();
+    let log = AuditLog::default();
+    let red = TenantContext::resolve(&TenancyConfig::default(), None, Some("red-token"))?;
+    for documents in [10, 20, 30] {
+        log.append(
+            &config,
+            AuditEvent::new(&red, "brainml.index")
+                .collection("handbook")
+                .documents(documents),
+            None,
+        )?;
+    }
+    let lines = dir.lines();
+    let borrowed: Vec<&str> = lines.iter().map(String::as_str).collect();
+    assert!(verify_chain(&borrowed).valid);
+
+    let edited = lines[1].replace("\"documents\":20", "\"documents\":2");
+    assert_ne!(edited, lines[1]);
+    let status = verify_chain(&[&lines[0], &edited, &lines[2]]);
+    assert!(!status.valid);
+    assert_eq!(status.broken_at, Some(2));
+    assert_eq!(
+        status.reason.as_deref(),
+        Some("entry does not match its hash")
+    );
+
+    let status = verify_chain(&[&lines[0], &lines[2]]);
+    assert_eq!(status.broken_at, Some(2));
+    let status = verify_chain(&[&lines[1], &lines[2]]);
+    assert_eq!(status.broken_at, Some(1));
+    let status = verify_chain(&[&lines[0], &lines[1], &lines[2], "{\"seq\":"]);
+    assert_eq!(status.broken_at, Some(4));
+
+    // The same checks run on every query of the log.
+    std::fs::write(
+        &config.path,
+        format!("{}\n{edited}\n{}\n", lines[0], lines[2]),
+    )?;
+    let (entries, status) = log.read(&config)?;
+    assert_eq!(entries.len(), 3);
+    assert_eq!(status.broken_at, Some(2));
+    Ok(())
+}
+
+#[tokio::test]
+async fn removing_the_newest_entries_breaks_the_chain() -> Result<()> {
+    let dir = LogDir::new("truncate");
+    let config = dir.config();
+    let red = TenantContext::resolve(&TenancyConfig::default(), None, Some("red-token"))?;
+    let log = AuditLog::default();
+    let mut appended = Vec::new();
+    for _ in 0..3 {
+        appended.push(log.append(&config, AuditEvent::new(&red, "brainml.train"), None)?);
+    }
+    let lines = dir.lines();
+    std::fs::write(&config.path, format!("{}\n{}\n", lines[0], lines[1]))?;
+    let (entries, status) = log.read(&config)?;
+    assert_eq!(entries.len(), 2);
+    assert!(!status.valid);
+    assert_eq!(status.broken_at, Some(3));
+    assert_eq!(
+        status.reason.as_deref(),
+        Some("log ends at entry 2 but its head file records entry 3")
+    );
+
+    // After a restart the chain continues from the recorded head, so the
+    // removed entry still shows up as a gap.
+    let log = AuditLog::default();
+    let next = log.append(&config, AuditEvent::new(&red, "brainml.train"), None)?;
+    assert_eq!(next.record.seq, 4);
+    assert_eq!(next.record.prev_hash, appended[2].hash);
+    let (_, status) = log.read(&config)?;
+    assert!(!status.valid);
+    assert_eq!(status.broken_at, Some(3));
+    Ok(())
+}
+
+#[tokio::test]
+async fn torn_entry_is_dropped_before_the_next_append() -> Result<()> {
+    let dir = LogDir::new("torn");
+    let config = dir.config();
+    let red = TenantContext::resolve(&TenancyConfig::default(), None, Some("red-token"))?;
+    let first =
+        AuditLog::default().append(&config, AuditEvent::new(&red, "brainml.train"), None)?;
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&config.path)?;
+    std::io::Write::write_all(&mut file, b"{\"seq\":2,\"timest")?;
+
+    // A fresh log, as after a restart, continues from the last whole entry.
+    let log = AuditLog::default();
+    let second = log.append(
+        &config,
+        AuditEvent::new(&red, "brainml.admin").action("reloadConfig"),
+        Some("config file missing".into()),
+    )?;
+    assert_eq!(second.record.seq, 2);
+    assert_eq!(second.record.prev_hash, first.hash);
+    assert_eq!(second.record.outcome, AuditOutcome::Failure);
+    let (entries, status) = log.read(&config)?;
+    assert_eq!(entries.len(), 2);
+    assert!(status.valid);
+    assert_eq!(status.head_hash, Some(second.hash));
+    Ok(())
+}
+
+#[tokio::test]
+async fn queries_filter_and_stay_within_the_tenant() -> Result<()> {
+    let dir = LogDir::new("query");
+    let state = state(dir.config());
+    let (red, blue) = (tenant(&state, "red-token"), tenant(&state, "blue-token"));
+    state
+        .process_index_for(&red, index_request("handbook", 2))
+        .await?;
+    state
+        .process_index_for(&red, index_request("faq", 1))
+        .await?;
+    state
+        .process_index_for(&blue, index_request("handbook", 5))
+        .await?;
+
+    let response = audit(
+        &state,
+        AuditQueryRequest {
+            collection: Some("handbook".into()),
+            ..Default::default()
+        },
+    )
+    .await?;
+    assert_eq!(response.total, 2);
+    let response = audit(
+        &state,
+        AuditQueryRequest {
+            tenant: Some("red".into()),
+            limit: Some(1),
+            ..Default::default()
+        },
+    )
+    .await?;
+    assert_eq!(response.total, 2);
+    assert_eq!(response.entries.len(), 1);
+    assert_eq!(
+        response.entries[0].record.collection.as_deref(),
+        Some("faq")
+    );
+    let response = audit(
+        &state,
+        AuditQueryRequest {
+            outcome: Some(AuditOutcome::Failure),
+            ..Default::default()
+        },
+    )
+    .await?;
+    assert_eq!(response.total, 0);
+
+    // A tenant asking for another tenant's entries still gets its own.
+    let response = brainml::api::router(state.clone())
+        .oneshot(
+            Request::get("/api/v1/brainml/audit?tenant=red")
+                .header(header::AUTHORIZATION, "Bearer blue-token")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: AuditQueryResponse =
+        serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await?)?;
+    assert_eq!(body.total, 1);
+    assert_eq!(body.entries[0].record.tenant, "blue");
+    assert_eq!(body.entries[0].record.documents, Some(5));
+    assert!(body.chain.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn expiry_deletes_are_recorded_for_the_owning_tenant() -> Result<()> {
+    let dir = LogDir::new("expiry");
+    let state = state_with(BrainmlConfig {
+        expiry: ExpiryConfig {
+            ttl_secs: BTreeMap::from([("events".into(), 3_600)]),
+            ..Default::default()
+        },
+        ..config(dir.config())
+    });
+    let mut record = DocumentRecord::new(
+        DocumentInput {
+            id: Some("old".into()),
+            text: "old event".into(),
+            metadata: serde_json::json!({}),
+        },
+        None,
+    );
+    record.created_at = Utc::now() - Duration::days(2);
+    record.updated_at = record.created_at;
+    state
+        .braindb
+        .upsert_documents(UpsertDocumentsRequest {
+            collection: "red::events".into(),
+            documents: vec![record],
+        })
+        .await?;
+    let report = state.expire_documents().await.expect("no sweep running");
+    assert_eq!(report.expired, 1);
+
+    let red = tenant(&state, "red-token");
+    let response = state
+        .process_audit_for(&red, AuditQueryRequest::default())
+        .await?;
+    assert_eq!(response.total, 1);
+    let record = &response.entries[0].record;
+    assert_eq!(record.capability, "brainml.expiry");
+    assert_eq!(record.action.as_deref(), Some("delete"));
+    assert_eq!(record.collection.as_deref(), Some("events"));
+    assert_eq!(record.documents, Some(1));
+    assert_eq!(record.caller, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn sync_deletes_are_recorded() -> Result<()> {
+    let dir = LogDir::new("sync");
+    let docs = dir.0.join("docs");
+    std::fs::create_dir_all(&docs)?;
+    std::fs::write(docs.join("alpha.md"), "# Alpha\n\nalpha notes")?;
+    let state = state_with(BrainmlConfig {
+        sync: SyncConfig {
+            manifest_dir: dir.0.join("manifests"),
+            sources: vec![SyncSource {
+                path: docs.clone(),
+                collection: "notes".into(),
+                tenant: Some("red".into()),
+                extensions: Vec::new(),
+                ignore: Vec::new(),
+                embed: false,
+                fts: true,
+            }],
+            ..Default::default()
+        },
+        ..config(dir.config())
+    });
+    state.sync_all().await;
+    std::fs::remove_file(docs.join("alpha.md"))?;
+    let reports = state.sync_all().await;
+    assert_eq!(reports[0].deleted, 1);
+
+    let response = audit(
+        &state,
+        AuditQueryRequest {
+            capability: Some("brainml.sync".into()),
+            ..Default::default()
+        },
+    )
+    .await?;
+    assert_eq!(response.total, 1);
+    let record = &response.entries[0].record;
+    assert_eq!(record.tenant, "red");
+    assert_eq!(record.action.as_deref(), Some("delete"));
+    assert_eq!(record.collection.as_deref(), Some("notes"));
+    assert_eq!(record.documents, Some(1));
+    // The files it ingested are recorded as ingests.
+    let response = audit(
+        &state,
+        AuditQueryRequest {
+            capability: Some("brainml.ingest".into()),
+            ..Default::default()
+        },
+    )
+    .await?;
+    assert_eq!(response.total, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn disabled_log_records_nothing() -> Result<()> {
+    let dir = LogDir::new("disabled");
+    let state = state(AuditConfig {
+        enabled: false,
+        ..dir.config()
+    });
+    state
+        .process_index_for(&tenant(&state, "red-token"), index_request("handbook", 1))
+        .await?;
+    assert!(dir.lines().is_empty());
+    let err = audit(&state, AuditQueryRequest::default())
+        .await
+        .unwrap_err();
+    assert!(err.is::<AuditError>());
+    assert_eq!(ApiError::from(err).code(), ErrorCode::Unsupported);
+    Ok(())
+}
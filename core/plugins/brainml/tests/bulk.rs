@@ -0,0 +1,298 @@
+use anyhow::Result;
+use axum::body::{to_bytes, Body, Bytes};
+use axum::http::{header, Request, StatusCode};
+use brainml::adapters::braindb::NullBraindbClient;
+use brainml::adapters::llm::NullLlmClient;
+use brainml::api::errors::ApiError;
+use brainml::api::AppState;
+use brainml::core::bulk::BulkError;
+use brainml::core::config::{BrainmlConfig, BulkConfig, TenancyConfig, TenantQuota};
+use brainml::core::schema::{
+    BulkChunk, BulkEvent, BulkLineError, BulkOptions, BulkProgress, BulkRequest, BulkStreamRef,
+    DocumentInput, QueryRequest,
+};
+use brainml::core::tenancy::TenantContext;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+fn state() -> AppState {
+    AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        Arc::new(NullLlmClient),
+        BrainmlConfig {
+            tenancy: TenancyConfig {
+                enabled: true,
+                tokens: ["red", "green", "blue"]
+                    .into_iter()
+                    .map(|tenant| (format!("{tenant}-token"), tenant.to_string()))
+                    .collect(),
+                quotas: HashMap::from([(
+                    "red".to_string(),
+                    TenantQuota {
+                        max_documents: Some(3),
+                        max_storage_bytes: None,
+                        max_qps: None,
+                    },
+                )]),
+                ..Default::default()
+            },
+            bulk: BulkConfig {
+                batch_size: 2,
+                max_line_bytes: 1024,
+                max_chunk_documents: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )
+}
+
+fn line(id: &str) -> String {
+    format!("{{\"id\":\"{id}\",\"text\":\"bulk import\"}}\n")
+}
+
+/// Sends `body` in 7-byte pieces so lines straddle chunk boundaries.
+async fn post_bulk(state: &AppState, tenant: &str, body: String) -> Result<Vec<BulkEvent>> {
+    let pieces: Vec<Result<Bytes, std::io::Error>> = body
+        .into_bytes()
+        .chunks(7)
+        .map(|piece| Ok(Bytes::copy_from_slice(piece)))
+        .collect();
+    let response = brainml::api::router(state.clone())
+        .oneshot(
+            Request::post("/api/v1/brainml/bulk?collection=docs&fts=true")
+                .header(header::CONTENT_TYPE, "application/x-ndjson")
+                .header(header::AUTHORIZATION, format!("Bearer {tenant}-token"))
+                .body(Body::from_stream(futures_util::stream::iter(pieces)))?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "application/x-ndjson"
+    );
+    let body = to_bytes(response.into_body(), usize::MAX).await?;
+    body.split(|&byte| byte == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| Ok(serde_json::from_slice(line)?))
+        .collect()
+}
+
+async fn count(state: &AppState, tenant: &TenantContext) -> Result<usize> {
+    let response = state
+        .process_query_for(
+            tenant,
+            QueryRequest {
+                collection: "docs".into(),
+                query: Some("bulk".into()),
+                top_k: 50,
+                ..Default::default()
+            },
+        )
+        .await?;
+    Ok(response.results.len())
+}
+
+#[tokio::test]
+async fn ndjson_stream_reports_progress_per_batch() -> Result<()> {
+    let state = state();
+    let body = [
+        line("doc-1"),
+        line("doc-2"),
+        "{not json}\n".into(),
+        "\n".into(),
+        line("doc-3"),
+        format!("{{\"text\":\"{}\"}}\n", "x".repeat(2000)),
+        line("doc-4"),
+        // The last line has no trailing newline.
+        line("doc-5").trim_end().to_string(),
+    ]
+    .concat();
+    let events = post_bulk(&state, "green", body).await?;
+
+    let progress = |received, indexed, failed, batches| {
+        BulkEvent::Progress(BulkProgress {
+            stream_id: None,
+            received,
+            indexed,
+            failed,
+            batches,
+        })
+    };
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[0], progress(2, 2, 0, 1));
+    assert_eq!(events[1], progress(6, 4, 2, 2));
+    let BulkEvent::Summary(summary) = &events[2] else {
+        panic!("expected a summary, got {:?}", events[2]);
+    };
+    assert_eq!(summary.collection, "docs");
+    assert_eq!(
+        (
+            summary.received,
+            summary.indexed,
+            summary.failed,
+            summary.batches
+        ),
+        (7, 5, 2, 3)
+    );
+    assert_eq!(summary.error, None);
+    let lines: Vec<u64> = summary.errors.iter().map(|error| error.line).collect();
+    assert_eq!(lines, vec![3, 6]);
+    assert_eq!(
+        summary.errors[1],
+        BulkLineError {
+            line: 6,
+            message: "line exceeds 1024 bytes".into(),
+        }
+    );
+
+    let config = state.config.load().tenancy.clone();
+    let green = TenantContext::named(&config, "green")?;
+    assert_eq!(count(&state, &green).await?, 5);
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_batch_stops_the_stream() -> Result<()> {
+    let state = state();
+    let body = (1..=6).map(|idx| line(&format!("doc-{idx}"))).collect();
+    let events = post_bulk(&state, "red", body).await?;
+
+    let Some(BulkEvent::Summary(summary)) = events.last() else {
+        panic!("expected a summary last, got {events:?}");
+    };
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        (summary.indexed, summary.failed, summary.batches),
+        (2, 2, 1)
+    );
+    assert!(summary
+        .error
+        .as_deref()
+        .is_some_and(|error| error.contains("quota")));
+
+    let config = state.config.load().tenancy.clone();
+    let red = TenantContext::named(&config, "red")?;
+    assert_eq!(count(&state, &red).await?, 2);
+    Ok(())
+}
+
+fn documents(ids: &[&str]) -> Vec<DocumentInput> {
+    ids.iter()
+        .map(|id| DocumentInput {
+            id: Some(id.to_string()),
+            text: "bulk import".into(),
+            metadata: serde_json::json!({}),
+        })
+        .collect()
+}
+
+fn chunk(stream_id: Uuid, seq: u64, ids: &[&str]) -> BulkRequest {
+    BulkRequest::Chunk(BulkChunk {
+        stream_id,
+        seq,
+        documents: documents(ids),
+    })
+}
+
+#[tokio::test]
+async fn bus_chunks_are_acknowledged_in_order() -> Result<()> {
+    let state = state();
+    let config = state.config.load().tenancy.clone();
+    let blue = TenantContext::named(&config, "blue")?;
+    let green = TenantContext::named(&config, "green")?;
+
+    let opened = state
+        .process_bulk_for(
+            &blue,
+            BulkRequest::Open(BulkOptions {
+                collection: "docs".into(),
+                fts: true,
+                ..Default::default()
+            }),
+        )
+        .await?;
+    let BulkEvent::Progress(BulkProgress {
+        stream_id: Some(stream_id),
+        ..
+    }) = opened
+    else {
+        panic!("expected a stream id, got {opened:?}");
+    };
+
+    let ack = state
+        .process_bulk_for(&blue, chunk(stream_id, 0, &["a", "b", "c"]))
+        .await?;
+    assert_eq!(
+        ack,
+        BulkEvent::Progress(BulkProgress {
+            stream_id: Some(stream_id),
+            received: 3,
+            indexed: 3,
+            failed: 0,
+            batches: 2,
+        })
+    );
+
+    let rejected = [
+        (&blue, chunk(stream_id, 0, &["a"])),
+        (&blue, chunk(stream_id, 1, &["d", "e", "f", "g", "h"])),
+        (&green, chunk(stream_id, 1, &["d"])),
+    ];
+    for (tenant, request) in rejected {
+        let err = state.process_bulk_for(tenant, request).await.unwrap_err();
+        assert!(err.is::<BulkError>(), "{err}");
+    }
+    let err = state
+        .process_bulk_for(&green, chunk(stream_id, 1, &["d"]))
+        .await
+        .unwrap_err();
+    assert!(matches!(ApiError::from(err), ApiError::NotFound(_)));
+
+    state
+        .process_bulk_for(&blue, chunk(stream_id, 1, &["d", "e"]))
+        .await?;
+    let closed = state
+        .process_bulk_for(&blue, BulkRequest::Close(BulkStreamRef { stream_id }))
+        .await?;
+    let BulkEvent::Summary(summary) = closed else {
+        panic!("expected a summary, got {closed:?}");
+    };
+    assert_eq!(summary.stream_id, Some(stream_id));
+    assert_eq!(
+        (summary.received, summary.indexed, summary.batches),
+        (5, 5, 3)
+    );
+    assert_eq!(count(&state, &blue).await?, 5);
+    assert!(state.bulk.is_empty());
+
+    let err = state
+        .process_bulk_for(&blue, chunk(stream_id, 2, &["f"]))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<BulkError>(),
+        Some(BulkError::UnknownStream(_))
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn idle_bus_streams_are_dropped() -> Result<()> {
+    let state = state();
+    state
+        .process_bulk(BulkRequest::Open(BulkOptions {
+            collection: "docs".into(),
+            ..Default::default()
+        }))
+        .await?;
+    assert_eq!(state.bulk.len(), 1);
+    assert_eq!(state.bulk.purge_idle(Duration::from_secs(60)), 0);
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    assert_eq!(state.bulk.purge_idle(Duration::ZERO), 1);
+    assert!(state.bulk.is_empty());
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use brainml::core::distance::{self, Kernel};
+
+/// Deterministic values in [-1, 1).
+fn floats(len: usize, seed: u64) -> Vec<f32> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((state >> 40) as f32 / (1u64 << 24) as f32) * 2.0 - 1.0
+        })
+        .collect()
+}
+
+fn bytes(len: usize, seed: u64) -> Vec<i8> {
+    floats(len, seed)
+        .into_iter()
+        .map(|value| (value * 128.0).clamp(-128.0, 127.0) as i8)
+        .collect()
+}
+
+fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() <= 1e-4 * a.abs().max(b.abs()).max(1.0)
+}
+
+/// Lengths around every lane width so each tail path runs.
+const LENGTHS: [usize; 10] = [0, 1, 3, 4, 7, 8, 15, 17, 33, 1536];
+
+#[test]
+fn kernels_agree_with_scalar_on_f32() {
+    assert_eq!(Kernel::available().last(), Some(&Kernel::Scalar));
+    assert!(Kernel::available().contains(&distance::kernel()));
+    for kernel in Kernel::available() {
+        for len in LENGTHS {
+            let (a, b) = (floats(len, 1), floats(len, 2));
+            let pairs = [
+                (kernel.dot(&a, &b), Kernel::Scalar.dot(&a, &b)),
+                (kernel.cosine(&a, &b), Kernel::Scalar.cosine(&a, &b)),
+                (kernel.l2_squared(&a, &b), Kernel::Scalar.l2_squared(&a, &b)),
+                (kernel.l2(&a, &b), Kernel::Scalar.l2(&a, &b)),
+            ];
+            for (simd, scalar) in pairs {
+                assert!(
+                    close(simd, scalar),
+                    "{kernel} len {len}: {simd} vs {scalar}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn kernels_agree_with_scalar_on_int8() {
+    for kernel in Kernel::available() {
+        for len in LENGTHS {
+            let (a, b) = (bytes(len, 3), bytes(len, 4));
+            assert_eq!(kernel.dot_i8(&a, &b), Kernel::Scalar.dot_i8(&a, &b));
+            assert_eq!(
+                kernel.l2_squared_i8(&a, &b),
+                Kernel::Scalar.l2_squared_i8(&a, &b)
+            );
+            assert!(close(
+                kernel.cosine_i8(&a, &b),
+                Kernel::Scalar.cosine_i8(&a, &b)
+            ));
+        }
+        let extremes = [i8::MIN; 64];
+        assert_eq!(kernel.dot_i8(&extremes, &extremes), 64 * 128 * 128);
+        assert_eq!(
+            kernel.l2_squared_i8(&extremes, &[i8::MAX; 64]),
+            64 * 255 * 255
+        );
+    }
+}
+
+#[test]
+fn affine_kernel_matches_decoded_codes() {
+    for kernel in Kernel::available() {
+        for len in LENGTHS {
+            let query = floats(len, 5);
+            let codes: Vec<u8> = bytes(len, 6).into_iter().map(|b| b as u8).collect();
+            let (min, scale) = (-0.8, 0.006);
+            let decoded: Vec<f32> = codes
+                .iter()
+                .map(|code| min + scale * *code as f32)
+                .collect();
+            let expected = Kernel::Scalar.dot(&query, &decoded);
+            let got = kernel.dot_u8_affine(&query, &codes, min, scale);
+            assert!(
+                close(got, expected),
+                "{kernel} len {len}: {got} vs {expected}"
+            );
+        }
+    }
+}
+
+#[test]
+fn edge_cases_follow_the_scalar_rules() {
+    let (a, b) = (floats(20, 7), floats(12, 8));
+    for kernel in Kernel::available() {
+        // Dot and L2 use the common prefix; cosine needs equal lengths.
+        assert!(close(kernel.dot(&a, &b), Kernel::Scalar.dot(&a[..12], &b)));
+        assert!(close(
+            kernel.l2_squared(&a, &b),
+            Kernel::Scalar.l2_squared(&a[..12], &b)
+        ));
+        assert_eq!(kernel.cosine(&a, &b), 0.0);
+        assert_eq!(kernel.cosine(&[], &[]), 0.0);
+        assert_eq!(kernel.cosine(&[0.0; 16], &a[..16]), 0.0);
+        assert!(close(kernel.cosine(&a, &a), 1.0));
+        assert_eq!(kernel.cosine_i8(&[0; 16], &[1; 16]), 0.0);
+    }
+}
@@ -0,0 +1,166 @@
+use anyhow::Result;
+use brainml::adapters::braindb::NullBraindbClient;
+use brainml::adapters::llm::NullLlmClient;
+use brainml::api::AppState;
+use brainml::core::config::BrainmlConfig;
+use brainml::core::ranker::{RankerError, MAX_DIVERSIFY_CANDIDATES};
+use brainml::core::schema::{
+    DiversifyOptions, DocumentInput, IndexRequest, QueryRequest, TotalHitsRelation,
+};
+use std::sync::Arc;
+
+async fn seeded_state() -> Result<AppState> {
+    let state = AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        Arc::new(NullLlmClient),
+        BrainmlConfig::default(),
+    );
+    let documents = [
+        ("a-dup", "rust async runtime guide for tokio", "blog"),
+        ("b-dup", "Rust async runtime guide for tokio.", "blog"),
+        ("c-dup", "rust async runtime guide for tokio users", "docs"),
+        ("d-other", "rust borrow checker explained", "blog"),
+    ];
+    state
+        .process_index(IndexRequest {
+            collection: "chunks".into(),
+            documents: documents
+                .iter()
+                .map(|(id, text, source)| DocumentInput {
+                    id: Some(id.to_string()),
+                    text: text.to_string(),
+                    metadata: serde_json::json!({ "source": source }),
+                })
+                .collect(),
+            fts: true,
+            ..Default::default()
+        })
+        .await?;
+    Ok(state)
+}
+
+async fn query_ids(state: &AppState, diversify: DiversifyOptions) -> Result<Vec<String>> {
+    let response = state
+        .process_query(QueryRequest {
+            collection: "chunks".into(),
+            query: Some("rust".into()),
+            diversify: Some(diversify),
+            ..Default::default()
+        })
+        .await?;
+    Ok(response
+        .results
+        .into_iter()
+        .map(|result| result.id)
+        .collect())
+}
+
+#[tokio::test]
+async fn lambda_trades_relevance_for_diversity() -> Result<()> {
+    let state = seeded_state().await?;
+
+    let relevance_only = DiversifyOptions {
+        lambda: 1.0,
+        ..Default::default()
+    };
+    assert_eq!(
+        query_ids(&state, relevance_only).await?,
+        vec!["a-dup", "b-dup", "c-dup", "d-other"]
+    );
+
+    let diverse = DiversifyOptions {
+        lambda: 0.3,
+        ..Default::default()
+    };
+    let ids = query_ids(&state, diverse).await?;
+    assert_eq!(ids[..2], ["a-dup", "d-other"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn near_duplicates_collapse_by_similarity_or_key() -> Result<()> {
+    let state = seeded_state().await?;
+
+    let by_similarity = DiversifyOptions {
+        lambda: 1.0,
+        collapse_threshold: Some(0.8),
+        ..Default::default()
+    };
+    assert_eq!(
+        query_ids(&state, by_similarity).await?,
+        vec!["a-dup", "d-other"]
+    );
+
+    let by_key = DiversifyOptions {
+        lambda: 1.0,
+        collapse_key: Some("source".into()),
+        ..Default::default()
+    };
+    assert_eq!(query_ids(&state, by_key).await?, vec!["a-dup", "c-dup"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn lambda_outside_unit_interval_is_rejected() -> Result<()> {
+    let state = seeded_state().await?;
+    let err = query_ids(
+        &state,
+        DiversifyOptions {
+            lambda: 1.5,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<RankerError>(),
+        Some(RankerError::InvalidLambda(_))
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn diversified_pages_stop_at_the_requested_window() -> Result<()> {
+    let state = seeded_state().await?;
+    let request = |cursor: Option<String>| QueryRequest {
+        collection: "chunks".into(),
+        query: Some("rust".into()),
+        top_k: 1,
+        cursor,
+        diversify: Some(DiversifyOptions {
+            lambda: 0.3,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    let first = state.process_query(request(None)).await?;
+    assert_eq!(first.results[0].id, "a-dup");
+    assert_eq!(first.total_hits.value, 1);
+    assert_eq!(first.total_hits.relation, TotalHitsRelation::Gte);
+    let second = state.process_query(request(first.next_cursor)).await?;
+    assert_eq!(second.results[0].id, "d-other");
+    assert_eq!(second.results[0].rank, 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn candidate_pool_is_bounded() -> Result<()> {
+    let state = seeded_state().await?;
+    for candidates in [0, MAX_DIVERSIFY_CANDIDATES + 1] {
+        let err = query_ids(
+            &state,
+            DiversifyOptions {
+                candidates,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RankerError>(),
+            Some(RankerError::InvalidCandidates { .. })
+        ));
+    }
+    Ok(())
+}
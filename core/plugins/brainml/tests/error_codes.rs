@@ -0,0 +1,149 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use axum::body::{to_bytes, Body};
+use axum::http::{header, Request, StatusCode};
+use axum::response::IntoResponse;
+use brainml::adapters::braindb::{
+    BraindbClient, BraindbError, NullBraindbClient, PluginBusBraindbClient,
+};
+use brainml::adapters::llm::{EmbeddingRequest, EmbeddingVector, LlmClient, LlmError, LlmResult};
+use brainml::api::errors::ApiError;
+use brainml::api::AppState;
+use brainml::core::bus::{OutboundCommand, OutgoingMessage};
+use brainml::core::config::BrainmlConfig;
+use brainml::core::embeddings::UnknownVectorField;
+use brainml::core::errors::{ErrorCode, ErrorDetail};
+use std::sync::Arc;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+/// Embedding service that is never reachable.
+struct DownLlmClient;
+
+#[async_trait]
+impl LlmClient for DownLlmClient {
+    async fn embed(&self, _request: EmbeddingRequest) -> LlmResult<Vec<EmbeddingVector>> {
+        Err(LlmError::Connection("llm.embed has no provider".into()))
+    }
+}
+
+async fn body_json(response: axum::response::Response) -> Result<serde_json::Value> {
+    let bytes = to_bytes(response.into_body(), usize::MAX).await?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+#[tokio::test]
+async fn dependency_failures_map_to_distinct_statuses() -> Result<()> {
+    let cases = [
+        (
+            anyhow::Error::new(BraindbError::NotFound("collection docs".into())),
+            StatusCode::NOT_FOUND,
+            "not_found",
+        ),
+        (
+            anyhow::Error::new(LlmError::Connection("closed".into())),
+            StatusCode::SERVICE_UNAVAILABLE,
+            "unavailable",
+        ),
+        (
+            anyhow::Error::new(BraindbError::Timeout("db.hybridQuery".into())),
+            StatusCode::GATEWAY_TIMEOUT,
+            "timeout",
+        ),
+        (
+            anyhow::Error::new(UnknownVectorField("title".into())),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "validation_failed",
+        ),
+        (
+            anyhow::anyhow!("disk on fire"),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal",
+        ),
+    ];
+    for (error, status, code) in cases {
+        let response = ApiError::from(error).into_response();
+        assert_eq!(response.status(), status);
+        assert_eq!(body_json(response).await?["code"], code);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn http_query_reports_unavailable_embedding_service() -> Result<()> {
+    let state = AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        Arc::new(DownLlmClient),
+        BrainmlConfig::default(),
+    );
+    let response = brainml::api::router(state)
+        .oneshot(
+            Request::post("/api/v1/brainml/query")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(
+                    r#"{"collection":"docs","query":"revenue","hybrid":true}"#,
+                ))?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body = body_json(response).await?;
+    assert_eq!(body["code"], "unavailable");
+    assert!(body["error"]
+        .as_str()
+        .unwrap()
+        .contains("llm.embed has no provider"));
+    Ok(())
+}
+
+#[test]
+fn bus_errors_are_structured_and_read_back() {
+    let message = OutgoingMessage::Response {
+        requestId: Uuid::nil(),
+        success: false,
+        data: None,
+        error: Some(ErrorDetail::new(ErrorCode::Timeout, "db.scan")),
+        traceparent: None,
+    };
+    let value = serde_json::to_value(&message).unwrap();
+    assert_eq!(
+        value["error"],
+        serde_json::json!({ "code": "timeout", "message": "db.scan" })
+    );
+
+    let detail = ErrorDetail::from_bus(serde_json::json!({
+        "code": "not_found",
+        "message": "collection docs"
+    }));
+    assert!(matches!(
+        BraindbError::from(detail),
+        BraindbError::NotFound(message) if message == "collection docs"
+    ));
+
+    // Plain strings and codes from newer peers are upstream errors.
+    let detail = ErrorDetail::from_bus(serde_json::json!("boom"));
+    assert_eq!(detail, ErrorDetail::new(ErrorCode::UpstreamError, "boom"));
+    let detail = ErrorDetail::from_bus(serde_json::json!({ "code": "teapot", "message": "x" }));
+    assert_eq!(detail.code, ErrorCode::UpstreamError);
+    assert!(matches!(
+        LlmError::from(ErrorDetail::new(ErrorCode::Unavailable, "down")),
+        LlmError::Connection(_)
+    ));
+}
+
+#[tokio::test]
+async fn abandoned_invocations_are_cancelled_on_the_bus() -> Result<()> {
+    let (sender, mut receiver) = brainml::core::bus::channel();
+    let client = PluginBusBraindbClient::new(sender);
+    let call = tokio::spawn(async move { client.stats().await });
+    let Some(OutboundCommand::Invoke { request_id, .. }) = receiver.recv().await else {
+        panic!("expected an invoke command");
+    };
+
+    call.abort();
+    assert!(call.await.unwrap_err().is_cancelled());
+    let Some(OutboundCommand::Cancel { request_id: cancelled }) = receiver.recv().await else {
+        panic!("expected a cancel command");
+    };
+    assert_eq!(cancelled, request_id);
+    Ok(())
+}
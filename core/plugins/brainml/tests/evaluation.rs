@@ -0,0 +1,109 @@
+use anyhow::Result;
+use brainml::adapters::braindb::NullBraindbClient;
+use brainml::adapters::llm::NullLlmClient;
+use brainml::api::AppState;
+use brainml::core::config::BrainmlConfig;
+use brainml::core::evaluation::{
+    ndcg_at_k, recall_at_k, reciprocal_rank, EvaluationError, EvaluationOptions, Qrels,
+};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+fn ids(ids: &[&str]) -> Vec<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+fn close(actual: f64, expected: f64) -> bool {
+    (actual - expected).abs() < 1e-4
+}
+
+#[test]
+fn metrics_match_hand_computed_values() {
+    let grades: BTreeMap<String, u32> = [("a".to_string(), 2), ("c".to_string(), 1)].into();
+    let retrieved = ids(&["b", "a", "d", "c"]);
+
+    assert_eq!(recall_at_k(&retrieved, &grades, 2), 0.5);
+    assert_eq!(recall_at_k(&retrieved, &grades, 4), 1.0);
+    assert_eq!(reciprocal_rank(&retrieved, &grades, 4), 0.5);
+    assert_eq!(reciprocal_rank(&retrieved, &grades, 1), 0.0);
+    // DCG = 3/log2(3) + 1/log2(5); ideal = 3/log2(2) + 1/log2(3).
+    let expected = (3.0 / 3f64.log2() + 1.0 / 5f64.log2()) / (3.0 + 1.0 / 3f64.log2());
+    assert!(close(ndcg_at_k(&retrieved, &grades, 4), expected));
+    assert!(close(ndcg_at_k(&ids(&["a", "c"]), &grades, 4), 1.0));
+    assert_eq!(ndcg_at_k(&ids(&["b", "d"]), &grades, 4), 0.0);
+}
+
+#[tokio::test]
+async fn fixture_qrels_meet_baseline_quality() -> Result<()> {
+    let qrels = Qrels::load(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/qrels.json"
+    ))?;
+    let state = AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        Arc::new(NullLlmClient),
+        BrainmlConfig::default(),
+    );
+    state
+        .process_index(qrels.index_request(false).expect("corpus"))
+        .await?;
+
+    let report = state
+        .evaluate(&qrels, &EvaluationOptions::default())
+        .await?;
+    assert_eq!(report.queries, 3);
+    assert_eq!(report.recall, 1.0);
+    assert!(close(report.mrr, 2.0 / 3.0), "mrr {}", report.mrr);
+    let borrowing = &report.per_query[0];
+    assert_eq!(borrowing.retrieved, ids(&["d1", "d2"]));
+    assert!(borrowing.ndcg < 1.0 && borrowing.ndcg > 0.75);
+
+    let top_one = state
+        .evaluate(
+            &qrels,
+            &EvaluationOptions {
+                k: 1,
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert!(close(top_one.recall, 0.5 / 3.0));
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_qrels_are_rejected() -> Result<()> {
+    let state = AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        Arc::new(NullLlmClient),
+        BrainmlConfig::default(),
+    );
+    let qrels: Qrels = serde_json::from_value(serde_json::json!({
+        "collection": "eval",
+        "queries": [{ "id": "q1", "query": "nothing", "relevant": { "d1": 0 } }]
+    }))?;
+    let err = state
+        .evaluate(&qrels, &EvaluationOptions::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<EvaluationError>(),
+        Some(EvaluationError::NoRelevantDocuments(id)) if id == "q1"
+    ));
+
+    let err = state
+        .evaluate(
+            &qrels,
+            &EvaluationOptions {
+                k: 0,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<EvaluationError>(),
+        Some(EvaluationError::InvalidK)
+    ));
+    Ok(())
+}
@@ -0,0 +1,175 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use brainml::adapters::braindb::NullBraindbClient;
+use brainml::adapters::llm::{EmbeddingRequest, EmbeddingVector, LlmClient, LlmResult};
+use brainml::api::AppState;
+use brainml::core::config::BrainmlConfig;
+use brainml::core::schema::{
+    DocumentField, DocumentInput, HighlightOptions, IndexRequest, QueryRequest, Snippet,
+    SnippetKind,
+};
+use std::sync::Arc;
+
+/// Embeds text as counts of a few keywords so similarity is predictable.
+struct KeywordLlmClient;
+
+const KEYWORDS: [&str; 3] = ["cat", "dog", "fish"];
+
+#[async_trait]
+impl LlmClient for KeywordLlmClient {
+    async fn embed(&self, request: EmbeddingRequest) -> LlmResult<Vec<EmbeddingVector>> {
+        Ok(request
+            .input
+            .iter()
+            .map(|text| {
+                let text = text.to_lowercase();
+                EmbeddingVector {
+                    embedding: KEYWORDS
+                        .iter()
+                        .map(|keyword| text.matches(keyword).count() as f32)
+                        .collect(),
+                }
+            })
+            .collect())
+    }
+}
+
+fn state() -> AppState {
+    AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        Arc::new(KeywordLlmClient),
+        BrainmlConfig::default(),
+    )
+}
+
+fn highlighted() -> HighlightOptions {
+    HighlightOptions {
+        enabled: true,
+        ..Default::default()
+    }
+}
+
+async fn seed(state: &AppState) -> Result<()> {
+    state
+        .process_index(IndexRequest {
+            collection: "pets".into(),
+            documents: vec![DocumentInput {
+                id: Some("pets-1".into()),
+                text: "Cats sleep all day. Dogs bark loudly at night. Fish swim in circles.".into(),
+                metadata: serde_json::json!({"kind": "pets"}),
+            }],
+            embed: true,
+            fts: true,
+            ..Default::default()
+        })
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn results_only_carry_requested_fields() -> Result<()> {
+    let state = state();
+    seed(&state).await?;
+
+    let response = state
+        .process_query(QueryRequest {
+            collection: "pets".into(),
+            query: Some("bark".into()),
+            ..Default::default()
+        })
+        .await?;
+    let document = &response.results[0].document;
+    assert!(document.text.is_empty());
+    assert!(document.embedding.is_none());
+    assert_eq!(document.metadata, serde_json::json!({"kind": "pets"}));
+
+    let response = state
+        .process_query(QueryRequest {
+            collection: "pets".into(),
+            query: Some("bark".into()),
+            fields: vec![DocumentField::Text, DocumentField::Embedding],
+            ..Default::default()
+        })
+        .await?;
+    let document = &response.results[0].document;
+    assert!(document.text.starts_with("Cats sleep"));
+    assert!(document.embedding.is_some());
+    assert!(document.metadata.is_null());
+    Ok(())
+}
+
+#[tokio::test]
+async fn term_matches_are_wrapped_in_tags() -> Result<()> {
+    let state = state();
+    seed(&state).await?;
+
+    let response = state
+        .process_query(QueryRequest {
+            collection: "pets".into(),
+            query: Some("bark".into()),
+            highlight: highlighted(),
+            ..Default::default()
+        })
+        .await?;
+    let snippets = &response.results[0].snippets;
+    assert_eq!(snippets.len(), 1);
+    assert_eq!(snippets[0].kind, SnippetKind::Term);
+    assert!(snippets[0].text.contains("Dogs <em>bark</em> loudly"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn vector_hits_get_the_closest_sentence() -> Result<()> {
+    let state = state();
+    seed(&state).await?;
+
+    let response = state
+        .process_query(QueryRequest {
+            collection: "pets".into(),
+            vector: Some(vec![0.0, 1.0, 0.0]),
+            highlight: highlighted(),
+            ..Default::default()
+        })
+        .await?;
+    assert_eq!(response.results.len(), 1);
+    let snippets: &[Snippet] = &response.results[0].snippets;
+    assert_eq!(snippets.len(), 1);
+    assert_eq!(snippets[0].kind, SnippetKind::Semantic);
+    assert_eq!(snippets[0].text, "Dogs bark loudly at night.");
+    Ok(())
+}
+
+#[tokio::test]
+async fn snippets_are_opt_in_and_escape_the_text() -> Result<()> {
+    let state = state();
+    state
+        .process_index(IndexRequest {
+            collection: "markup".into(),
+            documents: vec![DocumentInput {
+                id: Some("markup-1".into()),
+                text: "Never <script>bark</script> at \"cats\" & dogs.".into(),
+                metadata: serde_json::json!({}),
+            }],
+            fts: true,
+            ..Default::default()
+        })
+        .await?;
+    let query = |highlight| QueryRequest {
+        collection: "markup".into(),
+        query: Some("bark".into()),
+        highlight,
+        ..Default::default()
+    };
+
+    let response = state
+        .process_query(query(HighlightOptions::default()))
+        .await?;
+    assert!(response.results[0].snippets.is_empty());
+
+    let response = state.process_query(query(highlighted())).await?;
+    assert_eq!(
+        response.results[0].snippets[0].text,
+        "Never &lt;script&gt;<em>bark</em>&lt;/script&gt; at &quot;cats&quot; &amp; dogs."
+    );
+    Ok(())
+}
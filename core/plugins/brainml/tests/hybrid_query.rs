@@ -3,23 +3,19 @@ use brainml::adapters::braindb::NullBraindbClient;
 use brainml::adapters::llm::NullLlmClient;
 use brainml::api::AppState;
 use brainml::core::config::BrainmlConfig;
-use brainml::core::pipeline::PipelineManager;
 use brainml::core::schema::{DocumentInput, IndexRequest, QueryRequest};
 use std::sync::Arc;
 
 fn state() -> AppState {
-    AppState {
-        braindb: Arc::new(NullBraindbClient::default()),
-        llm: Arc::new(NullLlmClient::default()),
-        pipeline: PipelineManager::default(),
-        config: BrainmlConfig {
+    AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        Arc::new(NullLlmClient),
+        BrainmlConfig {
             port: 43133,
             bus: "ws://127.0.0.1:43121".into(),
-            embedding_model: None,
-            collection_defaults: Default::default(),
+            ..Default::default()
         },
-        start_time: std::time::Instant::now(),
-    }
+    )
 }
 
 #[tokio::test]
@@ -42,6 +38,7 @@ async fn hybrid_query_combines_signals() -> Result<()> {
             ],
             embed: true,
             fts: true,
+            ..Default::default()
         })
         .await?;
 
@@ -53,6 +50,7 @@ async fn hybrid_query_combines_signals() -> Result<()> {
             top_k: 2,
             hybrid: true,
             filters: Vec::new(),
+            ..Default::default()
         })
         .await?;
     assert_eq!(response.results.len(), 2);
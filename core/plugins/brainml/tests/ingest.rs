@@ -0,0 +1,241 @@
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use brainml::adapters::braindb::NullBraindbClient;
+use brainml::adapters::llm::NullLlmClient;
+use brainml::api::AppState;
+use brainml::core::config::{BrainmlConfig, IngestConfig};
+use brainml::core::ingest::{chunk_text, FileFormat, IngestError};
+use brainml::core::schema::{DocumentField, IngestFile, IngestRequest, QueryRequest};
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Document, Object, Stream};
+use std::sync::Arc;
+
+fn state() -> AppState {
+    AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        Arc::new(NullLlmClient),
+        BrainmlConfig {
+            ingest: IngestConfig {
+                chunk_size: 80,
+                chunk_overlap: 10,
+                max_file_bytes: 64 * 1024,
+            },
+            ..Default::default()
+        },
+    )
+}
+
+fn file(path: &str, content: &[u8]) -> IngestFile {
+    IngestFile {
+        path: path.into(),
+        content_type: None,
+        data: STANDARD.encode(content),
+    }
+}
+
+fn ingest_request(files: Vec<IngestFile>) -> IngestRequest {
+    IngestRequest {
+        collection: "files".into(),
+        files,
+        metadata: serde_json::json!({"team": "search"}),
+        fts: true,
+        ..Default::default()
+    }
+}
+
+async fn search(state: &AppState, query: &str) -> Result<Vec<(String, serde_json::Value, String)>> {
+    let response = state
+        .process_query(QueryRequest {
+            collection: "files".into(),
+            query: Some(query.into()),
+            top_k: 20,
+            fields: vec![DocumentField::Text, DocumentField::Metadata],
+            ..Default::default()
+        })
+        .await?;
+    Ok(response
+        .results
+        .into_iter()
+        .map(|result| (result.id, result.document.metadata, result.document.text))
+        .collect())
+}
+
+fn pdf(title: &str, line: &str) -> Vec<u8> {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Courier",
+    });
+    let resources_id = doc.add_object(dictionary! {
+        "Font" => dictionary! { "F1" => font_id },
+    });
+    let content = Content {
+        operations: vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F1".into(), 12.into()]),
+            Operation::new("Td", vec![72.into(), 720.into()]),
+            Operation::new("Tj", vec![Object::string_literal(line)]),
+            Operation::new("ET", vec![]),
+        ],
+    };
+    let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "Contents" => content_id,
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+            "Resources" => resources_id,
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    let info_id = doc.add_object(dictionary! {
+        "Title" => Object::string_literal(title),
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.trailer.set("Info", info_id);
+    let mut bytes = Vec::new();
+    doc.save_to(&mut bytes).unwrap();
+    bytes
+}
+
+#[tokio::test]
+async fn markdown_and_html_are_extracted_with_metadata() -> Result<()> {
+    let state = state();
+    let markdown = b"# Release Notes\n\nThe *cursor* API ships today.\n\n- faster ingest\n";
+    let html = br#"<html><head><title>Ops Guide</title><script>var tracking = 1;</script></head>
+        <body><nav>Home | Pricing</nav><main><h1>Ops Guide</h1><p>Restart the watcher nightly.</p></main>
+        <footer>Copyright ACME</footer></body></html>"#;
+    let response = state
+        .process_ingest_for(
+            &state.default_tenant(),
+            ingest_request(vec![
+                file("docs/release.md", markdown),
+                file("site/ops.html", html),
+            ]),
+        )
+        .await?;
+    assert_eq!(response.files.len(), 2);
+    assert_eq!(response.files[0].format, FileFormat::Markdown);
+    assert_eq!(response.files[0].title.as_deref(), Some("Release Notes"));
+    assert_eq!(response.files[1].format, FileFormat::Html);
+    assert_eq!(response.files[1].title.as_deref(), Some("Ops Guide"));
+
+    let hits = search(&state, "cursor").await?;
+    assert_eq!(hits.len(), 1);
+    let (id, metadata, text) = &hits[0];
+    assert_eq!(id, "docs/release.md#0");
+    assert_eq!(metadata["title"], "Release Notes");
+    assert_eq!(metadata["path"], "docs/release.md");
+    assert_eq!(metadata["format"], "markdown");
+    assert_eq!(metadata["team"], "search");
+    assert!(text.contains("The cursor API ships today."));
+    assert!(!text.contains('*'));
+
+    let hits = search(&state, "watcher").await?;
+    assert_eq!(hits.len(), 1);
+    let text = &hits[0].2;
+    assert!(text.contains("Restart the watcher nightly."));
+    for boilerplate in ["Pricing", "Copyright", "tracking"] {
+        assert!(
+            !text.contains(boilerplate),
+            "{boilerplate} leaked into {text}"
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn source_language_is_detected() -> Result<()> {
+    let state = state();
+    let response = state
+        .process_ingest_for(
+            &state.default_tenant(),
+            ingest_request(vec![
+                file("src/lib.rs", b"pub fn answer() -> u32 { 42 }\n"),
+                file("bin/deploy", b"#!/usr/bin/env python3\nprint('deploy')\n"),
+                file("notes.txt", b"plain notes"),
+            ]),
+        )
+        .await?;
+    let languages: Vec<_> = response
+        .files
+        .iter()
+        .map(|file| (file.format, file.language.as_deref()))
+        .collect();
+    assert_eq!(
+        languages,
+        vec![
+            (FileFormat::Source, Some("rust")),
+            (FileFormat::Source, Some("python")),
+            (FileFormat::PlainText, None),
+        ]
+    );
+    let hits = search(&state, "answer").await?;
+    assert_eq!(hits[0].1["language"], "rust");
+    Ok(())
+}
+
+#[tokio::test]
+async fn pdf_text_and_title_are_extracted() -> Result<()> {
+    let state = state();
+    let response = state
+        .process_ingest_for(
+            &state.default_tenant(),
+            ingest_request(vec![file(
+                "reports/q3.pdf",
+                &pdf("Quarterly Report", "Revenue grew in the third quarter"),
+            )]),
+        )
+        .await?;
+    assert_eq!(response.files[0].format, FileFormat::Pdf);
+    assert_eq!(response.files[0].title.as_deref(), Some("Quarterly Report"));
+    let hits = search(&state, "revenue grew").await?;
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].1["title"], "Quarterly Report");
+    Ok(())
+}
+
+#[tokio::test]
+async fn long_files_are_chunked_and_limits_enforced() -> Result<()> {
+    let paragraph = "word ".repeat(40);
+    let chunks = chunk_text(&format!("short intro\n\n{paragraph}"), 80, 10);
+    assert!(chunks.len() > 2);
+    assert!(chunks.iter().all(|chunk| chunk.chars().count() <= 80));
+    assert!(chunks[0].starts_with("short intro"));
+
+    let state = state();
+    let response = state
+        .process_ingest_for(
+            &state.default_tenant(),
+            ingest_request(vec![file("long.txt", paragraph.as_bytes())]),
+        )
+        .await?;
+    assert_eq!(response.documents, response.files[0].chunks);
+    assert!(response.documents > 1);
+
+    let err = state
+        .process_ingest_for(
+            &state.default_tenant(),
+            ingest_request(vec![file("huge.txt", &vec![b'a'; 70 * 1024])]),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<IngestError>(),
+        Some(IngestError::TooLarge { .. })
+    ));
+    Ok(())
+}
@@ -3,7 +3,6 @@ use brainml::adapters::braindb::NullBraindbClient;
 use brainml::adapters::llm::NullLlmClient;
 use brainml::api::AppState;
 use brainml::core::config::BrainmlConfig;
-use brainml::core::pipeline::PipelineManager;
 use brainml::core::schema::{DocumentInput, IndexRequest, QueryRequest};
 use std::sync::Arc;
 
@@ -11,19 +10,16 @@ fn test_config() -> BrainmlConfig {
     BrainmlConfig {
         port: 43133,
         bus: "ws://127.0.0.1:43121".into(),
-        embedding_model: None,
-        collection_defaults: Default::default(),
+        ..Default::default()
     }
 }
 
 fn test_state() -> AppState {
-    AppState {
-        braindb: Arc::new(NullBraindbClient::default()),
-        llm: Arc::new(NullLlmClient::default()),
-        pipeline: PipelineManager::default(),
-        config: test_config(),
-        start_time: std::time::Instant::now(),
-    }
+    AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        Arc::new(NullLlmClient),
+        test_config(),
+    )
 }
 
 #[tokio::test]
@@ -50,6 +46,7 @@ async fn index_then_query_returns_results() -> Result<()> {
         ],
         embed: true,
         fts: true,
+        ..Default::default()
     };
     let response = state.process_index(request).await?;
     assert_eq!(response.results.len(), 0);
@@ -61,6 +58,7 @@ async fn index_then_query_returns_results() -> Result<()> {
         top_k: 3,
         hybrid: true,
         filters: Vec::new(),
+        ..Default::default()
     };
     let response = state.process_query(query).await?;
     assert!(!response.results.is_empty());
@@ -0,0 +1,110 @@
+use anyhow::Result;
+use brainml::core::config::{BrainmlConfigLoader, ConfigOverrides, ConfigSources};
+use std::process::Command;
+
+fn temp_yaml(contents: &str) -> Result<std::path::PathBuf> {
+    let path = std::env::temp_dir().join(format!("brainml-{}.yaml", uuid::Uuid::new_v4()));
+    std::fs::write(&path, contents)?;
+    Ok(path)
+}
+
+#[test]
+fn layers_apply_in_precedence_order() -> Result<()> {
+    let path = temp_yaml(
+        "port: 43300\nembedding_model: file-model\ncollection_defaults:\n  top_k: 20\n  rrf_k: 40\n",
+    )?;
+    std::env::set_var("BRAINML_EMBEDDING_MODEL", "env-model");
+    std::env::set_var("BRAINML_COLLECTION_DEFAULTS__RRF_K", "30");
+    std::env::set_var("PLUGIN_PORT", "43301");
+
+    let sources = ConfigSources {
+        file: Some(path.clone()),
+        file_required: true,
+        environment: true,
+        overrides: ConfigOverrides {
+            top_k: Some(5),
+            ..Default::default()
+        },
+    };
+    let config = BrainmlConfigLoader::load_layered(&sources)?;
+    assert_eq!(config.port, 43301);
+    assert_eq!(config.embedding_model.as_deref(), Some("env-model"));
+    assert_eq!(config.collection_defaults.rrf_k, 30);
+    assert_eq!(config.collection_defaults.top_k, 5);
+    assert_eq!(config.bus, "ws://127.0.0.1:43121");
+
+    std::env::set_var("BRAINML_PORT", "43302");
+    let config = BrainmlConfigLoader::load_layered(&sources)?;
+    assert_eq!(config.port, 43302);
+
+    for key in [
+        "BRAINML_EMBEDDING_MODEL",
+        "BRAINML_COLLECTION_DEFAULTS__RRF_K",
+        "PLUGIN_PORT",
+        "BRAINML_PORT",
+    ] {
+        std::env::remove_var(key);
+    }
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn print_config_reports_effective_values() -> Result<()> {
+    let path = temp_yaml("embedding_model: file-model\n")?;
+    let output = Command::new(env!("CARGO_BIN_EXE_brainml"))
+        .arg("--config")
+        .arg(&path)
+        .args(["--port", "43310", "--print-config"])
+        // `layers_apply_in_precedence_order` sets BRAINML_* and PLUGIN_PORT
+        // on this process while the tests run in parallel.
+        .env_clear()
+        .output()?;
+    assert!(output.status.success());
+    let printed: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(printed["port"], 43310);
+    assert_eq!(printed["embedding_model"], "file-model");
+    assert_eq!(printed["collection_defaults"]["top_k"], 10);
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn map_keys_keep_the_case_of_the_file() -> Result<()> {
+    let path = temp_yaml(
+        "\
+vector_fields:
+  titleVec: { model: title-model }
+query_rewrite:
+  synonyms:
+    HandBook: [[NYC, new york]]
+expiry:
+  ttl_secs: { HandBook: 60 }
+quantization:
+  collections:
+    BigDocs: { kind: int8 }
+tenancy:
+  tokens: { Blue-Token: TeamBlue }
+  quotas:
+    TeamBlue: { max_documents: 10 }
+",
+    )?;
+    let config = BrainmlConfigLoader::load(&path)?;
+    assert_eq!(config.vector_fields["titleVec"].model, "title-model");
+    assert_eq!(
+        config.query_rewrite.synonyms["HandBook"],
+        vec![vec!["NYC".to_string(), "new york".to_string()]]
+    );
+    assert_eq!(config.expiry.ttl_secs["HandBook"], 60);
+    assert!(config.quantization.collections.contains_key("BigDocs"));
+    assert_eq!(config.tenancy.tokens["Blue-Token"], "TeamBlue");
+    assert_eq!(config.tenancy.quotas["TeamBlue"].max_documents, Some(10));
+    assert!(!config.tenancy.tokens.contains_key("blue-token"));
+    std::fs::remove_file(&path)?;
+
+    let path = temp_yaml("tenancy:\n  tokens: { Token-A: blue, token-a: green }\n")?;
+    let err = BrainmlConfigLoader::load(&path).unwrap_err();
+    assert!(format!("{err:#}").contains("only differ in case"));
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use anyhow::Result;
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use brainml::adapters::braindb::NullBraindbClient;
+use brainml::adapters::llm::NullLlmClient;
+use brainml::api::limits::capability_for_path;
+use brainml::api::AppState;
+use brainml::core::config::{BrainmlConfig, LimitsConfig, RateLimit, TenancyConfig};
+use brainml::core::limits::{Admission, LimitError, Limiter};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceExt;
+
+fn limits(max_in_flight: usize, reserved_slots: usize) -> LimitsConfig {
+    LimitsConfig {
+        max_in_flight,
+        reserved_slots,
+        queue_timeout_ms: 50,
+        concurrency: BTreeMap::new(),
+        ..Default::default()
+    }
+}
+
+fn query_request(tenant: &str) -> Request<Body> {
+    Request::post("/api/v1/brainml/query")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {tenant}-token"))
+        .body(Body::from(r#"{"collection":"docs","query":"revenue"}"#))
+        .unwrap()
+}
+
+async fn wait_for_queue(limiter: &Limiter, waiting: usize) {
+    while limiter.stats().waiting < waiting {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+}
+
+#[tokio::test]
+async fn http_requests_over_the_rate_get_429_per_tenant() -> Result<()> {
+    let state = AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        Arc::new(NullLlmClient),
+        BrainmlConfig {
+            tenancy: TenancyConfig {
+                enabled: true,
+                tokens: ["red", "green", "blue"]
+                    .into_iter()
+                    .map(|tenant| (format!("{tenant}-token"), tenant.to_string()))
+                    .collect(),
+                ..Default::default()
+            },
+            limits: LimitsConfig {
+                rate: BTreeMap::from([(
+                    "query".to_string(),
+                    RateLimit {
+                        per_second: 0.5,
+                        burst: 2,
+                    },
+                )]),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    );
+    let router = brainml::api::router(state.clone());
+
+    for _ in 0..2 {
+        let response = router.clone().oneshot(query_request("red")).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = router.clone().oneshot(query_request("red")).await?;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()[header::RETRY_AFTER], "2");
+
+    // Buckets are per tenant, and unlisted routes are not limited.
+    let response = router.clone().oneshot(query_request("blue")).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = router
+        .oneshot(Request::get("/health/live").body(Body::empty())?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(state.admin_status().limits.unwrap().rate_limited, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn capability_concurrency_is_capped() -> Result<()> {
+    let limiter = Limiter::default();
+    let config = LimitsConfig {
+        concurrency: BTreeMap::from([("index".to_string(), 1)]),
+        ..limits(8, 0)
+    };
+    let first = limiter.acquire(&config, "t", "brainml.index").await?;
+    let rejected = limiter.acquire(&config, "t", "brainml.index").await;
+    assert!(matches!(rejected, Err(LimitError::Overloaded { .. })));
+    // Other capabilities still get through.
+    let _query = limiter.acquire(&config, "t", "brainml.query").await?;
+    assert_eq!(limiter.stats().in_flight["index"], 1);
+
+    drop(first);
+    limiter.acquire(&config, "t", "brainml.index").await?;
+    assert_eq!(limiter.stats().overloaded, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn reserved_slots_are_left_for_queries() -> Result<()> {
+    let limiter = Limiter::default();
+    let config = limits(2, 1);
+    let _index = limiter.acquire(&config, "t", "brainml.index").await?;
+    let rejected = limiter.acquire(&config, "t", "brainml.ingest").await;
+    assert!(matches!(rejected, Err(LimitError::Overloaded { .. })));
+    let _query = limiter.acquire(&config, "t", "brainml.query").await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn queued_queries_are_admitted_before_writes() -> Result<()> {
+    let limiter = Limiter::default();
+    let config = LimitsConfig {
+        queue_timeout_ms: 5_000,
+        ..limits(1, 0)
+    };
+    let held = limiter.acquire(&config, "t", "brainml.index").await?;
+
+    let index = tokio::spawn({
+        let (limiter, config) = (limiter.clone(), config.clone());
+        async move { limiter.acquire(&config, "t", "brainml.index").await }
+    });
+    wait_for_queue(&limiter, 1).await;
+    let query = tokio::spawn({
+        let (limiter, config) = (limiter.clone(), config.clone());
+        async move { limiter.acquire(&config, "t", "brainml.query").await }
+    });
+    wait_for_queue(&limiter, 2).await;
+
+    drop(held);
+    let query = query.await??;
+    assert_eq!(
+        limiter.stats().in_flight.keys().collect::<Vec<_>>(),
+        vec!["query"]
+    );
+    assert!(!index.is_finished());
+    drop(query);
+    index.await??;
+    Ok(())
+}
+
+#[test]
+fn http_paths_map_to_capabilities() {
+    assert_eq!(
+        capability_for_path("/api/v1/brainml/query"),
+        Some("brainml.query")
+    );
+    assert_eq!(
+        capability_for_path("/api/v1/brainml/admin/status"),
+        Some("brainml.admin")
+    );
+    assert_eq!(
+        capability_for_path("/api/v1/brainml/analytics/report"),
+        Some("brainml.analytics")
+    );
+    assert_eq!(
+        capability_for_path("/api/v1/brainml/audit"),
+        Some("brainml.audit")
+    );
+    assert_eq!(capability_for_path("/health/ready"), None);
+    assert_eq!(capability_for_path("/api-docs/openapi.json"), None);
+}
+
+#[tokio::test]
+async fn requests_past_the_queue_cap_are_rejected_without_waiting() -> Result<()> {
+    let limiter = Limiter::default();
+    let config = LimitsConfig {
+        queue_timeout_ms: 5_000,
+        max_queued: 1,
+        ..limits(1, 0)
+    };
+    let held = limiter.acquire(&config, "t", "brainml.index").await?;
+
+    let queued = limiter.try_acquire(&config, "t", "brainml.index")?;
+    assert!(matches!(queued, Admission::Queued(_)));
+    assert_eq!(limiter.stats().waiting, 1);
+    let rejected = limiter.try_acquire(&config, "t", "brainml.ingest");
+    assert!(matches!(rejected, Err(LimitError::Overloaded { .. })));
+    assert_eq!(limiter.stats().overloaded, 1);
+
+    drop(held);
+    let permit = queued.permit().await?;
+    assert_eq!(limiter.stats().waiting, 0);
+    drop(permit);
+    assert!(matches!(
+        limiter.try_acquire(&config, "t", "brainml.index")?,
+        Admission::Admitted(_)
+    ));
+    Ok(())
+}
@@ -0,0 +1,228 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use brainml::adapters::braindb::{
+    BraindbClient, NullBraindbClient, ScanDocumentsRequest, UpsertDocumentsRequest,
+};
+use brainml::adapters::llm::{
+    EmbeddingRequest, EmbeddingVector, LlmClient, LlmResult, NullLlmClient,
+};
+use brainml::api::AppState;
+use brainml::core::config::{BrainmlConfig, QueryCacheConfig, TenancyConfig};
+use brainml::core::schema::{DocumentInput, IndexRequest, MigrationState, QueryRequest};
+use brainml::core::tenancy::TenantContext;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+fn config(model: &str) -> BrainmlConfig {
+    BrainmlConfig {
+        port: 43133,
+        bus: "ws://127.0.0.1:43121".into(),
+        embedding_model: Some(model.into()),
+        ..Default::default()
+    }
+}
+
+fn index_request(collection: &str, count: usize) -> IndexRequest {
+    IndexRequest {
+        collection: collection.into(),
+        documents: (0..count)
+            .map(|idx| DocumentInput {
+                id: Some(format!("doc-{idx}")),
+                text: format!("document number {idx}"),
+                metadata: serde_json::json!({}),
+            })
+            .collect(),
+        embed: true,
+        ..Default::default()
+    }
+}
+
+async fn scan(braindb: &NullBraindbClient) -> Result<Vec<brainml::core::schema::DocumentRecord>> {
+    scan_collection(braindb, "kb").await
+}
+
+async fn scan_collection(
+    braindb: &NullBraindbClient,
+    collection: &str,
+) -> Result<Vec<brainml::core::schema::DocumentRecord>> {
+    Ok(braindb
+        .scan_documents(ScanDocumentsRequest {
+            collection: collection.into(),
+            offset: 0,
+            limit: 100,
+        })
+        .await?)
+}
+
+#[tokio::test]
+async fn migrator_reembeds_records_from_previous_model() -> Result<()> {
+    let braindb = Arc::new(NullBraindbClient::default());
+    let before = AppState::new(braindb.clone(), Arc::new(NullLlmClient), config("model-a"));
+    before.process_index(index_request("kb", 3)).await?;
+    let stored = scan(&braindb).await?;
+    assert!(stored
+        .iter()
+        .all(|record| record.embedding_model.as_deref() == Some("model-a")));
+
+    let after = AppState::new(braindb.clone(), Arc::new(NullLlmClient), config("model-b"));
+    let stale_vector = stored[0].embedding.clone();
+    let response = after
+        .process_query(QueryRequest {
+            collection: "kb".into(),
+            vector: stale_vector,
+            ..Default::default()
+        })
+        .await?;
+    assert!(response.results.is_empty(), "stale vectors must not match");
+
+    let progress = after
+        .migrator
+        .migrate_all(
+            braindb.as_ref(),
+            &NullLlmClient,
+            &after.cache,
+            &after.config.load(),
+            &after.default_tenant(),
+            2,
+        )
+        .await?;
+    assert_eq!(progress.len(), 1);
+    assert_eq!(progress[0].state, MigrationState::Completed);
+    assert_eq!(progress[0].scanned, 3);
+    assert_eq!(progress[0].migrated, 3);
+
+    let migrated = scan(&braindb).await?;
+    assert!(migrated
+        .iter()
+        .all(|record| record.embedding_model.as_deref() == Some("model-b")));
+    assert_ne!(migrated[0].embedding, stored[0].embedding);
+    // Re-embedding is not a write: TTLs and recency boosts stay as they were.
+    assert!(migrated
+        .iter()
+        .zip(&stored)
+        .all(|(after, before)| after.updated_at == before.updated_at));
+
+    let status = after.admin_status();
+    assert_eq!(status.migrations[0].migrated, 3);
+    Ok(())
+}
+
+/// Edits `doc-0` while the migrator is embedding, as a concurrent index
+/// request would.
+struct EditingLlm {
+    braindb: Arc<NullBraindbClient>,
+    edited: AtomicBool,
+}
+
+#[async_trait]
+impl LlmClient for EditingLlm {
+    async fn embed(&self, request: EmbeddingRequest) -> LlmResult<Vec<EmbeddingVector>> {
+        if !self.edited.swap(true, Ordering::SeqCst) {
+            let mut record = scan(&self.braindb).await.unwrap().remove(0);
+            // Writers need not touch `updated_at` for the edit to be seen.
+            record.text = "edited during the migration".into();
+            self.braindb
+                .upsert_documents(UpsertDocumentsRequest {
+                    collection: "kb".into(),
+                    documents: vec![record],
+                })
+                .await
+                .unwrap();
+        }
+        NullLlmClient.embed(request).await
+    }
+}
+
+#[tokio::test]
+async fn records_written_during_a_batch_are_not_overwritten() -> Result<()> {
+    let braindb = Arc::new(NullBraindbClient::default());
+    AppState::new(braindb.clone(), Arc::new(NullLlmClient), config("model-a"))
+        .process_index(index_request("kb", 3))
+        .await?;
+    let after = AppState::new(braindb.clone(), Arc::new(NullLlmClient), config("model-b"));
+    let llm = EditingLlm {
+        braindb: braindb.clone(),
+        edited: AtomicBool::new(false),
+    };
+    let progress = after
+        .migrator
+        .migrate_all(
+            braindb.as_ref(),
+            &llm,
+            &after.cache,
+            &after.config.load(),
+            &after.default_tenant(),
+            10,
+        )
+        .await?;
+    assert_eq!(progress[0].scanned, 3);
+    assert_eq!(progress[0].migrated, 2);
+
+    let records = scan(&braindb).await?;
+    let edited = records.iter().find(|record| record.id == "doc-0").unwrap();
+    assert_eq!(edited.text, "edited during the migration");
+    assert_eq!(edited.embedding_model.as_deref(), Some("model-a"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn tenants_migrate_their_own_collections_and_drop_cached_results() -> Result<()> {
+    let tenancy = |model: &str| BrainmlConfig {
+        tenancy: TenancyConfig {
+            enabled: true,
+            tokens: HashMap::from([
+                ("blue-token".to_string(), "blue".to_string()),
+                ("red-token".to_string(), "red".to_string()),
+            ]),
+            ..Default::default()
+        },
+        query_cache: QueryCacheConfig {
+            enabled: true,
+            ..Default::default()
+        },
+        ..config(model)
+    };
+    let braindb = Arc::new(NullBraindbClient::default());
+    let before = AppState::new(braindb.clone(), Arc::new(NullLlmClient), tenancy("model-a"));
+    let after = AppState::new(braindb.clone(), Arc::new(NullLlmClient), tenancy("model-b"));
+    let blue = TenantContext::named(&before.config.load().tenancy, "blue")?;
+    let red = TenantContext::named(&before.config.load().tenancy, "red")?;
+    before
+        .process_index_for(&blue, index_request("kb", 2))
+        .await?;
+    before
+        .process_index_for(&red, index_request("kb", 2))
+        .await?;
+    after
+        .process_query_for(
+            &blue,
+            QueryRequest {
+                collection: "kb".into(),
+                query: Some("document".into()),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert_eq!(after.cache.stats().entries, 1);
+
+    let progress = after
+        .migrator
+        .migrate_all(
+            braindb.as_ref(),
+            &NullLlmClient,
+            &after.cache,
+            &after.config.load(),
+            &blue,
+            10,
+        )
+        .await?;
+    let collections: Vec<_> = progress.iter().map(|p| p.collection.as_str()).collect();
+    assert_eq!(collections, vec!["blue::kb"]);
+    assert_eq!(after.cache.stats().entries, 0);
+    assert!(scan_collection(&braindb, "red::kb")
+        .await?
+        .iter()
+        .all(|record| record.embedding_model.as_deref() == Some("model-a")));
+    Ok(())
+}
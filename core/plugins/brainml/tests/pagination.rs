@@ -0,0 +1,138 @@
+use anyhow::Result;
+use brainml::adapters::braindb::NullBraindbClient;
+use brainml::adapters::llm::NullLlmClient;
+use brainml::api::AppState;
+use brainml::core::config::BrainmlConfig;
+use brainml::core::pagination::PaginationError;
+use brainml::core::schema::{
+    DocumentInput, IndexRequest, QueryRequest, TotalHits, TotalHitsRelation,
+};
+use std::sync::Arc;
+
+fn state() -> AppState {
+    AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        Arc::new(NullLlmClient),
+        BrainmlConfig::default(),
+    )
+}
+
+async fn index(state: &AppState, ids: &[&str]) -> Result<()> {
+    state
+        .process_index(IndexRequest {
+            collection: "pages".into(),
+            documents: ids
+                .iter()
+                .map(|id| DocumentInput {
+                    id: Some(id.to_string()),
+                    text: format!("page content {id}"),
+                    metadata: serde_json::json!({}),
+                })
+                .collect(),
+            fts: true,
+            ..Default::default()
+        })
+        .await?;
+    Ok(())
+}
+
+fn page(top_k: usize, offset: usize, cursor: Option<String>) -> QueryRequest {
+    QueryRequest {
+        collection: "pages".into(),
+        query: Some("page".into()),
+        top_k,
+        offset,
+        cursor,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn offset_pages_are_stable_and_report_totals() -> Result<()> {
+    let state = state();
+    index(&state, &["doc-5", "doc-3", "doc-1", "doc-4", "doc-2"]).await?;
+
+    let first = state.process_query(page(2, 0, None)).await?;
+    let second = state.process_query(page(2, 2, None)).await?;
+    let ids: Vec<_> = first
+        .results
+        .iter()
+        .chain(second.results.iter())
+        .map(|result| result.id.as_str())
+        .collect();
+    assert_eq!(ids, vec!["doc-1", "doc-2", "doc-3", "doc-4"]);
+    assert_eq!(second.results[0].rank, 3);
+    assert_eq!(
+        first.total_hits,
+        TotalHits {
+            value: 2,
+            relation: TotalHitsRelation::Gte,
+        }
+    );
+
+    let all = state.process_query(page(10, 0, None)).await?;
+    assert_eq!(
+        all.total_hits,
+        TotalHits {
+            value: 5,
+            relation: TotalHitsRelation::Eq,
+        }
+    );
+    assert!(all.next_cursor.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn cursors_survive_concurrent_upserts() -> Result<()> {
+    let state = state();
+    index(&state, &["doc-2", "doc-3", "doc-4", "doc-5", "doc-6"]).await?;
+
+    let first = state.process_query(page(2, 0, None)).await?;
+    let ids: Vec<_> = first.results.iter().map(|r| r.id.clone()).collect();
+    assert_eq!(ids, vec!["doc-2", "doc-3"]);
+
+    // Sorts ahead of the cursor; with offsets it would shift every page.
+    index(&state, &["doc-1"]).await?;
+
+    let mut seen = ids;
+    let mut cursor = first.next_cursor;
+    while let Some(next) = cursor {
+        let response = state.process_query(page(2, 0, Some(next))).await?;
+        seen.extend(response.results.iter().map(|r| r.id.clone()));
+        cursor = response.next_cursor;
+    }
+    assert_eq!(seen, vec!["doc-2", "doc-3", "doc-4", "doc-5", "doc-6"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_pagination_is_rejected() -> Result<()> {
+    let state = state();
+    index(&state, &["doc-1", "doc-2", "doc-3"]).await?;
+
+    let err = state
+        .process_query(page(2, 0, Some("not a cursor".into())))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<PaginationError>(),
+        Some(PaginationError::InvalidCursor(_))
+    ));
+
+    let first = state.process_query(page(1, 0, None)).await?;
+    let err = state
+        .process_query(page(1, 1, first.next_cursor))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<PaginationError>(),
+        Some(PaginationError::OffsetWithCursor)
+    ));
+
+    let err = state.process_query(page(10, 995, None)).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<PaginationError>(),
+        Some(PaginationError::WindowTooLarge { .. })
+    ));
+    Ok(())
+}
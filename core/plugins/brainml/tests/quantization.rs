@@ -0,0 +1,239 @@
+use anyhow::Result;
+use brainml::adapters::braindb::{
+    BraindbClient, HybridQueryRequest, NullBraindbClient, ScanDocumentsRequest,
+    UpsertDocumentsRequest,
+};
+use brainml::adapters::llm::NullLlmClient;
+use brainml::api::AppState;
+use brainml::core::config::{BrainmlConfig, QuantizationConfig, QuantizationSettings};
+use brainml::core::quantization::{Quantization, QuantizedVector};
+use brainml::core::retriever::ensure_collection;
+use brainml::core::schema::{
+    DocumentInput, DocumentRecord, IndexRequest, QueryResult, QueryStrategy,
+};
+use brainml::core::tenancy::TenantContext;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+const DIMS: usize = 96;
+
+/// Deterministic values in [-1, 1).
+fn vectors(seed: u64, count: usize) -> Vec<Vec<f32>> {
+    let mut state = seed;
+    let mut next = move || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((state >> 40) as f32 / (1u64 << 24) as f32) * 2.0 - 1.0
+    };
+    (0..count)
+        .map(|_| (0..DIMS).map(|_| next()).collect())
+        .collect()
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+fn settings(kind: Quantization, rescore: bool, oversampling: usize) -> QuantizationSettings {
+    QuantizationSettings {
+        kind,
+        rescore,
+        oversampling,
+    }
+}
+
+async fn collection(settings: &QuantizationSettings) -> Result<NullBraindbClient> {
+    let client = NullBraindbClient::default();
+    ensure_collection(&client, "docs", settings).await?;
+    let documents = vectors(7, 60)
+        .into_iter()
+        .enumerate()
+        .map(|(idx, embedding)| {
+            DocumentRecord::new(
+                DocumentInput {
+                    id: Some(format!("doc-{idx:02}")),
+                    text: format!("document {idx}"),
+                    metadata: serde_json::json!({}),
+                },
+                Some(embedding),
+            )
+        })
+        .collect();
+    client
+        .upsert_documents(UpsertDocumentsRequest {
+            collection: "docs".into(),
+            documents,
+        })
+        .await?;
+    Ok(client)
+}
+
+async fn search(client: &NullBraindbClient, vector: &[f32]) -> Result<Vec<QueryResult>> {
+    Ok(client
+        .hybrid_query(HybridQueryRequest {
+            collection: "docs".into(),
+            query: None,
+            vector: Some(vector.to_vec()),
+            top_k: 5,
+            strategy: QueryStrategy::Vector,
+            filters: Vec::new(),
+            vector_field: None,
+            embedding_model: None,
+            date_range: None,
+        })
+        .await?)
+}
+
+fn ranking(results: &[QueryResult]) -> Vec<(String, f32)> {
+    results
+        .iter()
+        .map(|result| (result.id.clone(), result.score))
+        .collect()
+}
+
+#[test]
+fn codes_approximate_the_original_values() {
+    let values = &vectors(1, 1)[0];
+    let query = &vectors(2, 1)[0];
+
+    let int8 = QuantizedVector::encode(Quantization::Int8, values).unwrap();
+    let QuantizedVector::Int8 { scale, .. } = int8 else {
+        unreachable!()
+    };
+    let decoded = int8.decode();
+    assert_eq!(decoded.len(), DIMS);
+    for (original, decoded) in values.iter().zip(&decoded) {
+        assert!((original - decoded).abs() <= scale / 2.0 + 1e-6);
+    }
+    assert!((int8.dot(query) - dot(&decoded, query)).abs() < 1e-3);
+    assert!((int8.dot(query) - dot(values, query)).abs() < 0.05);
+    assert_eq!(int8.size_bytes(), DIMS + 8);
+
+    let binary = QuantizedVector::encode(Quantization::Binary, values).unwrap();
+    let decoded = binary.decode();
+    assert_eq!(binary.dimensions(), DIMS);
+    for (original, decoded) in values.iter().zip(&decoded) {
+        assert_eq!(*original > 0.0, *decoded > 0.0);
+    }
+    assert!((binary.dot(query) - dot(&decoded, query)).abs() < 1e-3);
+    assert_eq!(binary.size_bytes(), 2 * 8 + 4);
+
+    assert_eq!(QuantizedVector::encode(Quantization::None, values), None);
+    let flat = QuantizedVector::encode(Quantization::Int8, &[0.5; 4]).unwrap();
+    assert_eq!(flat.decode(), vec![0.5; 4]);
+}
+
+#[tokio::test]
+async fn rescoring_restores_full_precision_ranking() -> Result<()> {
+    let query = &vectors(99, 1)[0];
+    let plain = search(&collection(&QuantizationSettings::default()).await?, query).await?;
+    assert_eq!(plain.len(), 5);
+
+    let int8 = collection(&settings(Quantization::Int8, true, 4)).await?;
+    assert_eq!(ranking(&search(&int8, query).await?), ranking(&plain));
+
+    // Rescoring every candidate makes even binary codes exact.
+    let binary = collection(&settings(Quantization::Binary, true, 12)).await?;
+    let results = search(&binary, query).await?;
+    assert_eq!(ranking(&results), ranking(&plain));
+    assert_eq!(results[0].document.embedding, plain[0].document.embedding);
+
+    // Without rescoring scores come from the codes and stored embeddings
+    // are returned decoded.
+    let approximate = collection(&settings(Quantization::Binary, false, 4)).await?;
+    let results = search(&approximate, query).await?;
+    assert_eq!(results.len(), 5);
+    let embedding = results[0].document.embedding.clone().unwrap();
+    assert!((results[0].score - dot(&embedding, query)).abs() < 1e-3);
+    assert_ne!(ranking(&results), ranking(&plain));
+    Ok(())
+}
+
+#[tokio::test]
+async fn reconfiguring_reencodes_stored_documents() -> Result<()> {
+    let client = collection(&settings(Quantization::Int8, true, 4)).await?;
+    let before = client.stats().await?.collections[0].clone();
+    ensure_collection(&client, "docs", &QuantizationSettings::default()).await?;
+    let after = client.stats().await?.collections[0].clone();
+    assert_eq!(before.quantization, Quantization::Int8);
+    assert_eq!(after.quantization, Quantization::None);
+    assert_eq!(after.vector_bytes, 60 * DIMS as u64 * 4);
+    assert_eq!(
+        before.vector_bytes,
+        after.vector_bytes + 60 * (DIMS as u64 + 8)
+    );
+
+    let scanned = client
+        .scan_documents(ScanDocumentsRequest {
+            collection: "docs".into(),
+            offset: 0,
+            limit: 1,
+        })
+        .await?;
+    assert_eq!(scanned[0].embedding.as_ref(), Some(&vectors(7, 1)[0]));
+    Ok(())
+}
+
+#[tokio::test]
+async fn stats_report_memory_per_collection() -> Result<()> {
+    let state = AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        Arc::new(NullLlmClient),
+        BrainmlConfig {
+            quantization: QuantizationConfig {
+                collections: BTreeMap::from([
+                    ("int8".into(), settings(Quantization::Int8, false, 4)),
+                    ("binary".into(), settings(Quantization::Binary, false, 4)),
+                ]),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    );
+    for collection in ["plain", "int8", "binary"] {
+        state
+            .process_index(IndexRequest {
+                collection: collection.into(),
+                documents: (0..4)
+                    .map(|idx| DocumentInput {
+                        id: Some(format!("doc-{idx}")),
+                        text: format!("quantized document {idx}"),
+                        metadata: serde_json::json!({}),
+                    })
+                    .collect(),
+                embed: true,
+                ..Default::default()
+            })
+            .await?;
+    }
+
+    let config = state.config.load().tenancy.clone();
+    let stats = state
+        .process_stats_for(&TenantContext::default_tenant(&config))
+        .await?;
+    let by_name = |name: &str| {
+        stats
+            .collections
+            .iter()
+            .find(|stats| stats.name == name)
+            .unwrap()
+            .clone()
+    };
+    let (plain, int8, binary) = (by_name("plain"), by_name("int8"), by_name("binary"));
+    assert_eq!(
+        [plain.quantization, int8.quantization, binary.quantization],
+        [Quantization::None, Quantization::Int8, Quantization::Binary]
+    );
+    let dims = plain.embedding_dimensions.unwrap();
+    assert_eq!(int8.embedding_dimensions, Some(dims));
+    assert_eq!(plain.vector_bytes, 4 * dims as u64 * 4);
+    assert_eq!(int8.vector_bytes, 4 * (dims as u64 + 8));
+    assert!(binary.vector_bytes < int8.vector_bytes);
+    assert!(plain.memory_bytes > plain.vector_bytes);
+    assert_eq!(
+        plain.memory_bytes - plain.vector_bytes,
+        int8.memory_bytes - int8.vector_bytes
+    );
+    Ok(())
+}
@@ -0,0 +1,218 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use brainml::adapters::braindb::NullBraindbClient;
+use brainml::adapters::llm::{EmbeddingRequest, EmbeddingVector, LlmClient, LlmResult};
+use brainml::api::AppState;
+use brainml::core::config::{BrainmlConfig, QueryCacheConfig};
+use brainml::core::schema::{
+    CacheStats, DocumentInput, HighlightOptions, IndexRequest, QueryRequest,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+const KEYWORDS: [&str; 2] = ["revenue", "users"];
+
+/// Embeds text as keyword counts and counts the texts it was asked for.
+#[derive(Default)]
+struct CountingLlmClient {
+    embedded: AtomicUsize,
+}
+
+#[async_trait]
+impl LlmClient for CountingLlmClient {
+    async fn embed(&self, request: EmbeddingRequest) -> LlmResult<Vec<EmbeddingVector>> {
+        self.embedded
+            .fetch_add(request.input.len(), Ordering::SeqCst);
+        Ok(request
+            .input
+            .iter()
+            .map(|text| EmbeddingVector {
+                embedding: KEYWORDS
+                    .iter()
+                    .map(|keyword| text.to_lowercase().matches(keyword).count() as f32)
+                    .collect(),
+            })
+            .collect())
+    }
+}
+
+fn state(llm: Arc<CountingLlmClient>, query_cache: QueryCacheConfig) -> AppState {
+    AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        llm,
+        BrainmlConfig {
+            query_cache,
+            ..Default::default()
+        },
+    )
+}
+
+fn enabled() -> QueryCacheConfig {
+    QueryCacheConfig {
+        enabled: true,
+        ..Default::default()
+    }
+}
+
+async fn index(state: &AppState, collection: &str, id: &str, text: &str) -> Result<()> {
+    state
+        .process_index(IndexRequest {
+            collection: collection.into(),
+            documents: vec![DocumentInput {
+                id: Some(id.into()),
+                text: text.into(),
+                metadata: serde_json::json!({}),
+            }],
+            embed: true,
+            fts: true,
+            ..Default::default()
+        })
+        .await?;
+    Ok(())
+}
+
+fn query(text: &str) -> QueryRequest {
+    QueryRequest {
+        collection: "metrics".into(),
+        query: Some(text.into()),
+        hybrid: true,
+        ..Default::default()
+    }
+}
+
+async fn result_ids(state: &AppState, text: &str) -> Result<Vec<String>> {
+    let response = state.process_query(query(text)).await?;
+    let mut ids: Vec<_> = response.results.into_iter().map(|r| r.id).collect();
+    ids.sort();
+    Ok(ids)
+}
+
+#[tokio::test]
+async fn repeated_queries_skip_embedding_and_retrieval() -> Result<()> {
+    let llm = Arc::new(CountingLlmClient::default());
+    let state = state(llm.clone(), enabled());
+    index(&state, "metrics", "q1", "revenue q1").await?;
+    let embedded = llm.embedded.load(Ordering::SeqCst);
+
+    assert_eq!(result_ids(&state, "revenue").await?, vec!["q1"]);
+    assert_eq!(result_ids(&state, "  Revenue ").await?, vec!["q1"]);
+    assert_eq!(llm.embedded.load(Ordering::SeqCst), embedded + 1);
+    assert_eq!(
+        state.admin_status().query_cache,
+        CacheStats {
+            entries: 1,
+            hits: 1,
+            semantic_hits: 0,
+            misses: 1,
+        }
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn upserts_invalidate_only_their_collection() -> Result<()> {
+    let llm = Arc::new(CountingLlmClient::default());
+    let state = state(llm, enabled());
+    index(&state, "metrics", "q1", "revenue q1").await?;
+    assert_eq!(result_ids(&state, "revenue").await?, vec!["q1"]);
+
+    index(&state, "other", "x", "revenue elsewhere").await?;
+    assert_eq!(state.admin_status().query_cache.entries, 1);
+
+    index(&state, "metrics", "q2", "revenue q2").await?;
+    assert_eq!(state.admin_status().query_cache.entries, 0);
+    assert_eq!(result_ids(&state, "revenue").await?, vec!["q1", "q2"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn semantic_mode_reuses_results_for_similar_queries() -> Result<()> {
+    let llm = Arc::new(CountingLlmClient::default());
+    let state = state(
+        llm.clone(),
+        QueryCacheConfig {
+            semantic: true,
+            similarity_threshold: 0.9,
+            ..enabled()
+        },
+    );
+    index(&state, "metrics", "q1", "revenue report").await?;
+    index(&state, "metrics", "u1", "users report").await?;
+
+    assert_eq!(result_ids(&state, "revenue report").await?, vec!["q1"]);
+    // Same embedding, different wording: served from the cache even though a
+    // fresh full-text match would find nothing.
+    assert_eq!(result_ids(&state, "report revenue").await?, vec!["q1"]);
+    assert_eq!(state.admin_status().query_cache.semantic_hits, 1);
+
+    // Orthogonal embedding: a miss.
+    assert_eq!(result_ids(&state, "users").await?, vec!["u1"]);
+    assert_eq!(state.admin_status().query_cache.misses, 2);
+
+    // A different request shape never matches semantically.
+    state
+        .process_query(QueryRequest {
+            top_k: 1,
+            ..query("revenue")
+        })
+        .await?;
+    assert_eq!(state.admin_status().query_cache.semantic_hits, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn highlighted_and_debug_queries_are_not_reused_semantically() -> Result<()> {
+    let llm = Arc::new(CountingLlmClient::default());
+    let state = state(
+        llm,
+        QueryCacheConfig {
+            semantic: true,
+            similarity_threshold: 0.9,
+            ..enabled()
+        },
+    );
+    index(&state, "metrics", "q1", "revenue report").await?;
+    let highlighted = |text: &str| QueryRequest {
+        highlight: HighlightOptions {
+            enabled: true,
+            ..Default::default()
+        },
+        ..query(text)
+    };
+    let debug = |text: &str| QueryRequest {
+        debug: true,
+        ..query(text)
+    };
+
+    state.process_query(highlighted("revenue report")).await?;
+    state.process_query(highlighted("report revenue")).await?;
+    assert_eq!(state.admin_status().query_cache.semantic_hits, 0);
+
+    state.process_query(debug("revenue report")).await?;
+    let response = state.process_query(debug("report revenue")).await?;
+    let rewritten = response.debug.and_then(|debug| debug.rewritten_query);
+    assert_eq!(rewritten.as_deref(), Some("report revenue"));
+    assert_eq!(state.admin_status().query_cache.semantic_hits, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn least_recently_used_entries_are_evicted() -> Result<()> {
+    let llm = Arc::new(CountingLlmClient::default());
+    let state = state(
+        llm,
+        QueryCacheConfig {
+            max_entries: 1,
+            ..enabled()
+        },
+    );
+    index(&state, "metrics", "q1", "revenue users").await?;
+    result_ids(&state, "revenue").await?;
+    result_ids(&state, "users").await?;
+    result_ids(&state, "revenue").await?;
+    let stats = state.admin_status().query_cache;
+    assert_eq!(stats.entries, 1);
+    assert_eq!(stats.hits, 0);
+    assert_eq!(stats.misses, 3);
+    Ok(())
+}
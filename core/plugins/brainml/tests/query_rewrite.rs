@@ -0,0 +1,205 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use brainml::adapters::braindb::NullBraindbClient;
+use brainml::adapters::llm::{
+    ChatChoice, ChatMessage, ChatRequest, ChatResponse, EmbeddingRequest, EmbeddingVector,
+    LlmClient, LlmResult, NullLlmClient,
+};
+use brainml::api::AppState;
+use brainml::core::config::{BrainmlConfig, QueryRewriteConfig};
+use brainml::core::rewrite::{edit_distance, parse_expansions};
+use brainml::core::schema::{DocumentInput, IndexRequest, QueryRequest, TermCorrection};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Answers every chat request with a fixed list of phrasings.
+struct ExpandingLlmClient;
+
+#[async_trait]
+impl LlmClient for ExpandingLlmClient {
+    async fn embed(&self, request: EmbeddingRequest) -> LlmResult<Vec<EmbeddingVector>> {
+        Ok(request
+            .input
+            .iter()
+            .map(|_| EmbeddingVector {
+                embedding: vec![0.0; 4],
+            })
+            .collect())
+    }
+
+    async fn chat(&self, request: ChatRequest) -> LlmResult<ChatResponse> {
+        assert_eq!(request.messages.last().unwrap().content, "fix my car");
+        Ok(ChatResponse {
+            choices: vec![ChatChoice {
+                message: ChatMessage {
+                    role: "assistant".into(),
+                    content: "1. vehicle maintenance\n- \"auto repair\"\n\n3) vehicle maintenance"
+                        .into(),
+                },
+            }],
+        })
+    }
+}
+
+fn state(llm: Arc<dyn LlmClient>, query_rewrite: QueryRewriteConfig) -> AppState {
+    AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        llm,
+        BrainmlConfig {
+            query_rewrite,
+            ..Default::default()
+        },
+    )
+}
+
+async fn index(state: &AppState, texts: &[(&str, &str)]) -> Result<()> {
+    state
+        .process_index(IndexRequest {
+            collection: "docs".into(),
+            documents: texts
+                .iter()
+                .map(|(id, text)| DocumentInput {
+                    id: Some(id.to_string()),
+                    text: text.to_string(),
+                    metadata: serde_json::json!({}),
+                })
+                .collect(),
+            fts: true,
+            ..Default::default()
+        })
+        .await?;
+    Ok(())
+}
+
+async fn search(state: &AppState, query: &str) -> Result<brainml::core::schema::QueryResponse> {
+    state
+        .process_query(QueryRequest {
+            collection: "docs".into(),
+            query: Some(query.into()),
+            debug: true,
+            ..Default::default()
+        })
+        .await
+}
+
+fn ids(response: &brainml::core::schema::QueryResponse) -> Vec<&str> {
+    let mut ids: Vec<_> = response.results.iter().map(|r| r.id.as_str()).collect();
+    ids.sort();
+    ids
+}
+
+#[tokio::test]
+async fn synonyms_add_query_variants() -> Result<()> {
+    let config = QueryRewriteConfig {
+        synonyms: BTreeMap::from([(
+            "docs".to_string(),
+            vec![vec!["car".to_string(), "automobile".to_string()]],
+        )]),
+        ..Default::default()
+    };
+    let state = state(Arc::new(NullLlmClient), config);
+    index(
+        &state,
+        &[
+            ("manual", "automobile repair manual"),
+            ("dealer", "used car dealership"),
+            ("bike", "bicycle repair"),
+        ],
+    )
+    .await?;
+
+    let response = search(&state, "car").await?;
+    assert_eq!(ids(&response), vec!["dealer", "manual"]);
+    let debug = response.debug.expect("debug output");
+    assert_eq!(debug.variants, vec!["car", "automobile"]);
+
+    // Synonym groups only apply to the collection they are listed under.
+    let response = state
+        .process_query(QueryRequest {
+            collection: "other".into(),
+            query: Some("car".into()),
+            debug: true,
+            ..Default::default()
+        })
+        .await?;
+    assert_eq!(response.debug.unwrap().variants, vec!["car"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn misspelled_terms_are_corrected_against_the_index() -> Result<()> {
+    let mut config = QueryRewriteConfig::default();
+    config.typo_tolerance.enabled = true;
+    let state = state(Arc::new(NullLlmClient), config);
+    index(&state, &[("k8s", "kubernetes deployment guide")]).await?;
+
+    let response = search(&state, "Kubernets deployment").await?;
+    assert_eq!(ids(&response), vec!["k8s"]);
+    let debug = response.debug.expect("debug output");
+    assert_eq!(debug.query.as_deref(), Some("Kubernets deployment"));
+    assert_eq!(
+        debug.rewritten_query.as_deref(),
+        Some("kubernetes deployment")
+    );
+    assert_eq!(
+        debug.corrections,
+        vec![TermCorrection {
+            term: "kubernets".into(),
+            correction: "kubernetes".into(),
+        }]
+    );
+
+    // The dictionary is rebuilt after writes, and short words stay as typed.
+    index(&state, &[("tf", "terraform modules")]).await?;
+    let response = search(&state, "terrafrom").await?;
+    assert_eq!(ids(&response), vec!["tf"]);
+    let response = search(&state, "gde").await?;
+    assert!(response.results.is_empty());
+    assert!(response.debug.unwrap().corrections.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn llm_expansions_are_searched_and_reported() -> Result<()> {
+    let mut config = QueryRewriteConfig::default();
+    config.expansion.enabled = true;
+    let expanding = state(Arc::new(ExpandingLlmClient), config.clone());
+    index(
+        &expanding,
+        &[
+            ("garage", "vehicle maintenance schedule"),
+            ("shop", "auto repair shop"),
+        ],
+    )
+    .await?;
+
+    let response = search(&expanding, "fix my car").await?;
+    assert_eq!(ids(&response), vec!["garage", "shop"]);
+    let debug = response.debug.expect("debug output");
+    assert_eq!(debug.expansions, vec!["vehicle maintenance", "auto repair"]);
+    assert_eq!(
+        debug.variants,
+        vec!["fix my car", "vehicle maintenance", "auto repair"]
+    );
+
+    // Clients without chat support fall back to the plain query.
+    let fallback = state(Arc::new(NullLlmClient), config);
+    let response = search(&fallback, "fix my car").await?;
+    assert!(response.debug.unwrap().expansions.is_empty());
+    Ok(())
+}
+
+#[test]
+fn edit_distance_counts_transpositions_once() {
+    assert_eq!(edit_distance("terrafrom", "terraform"), 1);
+    assert_eq!(edit_distance("kubernets", "kubernetes"), 1);
+    assert_eq!(edit_distance("cat", "dog"), 3);
+}
+
+#[test]
+fn expansion_replies_are_parsed_line_by_line() {
+    assert_eq!(
+        parse_expansions("* one\n2. two\n2024 tax rules\n'four'", 3),
+        vec!["one", "two", "2024 tax rules"]
+    );
+}
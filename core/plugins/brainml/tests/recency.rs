@@ -0,0 +1,319 @@
+use anyhow::Result;
+use brainml::adapters::braindb::{
+    BraindbClient, NullBraindbClient, ScanDocumentsRequest, UpsertDocumentsRequest,
+};
+use brainml::adapters::llm::NullLlmClient;
+use brainml::api::AppState;
+use brainml::core::aliases::AliasChange;
+use brainml::core::config::{BrainmlConfig, ExpiryConfig};
+use brainml::core::schema::{
+    DateField, DateRange, DecayFunction, DocumentInput, DocumentRecord, QueryRequest, RecencyBoost,
+};
+use brainml::core::scoring::{decay_factor, ScoringError};
+use chrono::{Duration, Utc};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+fn record(id: &str, age: Duration) -> DocumentRecord {
+    let mut record = DocumentRecord::new(
+        DocumentInput {
+            id: Some(id.into()),
+            text: "release notes".into(),
+            metadata: serde_json::json!({}),
+        },
+        None,
+    );
+    record.created_at = Utc::now() - age;
+    record.updated_at = record.created_at;
+    record
+}
+
+async fn seed(braindb: &NullBraindbClient, collection: &str, records: Vec<DocumentRecord>) {
+    braindb
+        .upsert_documents(UpsertDocumentsRequest {
+            collection: collection.into(),
+            documents: records,
+        })
+        .await
+        .unwrap();
+}
+
+fn state(braindb: &NullBraindbClient, config: BrainmlConfig) -> AppState {
+    AppState::new(Arc::new(braindb.clone()), Arc::new(NullLlmClient), config)
+}
+
+fn boost(function: DecayFunction) -> RecencyBoost {
+    RecencyBoost {
+        function,
+        field: DateField::UpdatedAt,
+        scale_secs: 86_400,
+        offset_secs: 3_600,
+        decay: 0.5,
+        weight: 1.0,
+    }
+}
+
+async fn ids(state: &AppState, request: QueryRequest) -> Result<Vec<String>> {
+    let response = state.process_query(request).await?;
+    Ok(response
+        .results
+        .into_iter()
+        .map(|result| result.id)
+        .collect())
+}
+
+fn query() -> QueryRequest {
+    QueryRequest {
+        collection: "notes".into(),
+        query: Some("release".into()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn decay_functions_reach_decay_at_scale() {
+    for function in [
+        DecayFunction::Exp,
+        DecayFunction::Linear,
+        DecayFunction::Gauss,
+    ] {
+        let boost = boost(function);
+        assert_eq!(decay_factor(&boost, 0.0), 1.0);
+        assert_eq!(decay_factor(&boost, 3_600.0), 1.0);
+        assert!((decay_factor(&boost, 3_600.0 + 86_400.0) - 0.5).abs() < 1e-9);
+        assert!(decay_factor(&boost, 30.0 * 86_400.0) < 0.5);
+    }
+    // Linear decay hits zero at scale / (1 - decay) past the offset.
+    assert_eq!(
+        decay_factor(&boost(DecayFunction::Linear), 3_600.0 + 2.0 * 86_400.0),
+        0.0
+    );
+}
+
+#[tokio::test]
+async fn recency_boost_ranks_newer_documents_first() -> Result<()> {
+    let braindb = NullBraindbClient::default();
+    seed(
+        &braindb,
+        "notes",
+        vec![
+            record("a-old", Duration::days(30)),
+            record("b-new", Duration::hours(2)),
+            record("c-mid", Duration::days(3)),
+        ],
+    )
+    .await;
+    let state = state(&braindb, BrainmlConfig::default());
+
+    assert_eq!(ids(&state, query()).await?, vec!["a-old", "b-new", "c-mid"]);
+    for function in [
+        DecayFunction::Exp,
+        DecayFunction::Linear,
+        DecayFunction::Gauss,
+    ] {
+        // A week-long scale keeps linear decay above zero for the 3-day document.
+        let request = QueryRequest {
+            recency: Some(RecencyBoost {
+                scale_secs: 7 * 86_400,
+                ..boost(function)
+            }),
+            ..query()
+        };
+        assert_eq!(
+            ids(&state, request).await?,
+            vec!["b-new", "c-mid", "a-old"],
+            "{function:?}"
+        );
+    }
+
+    let disabled = QueryRequest {
+        recency: Some(RecencyBoost {
+            weight: 0.0,
+            ..boost(DecayFunction::Exp)
+        }),
+        ..query()
+    };
+    assert_eq!(
+        ids(&state, disabled).await?,
+        vec!["a-old", "b-new", "c-mid"]
+    );
+
+    let invalid = QueryRequest {
+        recency: Some(RecencyBoost {
+            decay: 1.5,
+            ..boost(DecayFunction::Exp)
+        }),
+        ..query()
+    };
+    let err = state.process_query(invalid).await.unwrap_err();
+    assert!(err.is::<ScoringError>());
+    Ok(())
+}
+
+#[tokio::test]
+async fn date_range_keeps_documents_inside_the_range() -> Result<()> {
+    let braindb = NullBraindbClient::default();
+    seed(
+        &braindb,
+        "notes",
+        vec![
+            record("a-old", Duration::days(30)),
+            record("b-new", Duration::hours(2)),
+            record("c-mid", Duration::days(3)),
+        ],
+    )
+    .await;
+    let state = state(&braindb, BrainmlConfig::default());
+
+    let last_week = QueryRequest {
+        date_range: Some(DateRange {
+            field: DateField::CreatedAt,
+            from: Some(Utc::now() - Duration::days(7)),
+            to: None,
+        }),
+        ..query()
+    };
+    assert_eq!(ids(&state, last_week).await?, vec!["b-new", "c-mid"]);
+
+    let before_yesterday = QueryRequest {
+        date_range: Some(DateRange {
+            to: Some(Utc::now() - Duration::days(1)),
+            ..Default::default()
+        }),
+        ..query()
+    };
+    assert_eq!(ids(&state, before_yesterday).await?, vec!["a-old", "c-mid"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn date_range_applies_before_the_result_window() -> Result<()> {
+    let braindb = NullBraindbClient::default();
+    seed(
+        &braindb,
+        "notes",
+        vec![
+            record("a-old", Duration::days(30)),
+            record("b-old", Duration::days(20)),
+            record("c-old", Duration::days(10)),
+            record("d-new", Duration::hours(2)),
+        ],
+    )
+    .await;
+    let state = state(&braindb, BrainmlConfig::default());
+
+    // Every document scores the same and ties break by id, so the old
+    // ones fill the window ahead of the only one in range.
+    let recent = QueryRequest {
+        top_k: 2,
+        date_range: Some(DateRange {
+            field: DateField::CreatedAt,
+            from: Some(Utc::now() - Duration::days(7)),
+            to: None,
+        }),
+        ..query()
+    };
+    assert_eq!(ids(&state, recent).await?, vec!["d-new"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn reindexing_keeps_the_original_creation_time() -> Result<()> {
+    let braindb = NullBraindbClient::default();
+    let original = record("doc", Duration::days(30));
+    seed(&braindb, "notes", vec![original.clone()]).await;
+    seed(&braindb, "notes", vec![record("doc", Duration::zero())]).await;
+
+    let stored = braindb
+        .scan_documents(ScanDocumentsRequest {
+            collection: "notes".into(),
+            offset: 0,
+            limit: 10,
+        })
+        .await?;
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].created_at, original.created_at);
+    assert!(stored[0].updated_at > original.updated_at);
+    Ok(())
+}
+
+#[tokio::test]
+async fn expiry_sweep_removes_documents_past_their_ttl() -> Result<()> {
+    let braindb = NullBraindbClient::default();
+    seed(
+        &braindb,
+        "events",
+        vec![
+            record("stale", Duration::hours(2)),
+            record("fresh", Duration::minutes(5)),
+        ],
+    )
+    .await;
+    seed(
+        &braindb,
+        "acme::events",
+        vec![record("acme-stale", Duration::hours(2))],
+    )
+    .await;
+    seed(
+        &braindb,
+        "logs_v2",
+        vec![record("log-stale", Duration::minutes(5))],
+    )
+    .await;
+    seed(
+        &braindb,
+        "archive",
+        vec![record("kept", Duration::days(365))],
+    )
+    .await;
+
+    let state = state(
+        &braindb,
+        BrainmlConfig {
+            expiry: ExpiryConfig {
+                ttl_secs: BTreeMap::from([("events".into(), 3_600), ("logs".into(), 60)]),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    );
+    let aliases_path = std::env::temp_dir().join(format!(
+        "brainml-recency-aliases-{}.json",
+        std::process::id()
+    ));
+    state.aliases.update(
+        &[AliasChange {
+            alias: "logs".into(),
+            collection: Some("logs_v2".into()),
+        }],
+        &HashSet::from(["logs_v2".to_string()]),
+        &aliases_path,
+    )?;
+    let _ = std::fs::remove_file(&aliases_path);
+
+    let report = state.expire_documents().await.unwrap();
+    assert_eq!(report.error, None);
+    assert_eq!(report.collections, 3);
+    assert_eq!(report.scanned, 4);
+    assert_eq!(report.expired, 3);
+    assert_eq!(state.admin_status().expiry.unwrap().expired, 3);
+
+    let remaining: Vec<(String, usize)> = braindb
+        .stats()
+        .await?
+        .collections
+        .into_iter()
+        .map(|stats| (stats.name, stats.document_count))
+        .collect();
+    assert_eq!(
+        remaining,
+        vec![
+            ("events".to_string(), 1),
+            ("acme::events".to_string(), 0),
+            ("logs_v2".to_string(), 0),
+            ("archive".to_string(), 1),
+        ]
+    );
+    Ok(())
+}
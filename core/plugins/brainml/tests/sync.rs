@@ -0,0 +1,162 @@
+use anyhow::Result;
+use brainml::adapters::braindb::NullBraindbClient;
+use brainml::adapters::llm::NullLlmClient;
+use brainml::api::AppState;
+use brainml::core::config::{BrainmlConfig, IngestConfig, SyncConfig, SyncSource};
+use brainml::core::schema::{AdminAction, AdminRequest, QueryRequest};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+struct Workspace {
+    root: PathBuf,
+}
+
+impl Workspace {
+    fn new(name: &str) -> Self {
+        let root = std::env::temp_dir().join(format!("brainml-sync-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("docs/nested")).unwrap();
+        std::fs::create_dir_all(root.join("docs/node_modules")).unwrap();
+        Self { root }
+    }
+
+    fn docs(&self) -> PathBuf {
+        self.root.join("docs")
+    }
+
+    fn write(&self, path: &str, contents: &str) {
+        std::fs::write(self.docs().join(path), contents).unwrap();
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+fn state(braindb: &NullBraindbClient, workspace: &Workspace) -> AppState {
+    AppState::new(
+        Arc::new(braindb.clone()),
+        Arc::new(NullLlmClient),
+        BrainmlConfig {
+            ingest: IngestConfig {
+                chunk_size: 80,
+                chunk_overlap: 10,
+                max_file_bytes: 64 * 1024,
+            },
+            sync: SyncConfig {
+                manifest_dir: workspace.root.join("manifests"),
+                sources: vec![source(&workspace.docs())],
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )
+}
+
+fn source(path: &Path) -> SyncSource {
+    SyncSource {
+        path: path.to_path_buf(),
+        collection: "notes".into(),
+        tenant: None,
+        extensions: vec!["md".into(), "txt".into()],
+        ignore: vec!["node_modules".into()],
+        embed: false,
+        fts: true,
+    }
+}
+
+async fn ids(state: &AppState, query: &str) -> Result<Vec<String>> {
+    let response = state
+        .process_query(QueryRequest {
+            collection: "notes".into(),
+            query: Some(query.into()),
+            top_k: 50,
+            ..Default::default()
+        })
+        .await?;
+    let mut ids: Vec<_> = response.results.into_iter().map(|r| r.id).collect();
+    ids.sort();
+    Ok(ids)
+}
+
+#[tokio::test]
+async fn changed_and_removed_files_are_synced() -> Result<()> {
+    let workspace = Workspace::new("changes");
+    workspace.write("alpha.md", "# Alpha\n\nalpha notes about sync");
+    workspace.write("nested/beta.txt", &"beta ".repeat(40));
+    workspace.write("skipped.rs", "fn beta() {}");
+    workspace.write("node_modules/gamma.md", "beta in dependencies");
+    workspace.write(".hidden.md", "beta hidden");
+    let braindb = NullBraindbClient::default();
+    let state = state(&braindb, &workspace);
+
+    let reports = state.sync_all().await;
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].scanned, 2);
+    assert_eq!(reports[0].upserted, 2);
+    assert!(reports[0].error.is_none());
+    let beta = ids(&state, "beta").await?;
+    assert!(beta.len() > 2);
+    assert!(beta.iter().all(|id| id.starts_with("nested/beta.txt#")));
+
+    // Shrinking a file drops the chunks it no longer produces.
+    workspace.write("nested/beta.txt", "beta shortened");
+    let reports = state.sync_all().await;
+    assert_eq!(reports[0].upserted, 1);
+    assert_eq!(reports[0].unchanged, 1);
+    assert_eq!(ids(&state, "beta").await?, vec!["nested/beta.txt#0"]);
+
+    std::fs::remove_file(workspace.docs().join("alpha.md"))?;
+    let reports = state.sync_all().await;
+    assert_eq!(reports[0].deleted, 1);
+    assert!(ids(&state, "alpha").await?.is_empty());
+
+    let status = state.admin_status();
+    assert!(!status.sync_running);
+    assert_eq!(status.sync[0].deleted, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn manifest_skips_unchanged_files_after_restart() -> Result<()> {
+    let workspace = Workspace::new("restart");
+    workspace.write("alpha.md", "alpha notes");
+    workspace.write("nested/beta.txt", "beta notes");
+    let braindb = NullBraindbClient::default();
+    let first = state(&braindb, &workspace).sync_all().await;
+    assert_eq!(first[0].upserted, 2);
+
+    workspace.write("alpha.md", "alpha notes, revised");
+    let restarted = state(&braindb, &workspace);
+    let reports = restarted.sync_all().await;
+    assert_eq!(reports[0].upserted, 1);
+    assert_eq!(reports[0].unchanged, 1);
+    assert_eq!(ids(&restarted, "revised").await?, vec!["alpha.md#0"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn admin_action_triggers_a_sync_pass() -> Result<()> {
+    let workspace = Workspace::new("admin");
+    workspace.write("alpha.md", "alpha notes");
+    let braindb = NullBraindbClient::default();
+    let state = state(&braindb, &workspace);
+
+    state
+        .process_admin(AdminRequest {
+            action: AdminAction::SyncDirectories,
+            ..Default::default()
+        })
+        .await?;
+    for _ in 0..100 {
+        if !state.admin_status().sync.is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    assert_eq!(state.admin_status().sync[0].upserted, 1);
+    assert_eq!(ids(&state, "alpha").await?, vec!["alpha.md#0"]);
+    Ok(())
+}
@@ -0,0 +1,328 @@
+use anyhow::Result;
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use brainml::adapters::braindb::NullBraindbClient;
+use brainml::adapters::llm::NullLlmClient;
+use brainml::api::AppState;
+use brainml::core::config::{BrainmlConfig, TenancyConfig, TenantQuota};
+use brainml::core::schema::{DocumentInput, IndexRequest, QueryRequest, SyncReport};
+use brainml::core::tenancy::{TenantContext, TenantError};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+fn tenancy_config() -> TenancyConfig {
+    TenancyConfig {
+        enabled: true,
+        tokens: HashMap::from([
+            ("token-blue".to_string(), "blue".to_string()),
+            ("token-green".to_string(), "green".to_string()),
+            ("token-red".to_string(), "red".to_string()),
+            ("token-default".to_string(), "default".to_string()),
+            ("token-bad".to_string(), "acme::x".to_string()),
+        ]),
+        quotas: HashMap::from([(
+            "red".to_string(),
+            TenantQuota {
+                max_documents: Some(1),
+                max_storage_bytes: None,
+                max_qps: Some(2),
+            },
+        )]),
+        ..Default::default()
+    }
+}
+
+fn test_state() -> AppState {
+    AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        Arc::new(NullLlmClient),
+        BrainmlConfig {
+            tenancy: tenancy_config(),
+            ..Default::default()
+        },
+    )
+}
+
+fn index_request(id: &str, text: &str) -> IndexRequest {
+    IndexRequest {
+        collection: "docs".into(),
+        documents: vec![DocumentInput {
+            id: Some(id.into()),
+            text: text.into(),
+            metadata: serde_json::json!({}),
+        }],
+        embed: false,
+        fts: true,
+        ..Default::default()
+    }
+}
+
+fn query_request(query: &str) -> QueryRequest {
+    QueryRequest {
+        collection: "docs".into(),
+        query: Some(query.into()),
+        vector: None,
+        top_k: 5,
+        hybrid: false,
+        filters: Vec::new(),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn tenants_do_not_see_each_others_collections() -> Result<()> {
+    let state = test_state();
+    let config = tenancy_config();
+    let blue = TenantContext::resolve(&config, None, Some("token-blue"))?;
+    let green = TenantContext::resolve(&config, Some("green"), Some("token-green"))?;
+    assert_eq!(blue.id, "blue");
+
+    state
+        .process_index_for(&blue, index_request("blue-1", "shared keyword"))
+        .await?;
+    state
+        .process_index_for(&green, index_request("green-1", "shared keyword"))
+        .await?;
+
+    let response = state
+        .process_query_for(&blue, query_request("keyword"))
+        .await?;
+    let ids: Vec<_> = response.results.iter().map(|r| r.id.as_str()).collect();
+    assert_eq!(ids, vec!["blue-1"]);
+
+    let default_view = state.process_query(query_request("keyword")).await?;
+    assert!(default_view.results.is_empty());
+
+    let stats = state.process_stats_for(&blue).await?;
+    assert_eq!(stats.collections.len(), 1);
+    assert_eq!(stats.collections[0].name, "docs");
+    assert_eq!(stats.tenants.len(), 1);
+    assert_eq!(stats.tenants[0].document_count, 1);
+
+    let operator = state.process_stats_for(&state.default_tenant()).await?;
+    let tenants: Vec<_> = operator.tenants.iter().map(|t| t.tenant.as_str()).collect();
+    assert_eq!(tenants, vec!["blue", "default", "green"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn admin_status_hides_other_tenants() -> Result<()> {
+    let state = test_state();
+    let config = tenancy_config();
+    let blue = TenantContext::resolve(&config, None, Some("token-blue"))?;
+    let green = TenantContext::resolve(&config, None, Some("token-green"))?;
+    state
+        .process_index_for(&blue, index_request("b1", "blue secret"))
+        .await?;
+    state
+        .process_index_for(&green, index_request("g1", "green note"))
+        .await?;
+    state
+        .migrator
+        .migrate_all(
+            state.braindb.as_ref(),
+            &NullLlmClient,
+            &state.cache,
+            &state.config.load(),
+            &state.default_tenant(),
+            10,
+        )
+        .await?;
+    for tenant in ["blue", "green"] {
+        state.sync.record(SyncReport {
+            collection: "docs".into(),
+            root: format!("/srv/{tenant}-files"),
+            tenant: Some(tenant.into()),
+            scanned: 1,
+            unchanged: 0,
+            upserted: 1,
+            deleted: 0,
+            failed: 0,
+            error: None,
+            finished_at: chrono::Utc::now(),
+        });
+    }
+    state.expire_documents().await;
+
+    let status = state.admin_status_for(&green);
+    assert!(!serde_json::to_string(&status)?.contains("blue"));
+    assert!(status.active_config.is_null());
+    assert!(status.limits.is_none());
+    assert!(status.expiry.is_none());
+    let migrated: Vec<_> = status
+        .migrations
+        .iter()
+        .map(|progress| progress.collection.as_str())
+        .collect();
+    assert_eq!(migrated, ["docs"]);
+    assert_eq!(status.sync.len(), 1);
+    assert_eq!(status.sync[0].root, "/srv/green-files");
+
+    let status = state.admin_status();
+    assert_eq!(status.migrations.len(), 2);
+    assert_eq!(status.sync.len(), 2);
+    assert!(status.limits.is_some());
+    assert!(status.expiry.is_some());
+    assert!(status.active_config.to_string().contains("blue"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn quotas_reject_excess_documents_and_queries() -> Result<()> {
+    let state = test_state();
+    let red = TenantContext::resolve(&tenancy_config(), None, Some("token-red"))?;
+
+    state
+        .process_index_for(&red, index_request("red-1", "first"))
+        .await?;
+    let err = state
+        .process_index_for(&red, index_request("red-2", "second"))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<TenantError>(),
+        Some(TenantError::QuotaExceeded { .. })
+    ));
+
+    state
+        .process_query_for(&red, query_request("first"))
+        .await?;
+    state
+        .process_query_for(&red, query_request("first"))
+        .await?;
+    let err = state
+        .process_query_for(&red, query_request("first"))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<TenantError>(),
+        Some(TenantError::QuotaExceeded { .. })
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn storage_quota_follows_stored_documents() -> Result<()> {
+    let braindb = Arc::new(NullBraindbClient::default());
+    let mut config = tenancy_config();
+    config.quotas.insert(
+        "green".to_string(),
+        TenantQuota {
+            max_documents: None,
+            max_storage_bytes: Some(200),
+            max_qps: None,
+        },
+    );
+    let state = AppState::new(
+        braindb.clone(),
+        Arc::new(NullLlmClient),
+        BrainmlConfig {
+            tenancy: config.clone(),
+            ..Default::default()
+        },
+    );
+    let green = TenantContext::resolve(&config, None, Some("token-green"))?;
+    let text = "a document of roughly sixty bytes for the storage quota";
+
+    // Re-indexing the same id replaces the document instead of adding to it.
+    for _ in 0..5 {
+        state
+            .process_index_for(&green, index_request("green-1", text))
+            .await?;
+    }
+    let err = state
+        .process_index_for(&green, index_request("green-2", &text.repeat(3)))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<TenantError>(),
+        Some(TenantError::QuotaExceeded { .. })
+    ));
+
+    // A restarted process sees the usage of the documents already stored.
+    let restarted = AppState::new(
+        braindb,
+        Arc::new(NullLlmClient),
+        BrainmlConfig {
+            tenancy: config,
+            ..Default::default()
+        },
+    );
+    let stats = restarted.process_stats_for(&green).await?;
+    assert_eq!(
+        stats.tenants[0].storage_bytes,
+        stats.collections[0].memory_bytes
+    );
+    assert!(stats.tenants[0].storage_bytes > 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn tenants_come_from_tokens_not_headers() -> Result<()> {
+    let config = tenancy_config();
+    let unauthorized = |result: Result<TenantContext, TenantError>| {
+        matches!(result, Err(TenantError::Unauthorized(_)))
+    };
+    assert!(unauthorized(TenantContext::resolve(&config, None, None)));
+    assert!(unauthorized(TenantContext::resolve(
+        &config,
+        Some("blue"),
+        None
+    )));
+    assert!(unauthorized(TenantContext::resolve(
+        &config,
+        None,
+        Some("guess")
+    )));
+    // The header may repeat the token's tenant but never switch to another,
+    // least of all to the operator.
+    assert_eq!(
+        TenantContext::resolve(&config, Some("blue"), Some("token-blue"))?.id,
+        "blue"
+    );
+    assert!(unauthorized(TenantContext::resolve(
+        &config,
+        Some("default"),
+        Some("token-blue")
+    )));
+    assert!(TenantContext::resolve(&config, None, Some("token-default"))?.is_default());
+    assert!(matches!(
+        TenantContext::resolve(&config, None, Some("token-bad")),
+        Err(TenantError::InvalidTenant(_))
+    ));
+    assert!(matches!(
+        TenantContext::named(&config, ""),
+        Err(TenantError::InvalidTenant(_))
+    ));
+
+    let router = brainml::api::router(test_state());
+    let stats = |token: Option<&str>, tenant: Option<&str>| {
+        let mut request = Request::get("/api/v1/brainml/stats");
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        if let Some(tenant) = tenant {
+            request = request.header("x-bkg-tenant", tenant);
+        }
+        request.body(Body::empty()).unwrap()
+    };
+    let response = router.clone().oneshot(stats(None, Some("default"))).await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = router
+        .clone()
+        .oneshot(stats(Some("token-blue"), Some("red")))
+        .await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = router
+        .clone()
+        .oneshot(stats(Some("token-blue"), None))
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    // Unlimited routes need no tenant.
+    let response = router
+        .oneshot(Request::get("/health/live").body(Body::empty())?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    Ok(())
+}
@@ -0,0 +1,246 @@
+use anyhow::Result;
+use axum::body::Body;
+use axum::http::Request;
+use brainml::adapters::braindb::{BraindbClient, PluginBusBraindbClient};
+use brainml::adapters::llm::NullLlmClient;
+use brainml::api::AppState;
+use brainml::core::bus::{IncomingMessage, OutboundCommand, OutgoingMessage};
+use brainml::core::config::{BrainmlConfig, TelemetryConfig};
+use brainml::core::errors::{ErrorCode, ErrorDetail};
+use brainml::util::otlp::OtlpExporter;
+use brainml::util::trace_context::{self, TraceContext, TraceLayer, TRACEPARENT};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tower::ServiceExt;
+use tracing_subscriber::layer::SubscriberExt;
+use uuid::Uuid;
+
+const CALLER: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+fn subscriber(exporter: Option<OtlpExporter>) -> impl tracing::Subscriber + Send + Sync {
+    tracing_subscriber::registry().with(TraceLayer::new(exporter))
+}
+
+#[test]
+fn traceparent_round_trips_and_rejects_malformed_values() {
+    let context = TraceContext::parse(CALLER).unwrap();
+    assert_eq!(context.trace_id, 0x4bf92f3577b34da6a3ce929d0e0e4736);
+    assert_eq!(context.span_id, 0x00f067aa0ba902b7);
+    assert!(context.sampled);
+    assert_eq!(context.to_string(), CALLER);
+
+    let child = context.child();
+    assert_eq!(child.trace_id, context.trace_id);
+    assert_ne!(child.span_id, context.span_id);
+
+    for malformed in [
+        "",
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+        "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+        "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+    ] {
+        assert_eq!(TraceContext::parse(malformed), None, "{malformed}");
+    }
+}
+
+#[tokio::test]
+async fn http_requests_continue_the_callers_trace() -> Result<()> {
+    let _guard = tracing::subscriber::set_default(subscriber(None));
+    let state = AppState::new(
+        Arc::new(brainml::adapters::braindb::NullBraindbClient::default()),
+        Arc::new(NullLlmClient),
+        BrainmlConfig::default(),
+    );
+    let response = brainml::api::router(state)
+        .oneshot(
+            Request::get("/health/live")
+                .header(TRACEPARENT, CALLER)
+                .body(Body::empty())?,
+        )
+        .await?;
+    let header = response.headers()[TRACEPARENT].to_str()?;
+    let returned = TraceContext::parse(header).unwrap();
+    let caller = TraceContext::parse(CALLER).unwrap();
+    assert_eq!(returned.trace_id, caller.trace_id);
+    assert_ne!(returned.span_id, caller.span_id);
+    Ok(())
+}
+
+#[tokio::test]
+async fn bus_invocations_carry_the_current_traceparent() -> Result<()> {
+    let _guard = tracing::subscriber::set_default(subscriber(None));
+    let (sender, mut receiver) = brainml::core::bus::channel();
+    let client = PluginBusBraindbClient::new(sender);
+    let span = tracing::info_span!("handler", traceparent = CALLER);
+    let call = tokio::spawn(tracing::Instrument::instrument(
+        async move { client.stats().await },
+        span,
+    ));
+
+    let Some(OutboundCommand::Invoke {
+        capability,
+        responder,
+        traceparent,
+        ..
+    }) = receiver.recv().await
+    else {
+        panic!("expected an invoke command");
+    };
+    assert_eq!(capability, "db.stats");
+    let sent = TraceContext::parse(&traceparent.unwrap()).unwrap();
+    assert_eq!(sent.trace_id, TraceContext::parse(CALLER).unwrap().trace_id);
+    let _ = responder.send(Err(ErrorDetail::new(ErrorCode::NotFound, "no stats")));
+    assert!(call.await?.is_err());
+
+    // Outside any span there is nothing to propagate.
+    assert_eq!(trace_context::current_traceparent(), None);
+    Ok(())
+}
+
+#[test]
+fn bus_messages_carry_traceparent_only_when_present() {
+    let message: IncomingMessage = serde_json::from_value(serde_json::json!({
+        "type": "request",
+        "requestId": Uuid::nil(),
+        "capability": "brainml.query",
+        "payload": {},
+        "token": null,
+        "traceparent": CALLER,
+    }))
+    .unwrap();
+    assert!(matches!(
+        message,
+        IncomingMessage::Request { traceparent: Some(value), .. } if value == CALLER
+    ));
+
+    let outgoing = OutgoingMessage::Request {
+        requestId: Uuid::nil(),
+        capability: "db.scan".into(),
+        payload: serde_json::Value::Null,
+        traceparent: None,
+    };
+    let value = serde_json::to_value(&outgoing).unwrap();
+    assert!(value.get("traceparent").is_none());
+}
+
+/// Accepts one HTTP request and answers 200, returning the path and body.
+async fn collector_stub(listener: TcpListener) -> Result<(String, serde_json::Value)> {
+    let (mut stream, _) = listener.accept().await?;
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let (head_end, content_length) = loop {
+        let read = stream.read(&mut chunk).await?;
+        anyhow::ensure!(read > 0, "connection closed before headers");
+        buffer.extend_from_slice(&chunk[..read]);
+        if let Some(position) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            let head = String::from_utf8_lossy(&buffer[..position]).to_ascii_lowercase();
+            let length = head
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length:"))
+                .and_then(|value| value.trim().parse::<usize>().ok())
+                .unwrap_or(0);
+            break (position + 4, length);
+        }
+    };
+    while buffer.len() < head_end + content_length {
+        let read = stream.read(&mut chunk).await?;
+        anyhow::ensure!(read > 0, "connection closed before body");
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+    stream
+        .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+        .await?;
+    let request_line = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+    let path = request_line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or_default()
+        .to_string();
+    let body = serde_json::from_slice(&buffer[head_end..head_end + content_length])?;
+    Ok((path, body))
+}
+
+#[tokio::test]
+async fn finished_spans_are_exported_to_the_collector() -> Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let endpoint = format!("http://{}", listener.local_addr()?);
+    let collector = tokio::spawn(collector_stub(listener));
+    let exporter = OtlpExporter::spawn(
+        &endpoint,
+        &TelemetryConfig {
+            service_name: "brainml-test".into(),
+            export_interval_ms: 60_000,
+            ..Default::default()
+        },
+    )?;
+
+    {
+        let _guard = tracing::subscriber::set_default(subscriber(Some(exporter.clone())));
+        let span = tracing::info_span!(
+            "bus.request",
+            otel.name = "brainml.query",
+            otel.kind = "server",
+            traceparent = CALLER,
+        );
+        span.in_scope(|| tracing::info_span!("embed").in_scope(|| {}));
+    }
+    exporter.flush().await;
+
+    let (path, body) = collector.await??;
+    assert_eq!(path, "/v1/traces");
+    let resource = &body["resourceSpans"][0];
+    assert_eq!(
+        resource["resource"]["attributes"][0]["value"]["stringValue"],
+        "brainml-test"
+    );
+    let spans = resource["scopeSpans"][0]["spans"].as_array().unwrap();
+    assert_eq!(spans.len(), 2);
+    let server = spans
+        .iter()
+        .find(|span| span["name"] == "brainml.query")
+        .unwrap();
+    let embed = spans.iter().find(|span| span["name"] == "embed").unwrap();
+    assert_eq!(server["traceId"], "4bf92f3577b34da6a3ce929d0e0e4736");
+    assert_eq!(server["parentSpanId"], "00f067aa0ba902b7");
+    assert_eq!(server["kind"], 2);
+    assert_eq!(embed["traceId"], server["traceId"]);
+    assert_eq!(embed["parentSpanId"], server["spanId"]);
+    assert_eq!(embed["kind"], 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn stalled_collector_does_not_block_the_exporter() -> Result<()> {
+    // Accepts connections and reads requests but never answers.
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let endpoint = format!("http://{}", listener.local_addr()?);
+    tokio::spawn(async move {
+        let mut held = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            held.push(stream);
+        }
+    });
+    let exporter = OtlpExporter::spawn(
+        &endpoint,
+        &TelemetryConfig {
+            export_interval_ms: 60_000,
+            export_timeout_ms: 200,
+            ..Default::default()
+        },
+    )?;
+
+    for _ in 0..2 {
+        {
+            let _guard = tracing::subscriber::set_default(subscriber(Some(exporter.clone())));
+            tracing::info_span!("embed").in_scope(|| {});
+        }
+        tokio::time::timeout(std::time::Duration::from_secs(5), exporter.flush())
+            .await
+            .expect("flush returns once the export times out");
+    }
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use anyhow::Result;
+use brainml::adapters::braindb::NullBraindbClient;
+use brainml::adapters::llm::NullLlmClient;
+use brainml::api::AppState;
+use brainml::core::config::{BrainmlConfig, VectorFieldConfig};
+use brainml::core::embeddings::UnknownVectorField;
+use brainml::core::schema::{DocumentField, DocumentInput, IndexRequest, QueryRequest};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+fn test_state() -> AppState {
+    AppState::new(
+        Arc::new(NullBraindbClient::default()),
+        Arc::new(NullLlmClient),
+        BrainmlConfig {
+            port: 43133,
+            bus: "ws://127.0.0.1:43121".into(),
+            vector_fields: BTreeMap::from([
+                (
+                    "minilm".to_string(),
+                    VectorFieldConfig {
+                        model: "all-MiniLM-L6-v2".into(),
+                    },
+                ),
+                (
+                    "e5".to_string(),
+                    VectorFieldConfig {
+                        model: "e5-small-v2".into(),
+                    },
+                ),
+            ]),
+            ..Default::default()
+        },
+    )
+}
+
+#[tokio::test]
+async fn documents_carry_one_vector_per_configured_field() -> Result<()> {
+    let state = test_state();
+    state
+        .process_index(IndexRequest {
+            collection: "ab".into(),
+            documents: vec![DocumentInput {
+                id: Some("doc-a".into()),
+                text: "embedding migration without downtime".into(),
+                metadata: serde_json::json!({}),
+            }],
+            embed: true,
+            ..Default::default()
+        })
+        .await?;
+
+    let response = state
+        .process_query(QueryRequest {
+            collection: "ab".into(),
+            query: Some("migration".into()),
+            fields: vec![DocumentField::Embedding],
+            ..Default::default()
+        })
+        .await?;
+    let document = &response.results[0].document;
+    assert_eq!(document.vectors.len(), 2);
+    assert_eq!(document.vectors["e5"].model, "e5-small-v2");
+    assert_eq!(document.vectors["minilm"].model, "all-MiniLM-L6-v2");
+    assert_ne!(
+        document.vectors["e5"].values,
+        document.vectors["minilm"].values
+    );
+
+    let e5 = document.vectors["e5"].values.clone();
+    let response = state
+        .process_query(QueryRequest {
+            collection: "ab".into(),
+            vector: Some(e5),
+            vector_field: Some("e5".into()),
+            ..Default::default()
+        })
+        .await?;
+    assert_eq!(response.results.len(), 1);
+    assert_eq!(response.results[0].id, "doc-a");
+    Ok(())
+}
+
+#[tokio::test]
+async fn unknown_vector_field_is_rejected() {
+    let state = test_state();
+    let err = state
+        .process_query(QueryRequest {
+            collection: "ab".into(),
+            query: Some("migration".into()),
+            hybrid: true,
+            vector_field: Some("missing".into()),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert!(err.is::<UnknownVectorField>());
+}
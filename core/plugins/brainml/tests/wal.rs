@@ -0,0 +1,297 @@
+use anyhow::Result;
+use brainml::adapters::braindb::{
+    BraindbClient, DeleteDocumentsRequest, ScanDocumentsRequest, UpsertDocumentsRequest,
+};
+use brainml::adapters::local::{LocalBraindbClient, RecoveryReport};
+use brainml::core::config::{QuantizationSettings, StorageConfig};
+use brainml::core::quantization::Quantization;
+use brainml::core::retriever::ensure_collection;
+use brainml::core::schema::{DocumentInput, DocumentRecord};
+use brainml::core::wal::FsyncPolicy;
+use std::collections::BTreeSet;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Set in the child process of `killed_writer_keeps_acknowledged_writes`.
+const CRASH_DIR_ENV: &str = "BRAINML_WAL_CRASH_DIR";
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("brainml-wal-{name}-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn config(dir: &Path) -> StorageConfig {
+    StorageConfig {
+        path: dir.to_path_buf(),
+        ..Default::default()
+    }
+}
+
+fn batch(prefix: &str, count: usize) -> UpsertDocumentsRequest {
+    UpsertDocumentsRequest {
+        collection: "docs".into(),
+        documents: (0..count)
+            .map(|idx| {
+                DocumentRecord::new(
+                    DocumentInput {
+                        id: Some(format!("{prefix}-{idx}")),
+                        text: format!("document {idx} of batch {prefix}"),
+                        metadata: serde_json::json!({ "batch": prefix }),
+                    },
+                    Some(vec![idx as f32, 1.0, -0.5]),
+                )
+            })
+            .collect(),
+    }
+}
+
+async fn ids(client: &LocalBraindbClient) -> Result<BTreeSet<String>> {
+    Ok(client
+        .scan_documents(ScanDocumentsRequest {
+            collection: "docs".into(),
+            offset: 0,
+            limit: usize::MAX,
+        })
+        .await?
+        .into_iter()
+        .map(|record| record.id)
+        .collect())
+}
+
+fn expected(batches: &[(&str, usize)]) -> BTreeSet<String> {
+    batches
+        .iter()
+        .flat_map(|(prefix, count)| (0..*count).map(move |idx| format!("{prefix}-{idx}")))
+        .collect()
+}
+
+#[tokio::test]
+async fn writes_survive_reopen_through_log_and_checkpoint() -> Result<()> {
+    let dir = temp_dir("reopen");
+    let int8 = QuantizationSettings {
+        kind: Quantization::Int8,
+        ..Default::default()
+    };
+    {
+        let client = LocalBraindbClient::open(&config(&dir)).await?;
+        ensure_collection(&client, "docs", &int8).await?;
+        let logged = client.wal_bytes();
+        // Re-creating with the same schema, as every index request does, is
+        // not logged again.
+        ensure_collection(&client, "docs", &int8).await?;
+        assert_eq!(client.wal_bytes(), logged);
+        client.upsert_documents(batch("a", 3)).await?;
+        client.upsert_documents(batch("b", 2)).await?;
+        client
+            .delete_documents(DeleteDocumentsRequest {
+                collection: "docs".into(),
+                ids: vec!["a-1".into()],
+            })
+            .await?;
+    }
+
+    let client = LocalBraindbClient::open(&config(&dir)).await?;
+    assert_eq!(
+        client.recovery(),
+        RecoveryReport {
+            collections: 0,
+            replayed: 4,
+            truncated_bytes: 0,
+        }
+    );
+    // Replayed entries are checkpointed right away.
+    assert_eq!(client.wal_bytes(), 0);
+    let mut survivors = expected(&[("a", 3), ("b", 2)]);
+    survivors.remove("a-1");
+    assert_eq!(ids(&client).await?, survivors);
+    drop(client);
+
+    let client = LocalBraindbClient::open(&config(&dir)).await?;
+    assert_eq!(client.recovery().collections, 1);
+    assert_eq!(client.recovery().replayed, 0);
+    assert_eq!(ids(&client).await?, survivors);
+    let stats = client.stats().await?.collections;
+    assert_eq!(stats[0].quantization, Quantization::Int8);
+    assert_eq!(stats[0].embedding_dimensions, Some(3));
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn torn_write_is_dropped_on_recovery() -> Result<()> {
+    let dir = temp_dir("torn");
+    {
+        let client = LocalBraindbClient::open(&config(&dir)).await?;
+        client.upsert_documents(batch("a", 2)).await?;
+        // The next entry dies 40 bytes in, as if the process was killed.
+        client.fail_after_wal_bytes(40);
+        assert!(client.upsert_documents(batch("b", 2)).await.is_err());
+        assert!(client.upsert_documents(batch("c", 2)).await.is_err());
+        assert_eq!(ids(&client).await?, expected(&[("a", 2)]));
+    }
+
+    let client = LocalBraindbClient::open(&config(&dir)).await?;
+    assert_eq!(client.recovery().replayed, 1);
+    assert_eq!(client.recovery().truncated_bytes, 40);
+    assert_eq!(ids(&client).await?, expected(&[("a", 2)]));
+    client.upsert_documents(batch("d", 1)).await?;
+    drop(client);
+
+    let client = LocalBraindbClient::open(&config(&dir)).await?;
+    assert_eq!(ids(&client).await?, expected(&[("a", 2), ("d", 1)]));
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn oversized_write_is_rejected_before_it_is_logged() -> Result<()> {
+    let dir = temp_dir("oversized");
+    let limited = StorageConfig {
+        max_entry_bytes: 2048,
+        ..config(&dir)
+    };
+    {
+        let client = LocalBraindbClient::open(&limited).await?;
+        client.upsert_documents(batch("a", 1)).await?;
+        let logged = client.wal_bytes();
+        let err = client.upsert_documents(batch("b", 20)).await.unwrap_err();
+        assert!(err.to_string().contains("exceeds the 2048 byte limit"));
+        assert_eq!(client.wal_bytes(), logged);
+        assert_eq!(ids(&client).await?, expected(&[("a", 1)]));
+        // Later writes are still logged and survive a restart.
+        client.upsert_documents(batch("c", 1)).await?;
+    }
+
+    let client = LocalBraindbClient::open(&limited).await?;
+    assert_eq!(client.recovery().truncated_bytes, 0);
+    assert_eq!(ids(&client).await?, expected(&[("a", 1), ("c", 1)]));
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn corrupt_entry_ends_replay() -> Result<()> {
+    let dir = temp_dir("corrupt");
+    let first_entry = {
+        let client = LocalBraindbClient::open(&config(&dir)).await?;
+        client.upsert_documents(batch("a", 1)).await?;
+        let first_entry = client.wal_bytes();
+        client.upsert_documents(batch("b", 1)).await?;
+        client.upsert_documents(batch("c", 1)).await?;
+        first_entry
+    };
+    let wal = dir.join("wal.log");
+    let mut bytes = std::fs::read(&wal)?;
+    let total = bytes.len() as u64;
+    bytes[first_entry as usize + 20] ^= 0xff;
+    std::fs::write(&wal, bytes)?;
+
+    let client = LocalBraindbClient::open(&config(&dir)).await?;
+    assert_eq!(client.recovery().replayed, 1);
+    assert_eq!(client.recovery().truncated_bytes, total - first_entry);
+    assert_eq!(ids(&client).await?, expected(&[("a", 1)]));
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn crash_before_log_reset_replays_over_checkpoint() -> Result<()> {
+    let dir = temp_dir("checkpoint");
+    {
+        let client = LocalBraindbClient::open(&config(&dir)).await?;
+        client.upsert_documents(batch("a", 3)).await?;
+        client
+            .delete_documents(DeleteDocumentsRequest {
+                collection: "docs".into(),
+                ids: vec!["a-0".into()],
+            })
+            .await?;
+        // Keep the log as it was before the checkpoint emptied it.
+        let wal = std::fs::read(dir.join("wal.log"))?;
+        client.checkpoint().await?;
+        assert_eq!(client.wal_bytes(), 0);
+        drop(client);
+        std::fs::write(dir.join("wal.log"), wal)?;
+    }
+
+    let client = LocalBraindbClient::open(&config(&dir)).await?;
+    assert_eq!(client.recovery().collections, 1);
+    assert_eq!(client.recovery().replayed, 2);
+    let mut survivors = expected(&[("a", 3)]);
+    survivors.remove("a-0");
+    assert_eq!(ids(&client).await?, survivors);
+    assert_eq!(client.stats().await?.collections[0].document_count, 2);
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+/// Runs a writer in a child process, kills it with SIGKILL while it is
+/// writing and checkpointing, then checks that every acknowledged batch was
+/// recovered and no batch was recovered in part.
+#[test]
+fn killed_writer_keeps_acknowledged_writes() -> Result<()> {
+    if let Ok(dir) = std::env::var(CRASH_DIR_ENV) {
+        return run_writer(PathBuf::from(dir));
+    }
+    let dir = temp_dir("killed");
+    let mut child = Command::new(std::env::current_exe()?)
+        .args([
+            "killed_writer_keeps_acknowledged_writes",
+            "--exact",
+            "--nocapture",
+            "--test-threads=1",
+        ])
+        .env(CRASH_DIR_ENV, &dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let mut acknowledged = Vec::new();
+    for line in BufReader::new(child.stdout.take().unwrap()).lines() {
+        if let Some(batch) = line?.strip_prefix("ack ") {
+            acknowledged.push(batch.to_string());
+            if acknowledged.len() == 40 {
+                break;
+            }
+        }
+    }
+    child.kill()?;
+    child.wait()?;
+    assert_eq!(acknowledged.len(), 40, "writer exited early");
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let client = LocalBraindbClient::open(&small_checkpoints(&dir)).await?;
+        let recovered = ids(&client).await?;
+        for batch in &acknowledged {
+            let ids = expected(&[(batch.as_str(), 5)]);
+            assert!(ids.is_subset(&recovered), "batch {batch} was lost");
+        }
+        assert_eq!(recovered.len() % 5, 0, "a batch was recovered in part");
+        Ok::<_, anyhow::Error>(())
+    })?;
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+fn small_checkpoints(dir: &Path) -> StorageConfig {
+    StorageConfig {
+        fsync: FsyncPolicy::Always,
+        checkpoint_bytes: 8 * 1024,
+        ..config(dir)
+    }
+}
+
+fn run_writer(dir: PathBuf) -> Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let client = LocalBraindbClient::open(&small_checkpoints(&dir)).await?;
+        for idx in 0.. {
+            let prefix = format!("batch{idx}");
+            client.upsert_documents(batch(&prefix, 5)).await?;
+            println!("ack {prefix}");
+        }
+        Ok(())
+    })
+}